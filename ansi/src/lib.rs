@@ -28,6 +28,9 @@ pub enum AnsiCode {
     ResetColorBackground,
     ResetAll, // reset color, bold, italic, underline, reverse etc.
     ClearScrollbackBuffer,
+    CursorShape(i32),                  // DECSCUSR, 0-6
+    SemanticPrompt(char, Option<i32>), // OSC 133, the mark (A-D) and the exit code of D
+    PrivateMode(i32, bool),            // DECSET/DECRST, e.g. 2004 for bracketed paste
 }
 
 pub struct AnsiWrap {
@@ -43,6 +46,8 @@ enum State {
     Escape,
     Number,
     Code,
+    Osc,
+    OscEscape,
 }
 
 impl Ansi {
@@ -70,7 +75,13 @@ impl Ansi {
     /// | 2K   | clear entire line                               |
     /// | nS   | scroll up                                       |
     /// | nT   | scroll down                                     |
+    /// | n q  | set cursor shape (DECSCUSR, note the space)     |
+    /// | ?nh  | set private mode n (DECSET)                     |
+    /// | ?nl  | reset private mode n (DECRST)                   |
     /// | ---- | ----------------------------------------------- |
+    ///
+    /// Operating system commands (`ESC ] ... BEL` or `ESC ] ... ESC \`) are consumed, only the
+    /// semantic prompt marks `133;A` to `133;D[;exit_code]` are returned.
     pub fn parse(text: &str) -> AnsiWrap {
         if text.is_empty() || !text.starts_with('\x1b') {
             return AnsiWrap {
@@ -83,6 +94,9 @@ impl Ansi {
         let chars = text.chars();
         let mut state = State::Normal;
         let mut numbers: Vec<i32> = Vec::new();
+        let mut intermediate = None;
+        let mut private = false;
+        let mut osc = String::new();
         let color_codes = color::AnsiColor::new();
 
         for c in chars {
@@ -92,6 +106,8 @@ impl Ansi {
                     char_count += 1;
                     state = State::Escape;
                     numbers.clear();
+                    intermediate = None;
+                    private = false;
                     continue;
                 } else {
                     return AnsiWrap { codes, char_count };
@@ -106,6 +122,10 @@ impl Ansi {
                 if c == '[' {
                     state = State::Number;
                     continue;
+                } else if c == ']' {
+                    state = State::Osc;
+                    osc.clear();
+                    continue;
                 } else {
                     state = State::Normal;
                     eprintln!("ansi: unknown escape sequence 7: {}", c);
@@ -113,6 +133,21 @@ impl Ansi {
                 }
             }
 
+            if old_state == State::Osc || old_state == State::OscEscape {
+                if old_state == State::Osc && c == '\x1b' {
+                    state = State::OscEscape;
+                } else if old_state == State::Osc && c != '\x07' {
+                    osc.push(c);
+                } else {
+                    // BEL or ESC \ end the command
+                    if let Some(code) = Ansi::parse_osc(&osc) {
+                        codes.push(code);
+                    }
+                    state = State::Normal;
+                }
+                continue;
+            }
+
             if old_state == State::Number {
                 if c.is_ascii_digit() {
                     let n = numbers.pop().unwrap_or(0);
@@ -121,6 +156,12 @@ impl Ansi {
                 } else if c == ';' || c == ':' {
                     numbers.push(0);
                     continue;
+                } else if c == '?' && numbers.is_empty() && !private {
+                    private = true;
+                    continue;
+                } else if c == ' ' && intermediate.is_none() {
+                    intermediate = Some(c);
+                    continue;
                 } else {
                     state = State::Code;
                 }
@@ -130,6 +171,21 @@ impl Ansi {
                 let number1 = numbers.first().cloned();
                 let number2 = numbers.get(1).cloned();
                 match c {
+                    'q' if intermediate == Some(' ') => {
+                        codes.push(AnsiCode::CursorShape(number1.unwrap_or(0)))
+                    }
+                    _ if intermediate.is_some() => {
+                        eprintln!("ansi: unknown escape sequence 11: ' {}'", c);
+                    }
+                    // several modes can be set at once, e.g. `?1049;2004h`
+                    'h' | 'l' if private => {
+                        for n in &numbers {
+                            codes.push(AnsiCode::PrivateMode(*n, c == 'h'));
+                        }
+                    }
+                    _ if private => {
+                        eprintln!("ansi: unknown escape sequence 12: '?{}'", c);
+                    }
                     'A' => codes.push(AnsiCode::CursorUp(number1.unwrap_or(1))),
                     'B' => codes.push(AnsiCode::CursorDown(number1.unwrap_or(1))),
                     'C' => codes.push(AnsiCode::CursorForward(number1.unwrap_or(1))),
@@ -151,7 +207,10 @@ impl Ansi {
                         } else if number1 == Some(3) {
                             codes.push(AnsiCode::ClearScrollbackBuffer);
                         } else {
-                            eprintln!("ansi: unknown escape sequence: 9{}J", number1.unwrap());
+                            eprintln!(
+                                "ansi: unknown escape sequence: 9{}J",
+                                number1.unwrap_or_default()
+                            );
                         }
                     }
                     'K' => {
@@ -162,7 +221,10 @@ impl Ansi {
                         } else if number1 == Some(2) {
                             codes.push(AnsiCode::ClearEntireLine);
                         } else {
-                            eprintln!("ansi: unknown escape sequence: 10{}K", number1.unwrap());
+                            eprintln!(
+                                "ansi: unknown escape sequence: 10{}K",
+                                number1.unwrap_or_default()
+                            );
                         }
                     }
                     'S' => codes.push(AnsiCode::ScrollScreen(number1.unwrap_or(1))),
@@ -276,6 +338,26 @@ impl Ansi {
         }
         AnsiWrap { codes, char_count }
     }
+
+    fn parse_osc(osc: &str) -> Option<AnsiCode> {
+        let mut fields = osc.split(';');
+        match fields.next() {
+            Some("133") => {}
+            // e.g. window titles, nothing to do for them
+            _ => return None,
+        }
+        let mark = fields.next().and_then(|f| f.chars().next());
+        match mark {
+            Some(mark @ 'A'..='D') => {
+                let exit_code = fields.next().and_then(|f| f.parse().ok());
+                Some(AnsiCode::SemanticPrompt(mark, exit_code))
+            }
+            _ => {
+                eprintln!("ansi: unknown semantic prompt mark: {}", osc);
+                None
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -294,6 +376,49 @@ mod tests {
         assert_eq!(codes.char_count, 7);
     }
 
+    #[test]
+    fn test_cursor_shape() {
+        let codes = Ansi::parse("\x1b[5 qvim");
+        assert_eq!(codes.codes, vec![AnsiCode::CursorShape(5)]);
+        assert_eq!(codes.char_count, 5);
+        let codes = Ansi::parse("\x1b[ q");
+        assert_eq!(codes.codes, vec![AnsiCode::CursorShape(0)]);
+    }
+
+    #[test]
+    fn test_private_mode() {
+        let codes = Ansi::parse("\x1b[?2004hls");
+        assert_eq!(codes.codes, vec![AnsiCode::PrivateMode(2004, true)]);
+        assert_eq!(codes.char_count, 8);
+        let codes = Ansi::parse("\x1b[?1049;2004l");
+        assert_eq!(
+            codes.codes,
+            vec![
+                AnsiCode::PrivateMode(1049, false),
+                AnsiCode::PrivateMode(2004, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_prompt() {
+        let codes = Ansi::parse("\x1b]133;A\x07$ ");
+        assert_eq!(codes.codes, vec![AnsiCode::SemanticPrompt('A', None)]);
+        assert_eq!(codes.char_count, 8);
+        let codes = Ansi::parse("\x1b]133;D;1\x1b\\\x1b]133;A\x07");
+        assert_eq!(
+            codes.codes,
+            vec![
+                AnsiCode::SemanticPrompt('D', Some(1)),
+                AnsiCode::SemanticPrompt('A', None)
+            ]
+        );
+        assert_eq!(codes.char_count, 19);
+        let codes = Ansi::parse("\x1b]0;title\x07ls");
+        assert!(codes.codes.is_empty());
+        assert_eq!(codes.char_count, 10);
+    }
+
     #[test]
     fn test_ansi2() {
         let text = "Hello\x1b[0m";
@@ -1,3 +1,8 @@
+//! rqos wide configuration settings
+//!
+//! The settings files have one setting per line, `KEY VALUE`, see `split_key_value`, and are
+//! read with `Config::parse_file`.
+
 use std::io::{Read, Write};
 
 pub struct Config {
@@ -32,6 +37,14 @@ impl Config {
         Ok(contents)
     }
 
+    /// passes each line of the file to `parse_line`, errors are reported with the line number and
+    /// skipped, a missing file has no lines
+    pub fn parse_file(&self, filename: &str, parse_line: impl FnMut(&str) -> Result<(), String>) {
+        if let Ok(content) = self.get_file(filename) {
+            parse_lines(&content, filename, parse_line);
+        }
+    }
+
     pub fn write_file(&self, filename: &str, content: &str) -> Result<(), std::io::Error> {
         let path = self.get_filename(filename);
         let mut file = std::fs::File::create(path)?;
@@ -52,3 +65,59 @@ impl Config {
         }
     }
 }
+
+/// passes each line of `text` to `parse_line`, errors are reported with `filename` and the line
+/// number and skipped
+pub fn parse_lines(
+    text: &str,
+    filename: &str,
+    mut parse_line: impl FnMut(&str) -> Result<(), String>,
+) {
+    for (i, line) in text.lines().enumerate() {
+        if let Err(e) = parse_line(line) {
+            eprintln!("{}, line {}: {}", filename, i + 1, e);
+        }
+    }
+}
+
+/// the key and the value of a `KEY VALUE` line, trimmed, `None` for empty lines and comments,
+/// which start with `#`
+pub fn split_key_value(line: &str) -> Result<Option<(&str, &str)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((key, value)) => Ok(Some((key, value.trim()))),
+        None => Err(format!("expected KEY VALUE: {}", line)),
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory, unchanged if `HOME` isn't set
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_key_value() {
+        assert_eq!(split_key_value("  # comment"), Ok(None));
+        assert_eq!(split_key_value(""), Ok(None));
+        assert_eq!(split_key_value("size  10x20 "), Ok(Some(("size", "10x20"))));
+        assert_eq!(split_key_value("slot 0 a b"), Ok(Some(("slot", "0 a b"))));
+        assert!(split_key_value("size").is_err());
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = std::env::var("HOME").unwrap_or_default();
+        assert_eq!(expand_home("~/a/b"), format!("{}/a/b", home));
+        assert_eq!(expand_home("/a/~b"), "/a/~b");
+    }
+}
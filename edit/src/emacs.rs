@@ -0,0 +1,429 @@
+//! emacs style editing in insert mode, as in readline: a kill ring, word motions, case changes
+//! and `Ctrl-X` key sequences
+//!
+//! Words are runs of alphanumeric chars, except for `Ctrl-W` which kills up to whitespace.
+
+use sfml::window::Key;
+
+use crate::{Edit, EditAction};
+
+const KILL_RING_SIZE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Last {
+    /// killing text forward, the next forward kill is appended to the same entry
+    KillForward,
+    /// the next backward kill is prepended to the same entry
+    KillBackward,
+    /// the yanked text starts at `start`, `Alt-Y` replaces it with the `idx`th entry of the ring
+    Yank {
+        start: usize,
+        len: usize,
+        idx: usize,
+    },
+}
+
+#[derive(Default)]
+pub(crate) struct EmacsState {
+    /// killed text, the newest entry last
+    kill_ring: Vec<String>,
+    last: Option<Last>,
+    /// `Ctrl-X` was pressed and waits for the next key
+    pub(crate) ctrl_x: bool,
+    /// a `Ctrl-X` sequence that is left to the application
+    ctrl_x_key: Option<Key>,
+}
+
+impl EmacsState {
+    /// a key that isn't a kill or yank ends the run of kills and `Alt-Y`
+    pub(crate) fn end_run(&mut self) {
+        self.last = None;
+    }
+
+    /// add killed text to the ring, `last` is the previous emacs command
+    fn kill(&mut self, last: Option<Last>, text: String, backward: bool) {
+        self.last = last;
+        if text.is_empty() {
+            // nothing was killed, the next kill still continues the same entry
+            return;
+        }
+        let kind = if backward {
+            Last::KillBackward
+        } else {
+            Last::KillForward
+        };
+        match (last, self.kill_ring.last_mut()) {
+            (Some(Last::KillForward), Some(entry)) if !backward => entry.push_str(&text),
+            (Some(Last::KillBackward), Some(entry)) if backward => entry.insert_str(0, &text),
+            _ => {
+                self.kill_ring.push(text);
+                if self.kill_ring.len() > KILL_RING_SIZE {
+                    self.kill_ring.remove(0);
+                }
+            }
+        }
+        self.last = Some(kind);
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+/// the end of the next word, `Alt-F`
+fn word_forward(line: &[char], x: usize) -> usize {
+    let mut i = x.min(line.len());
+    while i < line.len() && !is_word_char(line[i]) {
+        i += 1;
+    }
+    while i < line.len() && is_word_char(line[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// the start of the previous word, `Alt-B`
+fn word_backward(line: &[char], x: usize) -> usize {
+    let mut i = x.min(line.len());
+    while i > 0 && !is_word_char(line[i - 1]) {
+        i -= 1;
+    }
+    while i > 0 && is_word_char(line[i - 1]) {
+        i -= 1;
+    }
+    i
+}
+
+/// the start of the whitespace delimited word before `x`, `Ctrl-W`
+fn big_word_backward(line: &[char], x: usize) -> usize {
+    let mut i = x.min(line.len());
+    while i > 0 && line[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    while i > 0 && !line[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    i
+}
+
+#[derive(Clone, Copy)]
+enum Case {
+    Upper,
+    Lower,
+    Capitalize,
+}
+
+fn change_case(chars: &mut [char], case: Case) {
+    let mut first = true;
+    for c in chars.iter_mut() {
+        let upper = match case {
+            Case::Upper => true,
+            Case::Lower => false,
+            Case::Capitalize => first,
+        };
+        if is_word_char(*c) {
+            first = false;
+        }
+        *c = if upper {
+            c.to_uppercase().next().unwrap_or(*c)
+        } else {
+            c.to_lowercase().next().unwrap_or(*c)
+        };
+    }
+}
+
+impl Edit {
+    /// `Ctrl-X` and the key after it in insert mode, returns false if `code` is neither
+    pub(crate) fn ctrl_x_key(&mut self, code: Key) -> bool {
+        let control = self.control;
+        if self.emacs.ctrl_x {
+            self.emacs.ctrl_x = false;
+            match code {
+                Key::U if control => self.undo(1),
+                _ if control => self.emacs.ctrl_x_key = Some(code),
+                _ => {}
+            }
+        } else if code == Key::X && control && !self.alt {
+            self.emacs.ctrl_x = true;
+        } else {
+            return false;
+        }
+        self.emacs.end_run();
+        true
+    }
+
+    /// run an emacs command in insert mode
+    pub(crate) fn emacs_action(&mut self, action: EditAction) {
+        // a kill continues the kills right before it, `Alt-Y` replaces the yank before it
+        let last = self.emacs.last.take();
+        match action {
+            EditAction::BeginningOfLine => self.emacs_move(0),
+            EditAction::EndOfLine => self.emacs_move(usize::MAX),
+            EditAction::BackwardChar => self.emacs_move(self.cursor_x().saturating_sub(1)),
+            EditAction::ForwardChar => self.emacs_move(self.cursor_x() + 1),
+            EditAction::BackwardWord => {
+                self.emacs_move(word_backward(&self.line_chars(), self.cursor_x()))
+            }
+            EditAction::ForwardWord => {
+                self.emacs_move(word_forward(&self.line_chars(), self.cursor_x()))
+            }
+            EditAction::KillLine => self.emacs_kill(last, usize::MAX),
+            EditAction::UnixLineDiscard => self.emacs_kill(last, 0),
+            EditAction::UnixWordRubout => {
+                let x = big_word_backward(&self.line_chars(), self.cursor_x());
+                self.emacs_kill(last, x);
+            }
+            EditAction::KillWord => {
+                let x = word_forward(&self.line_chars(), self.cursor_x());
+                self.emacs_kill(last, x);
+            }
+            EditAction::BackwardKillWord => {
+                let x = word_backward(&self.line_chars(), self.cursor_x());
+                self.emacs_kill(last, x);
+            }
+            EditAction::Yank => self.emacs_yank(None),
+            EditAction::YankPop => {
+                if let Some(Last::Yank { start, len, idx }) = last {
+                    self.emacs_yank(Some((start, len, idx)));
+                }
+            }
+            EditAction::TransposeChars => self.with_undo(Self::emacs_transpose),
+            EditAction::UpcaseWord => self.emacs_case(Case::Upper),
+            EditAction::DowncaseWord => self.emacs_case(Case::Lower),
+            EditAction::CapitalizeWord => self.emacs_case(Case::Capitalize),
+            // not emacs commands, see `Edit::run_action`
+            EditAction::BackwardDeleteChar | EditAction::ViMovementMode => {}
+        }
+    }
+
+    /// a `Ctrl-X Ctrl-<key>` sequence the editor doesn't handle, e.g. `Ctrl-X Ctrl-E`
+    pub fn take_ctrl_x(&mut self) -> Option<Key> {
+        self.emacs.ctrl_x_key.take()
+    }
+
+    /// move the cursor on the line, this ends the insert run like the arrow keys in vi
+    fn emacs_move(&mut self, x: usize) {
+        self.finish_insert_run();
+        self.set_cursor_x(x.min(self.line_chars().len()));
+        self.text.redraw = true;
+    }
+
+    /// kill the text between the cursor and `x`
+    fn emacs_kill(&mut self, last: Option<Last>, x: usize) {
+        let mut line = self.line_chars();
+        let cursor = self.cursor_x().min(line.len());
+        let x = x.min(line.len());
+        let (start, end) = (cursor.min(x), cursor.max(x));
+        self.emacs
+            .kill(last, line[start..end].iter().collect(), x < cursor);
+        self.with_undo(|edit| {
+            line.drain(start..end);
+            edit.set_line_chars(&line);
+            edit.set_cursor_x(start);
+            edit.text.redraw = true;
+        });
+    }
+
+    /// `Ctrl-Y` inserts the newest kill, `Alt-Y` right after it replaces the yanked text with
+    /// the next older one
+    fn emacs_yank(&mut self, previous: Option<(usize, usize, usize)>) {
+        let ring_len = self.emacs.kill_ring.len();
+        if ring_len == 0 {
+            return;
+        }
+        let idx = match previous {
+            Some((_, _, idx)) => (idx + ring_len - 1) % ring_len,
+            None => ring_len - 1,
+        };
+        let text: Vec<char> = self.emacs.kill_ring[idx].chars().collect();
+        let mut line = self.line_chars();
+        let start = match previous {
+            Some((start, len, _)) => {
+                line.drain(start..(start + len).min(line.len()));
+                start
+            }
+            None => self.cursor_x().min(line.len()),
+        };
+        self.with_undo(|edit| {
+            line.splice(start..start, text.iter().copied());
+            edit.set_line_chars(&line);
+            edit.set_cursor_x(start + text.len());
+            edit.text.redraw = true;
+        });
+        self.emacs.last = Some(Last::Yank {
+            start,
+            len: text.len(),
+            idx,
+        });
+    }
+
+    /// `Ctrl-T` swaps the chars before and at the cursor, at the end of the line the last two
+    fn emacs_transpose(&mut self) {
+        let mut line = self.line_chars();
+        let x = self.cursor_x().min(line.len());
+        if x == 0 || line.len() < 2 {
+            return;
+        }
+        let x = if x == line.len() { x - 1 } else { x };
+        line.swap(x - 1, x);
+        self.set_line_chars(&line);
+        self.set_cursor_x(x + 1);
+        self.text.redraw = true;
+    }
+
+    /// `Alt-U`, `Alt-L` and `Alt-C` change the case up to the end of the word
+    fn emacs_case(&mut self, case: Case) {
+        let mut line = self.line_chars();
+        let x = self.cursor_x().min(line.len());
+        let end = word_forward(&line, x);
+        self.with_undo(|edit| {
+            change_case(&mut line[x..end], case);
+            edit.set_line_chars(&line);
+            edit.set_cursor_x(end);
+            edit.text.redraw = true;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EditBuilder, Mode};
+
+    /// press `keys` in insert mode, `|` in `before` and `after` marks the cursor
+    /// `C-` before a key holds control, `M-` holds alt
+    fn run(before: &str, keys: &[&str]) -> String {
+        let mut edit = EditBuilder::new().build();
+        let x = before.find('|').unwrap();
+        edit.replace(vec![before.replace('|', "")]);
+        edit.set_cursor_x(before[..x].chars().count());
+        for key in keys {
+            let (control, alt, name) = match key.split_at(2.min(key.len())) {
+                ("C-", name) => (true, false, name),
+                ("M-", name) => (false, true, name),
+                _ => (false, false, *key),
+            };
+            let code = match name {
+                "Left" => Key::Left,
+                "Right" => Key::Right,
+                "Backspace" => Key::Backspace,
+                "Home" => Key::Home,
+                "End" => Key::End,
+                "a" => Key::A,
+                "b" => Key::B,
+                "c" => Key::C,
+                "d" => Key::D,
+                "e" => Key::E,
+                "f" => Key::F,
+                "k" => Key::K,
+                "l" => Key::L,
+                "t" => Key::T,
+                "u" => Key::U,
+                "w" => Key::W,
+                "x" => Key::X,
+                "y" => Key::Y,
+                _ => panic!("unknown key {}", name),
+            };
+            edit.control = control;
+            edit.alt = alt;
+            edit.key_pressed(code);
+        }
+        let mut line = edit.get_text().join("\n");
+        let idx = line
+            .char_indices()
+            .nth(edit.get_cursor().x as usize)
+            .map_or(line.len(), |(i, _)| i);
+        line.insert(idx, '|');
+        line
+    }
+
+    #[test]
+    fn test_emacs() {
+        #[rustfmt::skip]
+        let table: &[(&str, &[&str], &str)] = &[
+            // motions
+            ("foo bar|", &["C-a"], "|foo bar"),
+            ("|foo bar", &["C-e"], "foo bar|"),
+            ("|foo bar", &["Home", "End"], "foo bar|"),
+            ("|foo", &["C-b", "C-f", "Right"], "fo|o"),
+            ("fo|o", &["Left", "Left", "Left"], "|foo"),
+            ("|foo.bar baz", &["M-f"], "foo|.bar baz"),
+            ("|foo.bar baz", &["M-f", "M-f"], "foo.bar| baz"),
+            ("foo.bar baz|", &["M-b", "M-b"], "foo.|bar baz"),
+            ("|foo bar", &["C-Right", "C-Right", "C-Left"], "foo |bar"),
+            // kills and yanks
+            ("foo |bar", &["C-k", "C-a", "C-y"], "bar|foo "),
+            ("foo |bar", &["C-u", "C-e", "C-y"], "barfoo |"),
+            ("ls -l /tmp|", &["C-w", "C-w", "C-y"], "ls -l /tmp|"),
+            ("ls -l /tmp|", &["C-w", "C-b", "C-w", "C-e", "C-y"], "ls  -l|"),
+            ("|foo.bar baz", &["M-d", "M-d", "C-y"], "foo.bar| baz"),
+            ("foo.bar baz|", &["M-Backspace"], "foo.bar |"),
+            ("a b c|", &["M-Backspace", "C-b", "M-Backspace", "C-y", "M-y"], "a c| "),
+            ("a b c|", &["M-Backspace", "C-b", "M-Backspace", "C-y", "M-y", "M-y"], "a b| "),
+            ("abc|", &["C-y"], "abc|"),
+            ("abc|", &["M-y"], "abc|"),
+            // transpose and case
+            ("ab|cd", &["C-t"], "acb|d"),
+            ("abcd|", &["C-t"], "abdc|"),
+            ("|abcd", &["C-t"], "|abcd"),
+            ("|foo bar", &["M-u"], "FOO| bar"),
+            ("|FOO BAR", &["M-l", "M-l"], "foo bar|"),
+            ("|fOO bAR", &["M-c", "M-c"], "Foo Bar|"),
+            // undo
+            ("foo |bar", &["C-k", "C-a", "M-u", "C-x", "C-u"], "|foo "),
+            ("foo bar|", &["C-w", "C-x", "C-u", "C-x", "C-u"], "foo bar|"),
+        ];
+        let failures: Vec<String> = table
+            .iter()
+            .filter(|(before, keys, after)| run(before, keys) != *after)
+            .map(|(before, keys, after)| {
+                format!(
+                    "{:?} with {:?}: expected {:?}, got {:?}",
+                    before,
+                    keys,
+                    after,
+                    run(before, keys)
+                )
+            })
+            .collect();
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_ctrl_x() {
+        let mut edit = EditBuilder::new().build();
+        edit.control = true;
+        edit.key_pressed(Key::X);
+        edit.key_pressed(Key::E);
+        assert_eq!(edit.take_ctrl_x(), Some(Key::E));
+        assert_eq!(edit.take_ctrl_x(), None);
+        // Ctrl-E without Ctrl-X moves the cursor
+        edit.key_pressed(Key::E);
+        assert_eq!(edit.take_ctrl_x(), None);
+    }
+
+    #[test]
+    fn test_run_action() {
+        let mut edit = EditBuilder::new().default_keys(false).build();
+        edit.write("foo bar");
+        // without default keys Ctrl-W is left to the application
+        edit.control = true;
+        edit.key_pressed(Key::W);
+        assert_eq!(edit.get_text(), ["foo bar"]);
+        assert!(edit.run_action(EditAction::UnixWordRubout));
+        assert!(edit.run_action(EditAction::BeginningOfLine));
+        assert!(edit.run_action(EditAction::Yank));
+        assert_eq!(edit.get_text(), ["barfoo "]);
+        // Ctrl-X sequences still work
+        edit.key_pressed(Key::X);
+        edit.key_pressed(Key::U);
+        assert_eq!(edit.get_text(), ["foo "]);
+        // in normal mode the Ctrl commands go back to insert mode
+        edit.set_mode(Mode::Normal);
+        assert!(!edit.run_action(EditAction::Yank));
+        assert!(edit.run_action(EditAction::EndOfLine));
+        assert!(edit.mode == Mode::Insert);
+        edit.set_mode(Mode::Visual);
+        assert!(!edit.run_action(EditAction::EndOfLine));
+    }
+}
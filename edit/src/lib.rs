@@ -1,23 +1,90 @@
 use sfml::graphics::{Color, RenderWindow};
 use sfml::system::Vector2i;
-use sfml::window::Key;
+use sfml::window::{clipboard, Key};
 
 use text::Text;
 
-// Definitions in: sfml/rust-sfml/src/ffi/window.rs
-const KEYMAP_NOSHIFT: &str = "abcdefghijklmnopqrstuvwxyz01234567890.........[];,.'/\\`=- ";
-const KEYMAP_SHIFT: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ)!@#$%^&*(..........{}:<>\"?|~+_ ";
-// LBracket, RBracket, Semicolon, Comma, Period, Quote, Slash, Backslash, Tilde, Equal, Hyphen, Space,
+mod emacs;
+mod mask;
+mod undo;
+mod vi;
+mod visual;
+
+/// the cursor of insert mode unless `EditBuilder::cursor_styles` sets another
+pub const INSERT_CURSOR_STYLE: text::CursorStyle =
+    text::CursorStyle::new(text::CursorShape::Bar, true);
+/// the cursor of normal and the visual modes unless `EditBuilder::cursor_styles` sets another
+pub const NORMAL_CURSOR_STYLE: text::CursorStyle =
+    text::CursorStyle::new(text::CursorShape::Block, false);
+
+/// returns the colors of the lines, see `Edit::set_highlighter`
+pub type Highlighter = Box<dyn Fn(&[String]) -> Vec<text::Highlight>>;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Mode {
     Normal,
     Insert,
+    /// `v`, a selection of chars
+    Visual,
+    /// `V`, a selection of whole lines
+    VisualLine,
+}
+
+impl Mode {
+    pub fn is_visual(&self) -> bool {
+        matches!(self, Mode::Visual | Mode::VisualLine)
+    }
+}
+
+/// the commands of the Ctrl and Alt keys, named after their readline functions, see
+/// `Edit::run_action` and `EditBuilder::default_keys`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditAction {
+    /// `Ctrl-A`, `Home`
+    BeginningOfLine,
+    /// `Ctrl-E`, `End`
+    EndOfLine,
+    /// `Ctrl-B`, `Left`
+    BackwardChar,
+    /// `Ctrl-F`, `Right`
+    ForwardChar,
+    /// `Alt-B`, `Ctrl-Left`
+    BackwardWord,
+    /// `Alt-F`, `Ctrl-Right`
+    ForwardWord,
+    /// `Ctrl-H`
+    BackwardDeleteChar,
+    /// `Ctrl-K`
+    KillLine,
+    /// `Ctrl-U`
+    UnixLineDiscard,
+    /// `Ctrl-W`
+    UnixWordRubout,
+    /// `Alt-D`
+    KillWord,
+    /// `Alt-Backspace`
+    BackwardKillWord,
+    /// `Ctrl-Y`
+    Yank,
+    /// `Alt-Y`
+    YankPop,
+    /// `Ctrl-T`
+    TransposeChars,
+    /// `Alt-U`
+    UpcaseWord,
+    /// `Alt-L`
+    DowncaseWord,
+    /// `Alt-C`
+    CapitalizeWord,
+    /// `Ctrl-[`, `Ctrl-J`
+    ViMovementMode,
 }
 
 pub struct EditBuilder {
     cursor_colors: Option<(Color, Color)>,
     cursor_state: Option<text::CursorState>,
+    cursor_styles: Option<(text::CursorStyle, text::CursorStyle)>,
+    default_keys: bool,
 }
 
 impl EditBuilder {
@@ -25,9 +92,18 @@ impl EditBuilder {
         Self {
             cursor_colors: None,
             cursor_state: None,
+            cursor_styles: None,
+            default_keys: true,
         }
     }
 
+    /// bind the `EditAction`s to their keys, off when the application looks up its own bindings
+    /// and calls `Edit::run_action`
+    pub fn default_keys(mut self, on: bool) -> Self {
+        self.default_keys = on;
+        self
+    }
+
     pub fn cursor_colors(mut self, insert: Color, normal: Color) -> Self {
         self.cursor_colors = Some((insert, normal));
         self
@@ -38,12 +114,33 @@ impl EditBuilder {
         self
     }
 
+    /// cursor shapes for insert and normal mode, `INSERT_CURSOR_STYLE` and `NORMAL_CURSOR_STYLE`
+    /// by default
+    pub fn cursor_styles(mut self, insert: text::CursorStyle, normal: text::CursorStyle) -> Self {
+        self.cursor_styles = Some((insert, normal));
+        self
+    }
+
     pub fn build(&self) -> Edit {
+        let (insert_cursor_style, normal_cursor_style) = self
+            .cursor_styles
+            .unwrap_or((INSERT_CURSOR_STYLE, NORMAL_CURSOR_STYLE));
         let mut edit = Edit {
             text: Text::new(),
             shift: false,
             control: false,
             mode: Mode::Insert,
+            insert_cursor_style,
+            normal_cursor_style,
+            vi: vi::ViState::default(),
+            history: undo::History::default(),
+            emacs: emacs::EmacsState::default(),
+            alt: false,
+            command_key: false,
+            highlighter: None,
+            highlighted: None,
+            masked: None,
+            default_keys: self.default_keys,
         };
         if let Some((insert, normal)) = self.cursor_colors {
             edit.cursor_colors(insert, normal);
@@ -54,6 +151,10 @@ impl EditBuilder {
             edit.set_cursor_state(text::CursorState::InsertActive);
         }
         edit.text.insert_mode = text::InsertMode::Insert;
+        // keep the last lines of a long command visible
+        edit.text
+            .vertical_alignment(text::VerticalAlignment::BottomOnOverflow);
+        edit.text.cursor_style(insert_cursor_style);
         edit
     }
 }
@@ -68,7 +169,22 @@ pub struct Edit {
     text: Text<'static>,
     pub shift: bool,
     pub control: bool,
+    pub alt: bool,
+    /// the last key press was a command, the text it produces is not typed
+    command_key: bool,
     pub mode: Mode,
+    insert_cursor_style: text::CursorStyle,
+    normal_cursor_style: text::CursorStyle,
+    vi: vi::ViState,
+    history: undo::History,
+    emacs: emacs::EmacsState,
+    highlighter: Option<Highlighter>,
+    // the lines the highlights were made for
+    highlighted: Option<Vec<String>>,
+    /// the text of masked input, see `set_masked`
+    masked: Option<String>,
+    /// the keys of the `EditAction`s are handled, see `EditBuilder::default_keys`
+    default_keys: bool,
 }
 
 impl Edit {
@@ -90,6 +206,25 @@ impl Edit {
         self
     }
 
+    pub fn set_cursor_styles(&mut self, insert: text::CursorStyle, normal: text::CursorStyle) {
+        self.insert_cursor_style = insert;
+        self.normal_cursor_style = normal;
+        self.set_mode(self.mode);
+    }
+
+    /// override the cursor style of the current mode until the mode changes
+    pub fn set_cursor_style(&mut self, cursor_style: text::CursorStyle) {
+        self.text.set_cursor_style(cursor_style);
+    }
+
+    pub fn get_cursor_style(&self) -> text::CursorStyle {
+        self.text.get_cursor_style()
+    }
+
+    pub fn blink_cursor(&mut self, on: bool) {
+        self.text.blink_cursor(on);
+    }
+
     pub fn set_background_color(&mut self, color: Color) {
         self.text.set_background_color(color);
     }
@@ -102,7 +237,52 @@ impl Edit {
         self.text.write(text);
     }
 
+    /// text typed in insert mode, it is repeated with `.` together with the change that
+    /// entered insert mode
+    fn insert_str(&mut self, text: &str) {
+        self.start_insert_run();
+        self.write(text);
+        self.vi.record_insert(text);
+    }
+
+    /// insert pasted text at the cursor, it is one undo step
+    pub fn paste(&mut self, text: &str) {
+        if self.masked_text_entered(text) {
+            return;
+        }
+        let text = paste_text(text).replace('\t', " ");
+        if text.is_empty() {
+            return;
+        }
+        if self.mode.is_visual() {
+            self.set_mode(Mode::Normal);
+        }
+        self.finish_insert_run();
+        let before = self.snapshot();
+        self.write(&text);
+        self.vi_clamp_cursor();
+        self.commit_undo(before);
+    }
+
+    pub fn paste_clipboard(&mut self) {
+        self.paste(&clipboard::get_string());
+    }
+
+    /// copy the visual selection, or the whole buffer with the lines joined with `\n`
+    pub fn copy_clipboard(&self) {
+        let text = match self.visual_text() {
+            Some((text, _)) => text,
+            None => self.get_text().join("\n"),
+        };
+        clipboard::set_string(&text);
+    }
+
+    /// replace the whole buffer, this also clears the undo history
     pub fn replace(&mut self, text: Vec<String>) -> Vec<String> {
+        if let Some(masked) = &mut self.masked {
+            masked.clear();
+        }
+        self.clear_history();
         self.text.replace(text)
     }
 
@@ -111,102 +291,318 @@ impl Edit {
     }
 
     pub fn draw(&mut self, window: &mut RenderWindow, font: &font::Font) {
+        self.update_highlights();
         self.text.draw(window, font);
     }
 
+    /// color the text with `highlighter`, `get_text` still returns it unchanged
+    pub fn set_highlighter(&mut self, highlighter: Option<Highlighter>) {
+        self.highlighter = highlighter;
+        self.highlighted = None;
+        if self.highlighter.is_none() {
+            self.text.set_highlights(vec![]);
+        }
+    }
+
+    /// the highlighter is only called when the text changed since the last draw
+    fn update_highlights(&mut self) {
+        let Some(highlighter) = &self.highlighter else {
+            return;
+        };
+        if self.highlighted.as_deref() == Some(self.text.lines()) {
+            return;
+        }
+        let highlights = highlighter(self.text.lines());
+        self.text.set_highlights(highlights);
+        self.highlighted = Some(self.text.get_text());
+    }
+
     pub fn must_draw(&self) -> bool {
         self.text.must_draw()
     }
 
-    pub fn set_position_size(&mut self, position: Vector2i, size: Vector2i) {
-        self.text.set_position_size(position, size);
+    /// draw again on the next frame, like after glyphs were added to the font
+    pub fn redraw(&mut self) {
+        self.text.redraw = true;
+    }
+
+    pub fn set_position_size(&mut self, position: Vector2i, size: Vector2i, char_size: Vector2i) {
+        self.text.set_position_size(position, size, char_size);
     }
 
     pub fn get_cursor(&self) -> Vector2i {
         self.text.cursor_position
     }
 
+    pub fn line_count(&self) -> usize {
+        self.text.lines().len()
+    }
+
+    /// split the line at the cursor, like typing Enter in an editor
+    pub fn insert_newline(&mut self) {
+        self.insert_str("\n");
+    }
+
+    /// move the cursor to `position`, it is kept inside the text
+    pub fn set_cursor(&mut self, position: Vector2i) {
+        self.finish_insert_run();
+        let y = position.y.clamp(0, self.text.lines().len() as i32 - 1);
+        self.text.cursor_position.y = y;
+        self.set_cursor_x(position.x.max(0) as usize);
+        let len = self.line_chars().len();
+        self.set_cursor_x(self.cursor_x().min(len));
+        self.vi_clamp_cursor();
+        self.text.redraw = true;
+    }
+
+    /// the first line shown if `rows` lines fit in the window
+    pub fn first_visible_line(&self, rows: usize) -> usize {
+        let end = (self.text.lines().len() as i32 + self.text.scroll_pos_y).max(0) as usize;
+        end.saturating_sub(rows)
+    }
+
+    /// scroll as little as possible to show the line of the cursor, `rows` lines fit in the
+    /// window
+    pub fn scroll_to_cursor(&mut self, rows: usize) {
+        let rows = rows.max(1);
+        let len = self.text.lines().len();
+        let y = self.text.cursor_position.y as usize;
+        let first = self.first_visible_line(rows);
+        let first = if y < first {
+            y
+        } else if y >= first + rows {
+            y + 1 - rows
+        } else {
+            first
+        };
+        // the text is aligned to the bottom, the lines after the window are scrolled away
+        let scroll_pos_y = (first + rows).min(len) as i32 - len as i32;
+        if scroll_pos_y != self.text.scroll_pos_y {
+            self.text.scroll_pos_y = scroll_pos_y;
+            self.text.redraw = true;
+        }
+    }
+
+    /// move the cursor to the line `delta` lines away, returns false if there is no such line
+    pub fn move_cursor_line(&mut self, delta: i32) -> bool {
+        let y = self.text.cursor_position.y + delta;
+        if y < 0 || y as usize >= self.text.lines().len() {
+            return false;
+        }
+        // like the arrow keys in vi, this ends the insert run
+        self.finish_insert_run();
+        self.text.cursor_position.y = y;
+        let len = self.line_chars().len();
+        self.set_cursor_x(self.cursor_x().min(len));
+        self.vi_clamp_cursor();
+        self.text.redraw = true;
+        true
+    }
+
+    /// the line of the cursor as chars
+    fn line_chars(&self) -> Vec<char> {
+        self.text.lines()[self.text.cursor_position.y as usize]
+            .chars()
+            .collect()
+    }
+
+    fn set_line_chars(&mut self, line: &[char]) {
+        let y = self.text.cursor_position.y as usize;
+        self.text.lines_mut()[y] = line.iter().collect();
+    }
+
+    fn cursor_x(&self) -> usize {
+        self.text.cursor_position.x.max(0) as usize
+    }
+
+    fn set_cursor_x(&mut self, x: usize) {
+        self.text.cursor_position.x = x as i32;
+    }
+
     pub fn set_mode(&mut self, mode: Mode) {
+        if self.mode == Mode::Insert && mode == Mode::Normal {
+            // like vi, the cursor moves onto the last char typed
+            self.finish_insert_run();
+            self.vi.finish_insert();
+            self.text.cursor_position.x = (self.text.cursor_position.x - 1).max(0);
+            self.text.redraw = true;
+        }
+        if self.mode.is_visual() && !mode.is_visual() {
+            self.text.selection_clear();
+        }
+        self.vi.clear_pending();
         self.mode = mode;
+        let insert = mode == Mode::Insert;
         let old_state = self.text.get_cursor_state();
-        let new_state = match (old_state, mode) {
-            (text::CursorState::InsertActive, Mode::Normal) => text::CursorState::NormalActive,
-            (text::CursorState::InsertInactive, Mode::Normal) => text::CursorState::NormalInactive,
-            (text::CursorState::NormalActive, Mode::Insert) => text::CursorState::InsertActive,
-            (text::CursorState::NormalInactive, Mode::Insert) => text::CursorState::InsertInactive,
+        let new_state = match (old_state, insert) {
+            (text::CursorState::InsertActive, false) => text::CursorState::NormalActive,
+            (text::CursorState::InsertInactive, false) => text::CursorState::NormalInactive,
+            (text::CursorState::NormalActive, true) => text::CursorState::InsertActive,
+            (text::CursorState::NormalInactive, true) => text::CursorState::InsertInactive,
             _ => old_state,
         };
         self.text.set_cursor_state(new_state);
+        self.text.set_cursor_style(match insert {
+            true => self.insert_cursor_style,
+            false => self.normal_cursor_style,
+        });
     }
 
+    /// keys that are commands, typed text arrives with `text_entered`
     pub fn key_pressed(&mut self, code: Key) {
-        // Note: I know sf::Event::TextEntered exists, but so does xterm.
-        let ucode = code as usize;
-        let start_alphanum = Key::A as usize;
-        let end_alphanum = Key::Num9 as usize;
-        let start_special = Key::LBracket as usize;
-        let end_special = Key::Space as usize;
-
-        if (ucode >= start_alphanum && ucode <= end_alphanum)
-            || (ucode >= start_special && ucode <= end_special)
-        {
-            let mode = self.mode;
-            const S_DN: bool = true;
-            const S_UP: bool = false;
-            const C_DN: bool = true;
-            const C_UP: bool = false;
-            match (mode, self.shift, self.control) {
-                (Mode::Insert, S_UP, C_UP) => self.write(&KEYMAP_NOSHIFT[ucode..ucode + 1]),
-                (Mode::Insert, S_DN, C_UP) => self.write(&KEYMAP_SHIFT[ucode..ucode + 1]),
-                (Mode::Insert, S_UP, C_DN) => match code {
-                    Key::H => self.backspace(),
-                    Key::W => self.delete_word(),
-                    Key::U => self.text.clear_to_here(),
-                    Key::K => self.text.clear_from_here(),
-                    Key::A => self.text.move_cursor_horz(-2),
-                    Key::B => self.text.move_cursor_horz(-1),
-                    Key::E => self.text.move_cursor_horz(2),
-                    Key::F => self.text.move_cursor_horz(1),
-                    Key::LBracket | Key::J => {
-                        self.set_mode(Mode::Normal);
-                    }
-                    _ => {}
-                },
-                (Mode::Insert, S_DN, C_DN) => {}
-                // normal mode
-                (Mode::Normal, S_UP, C_UP) => {
-                    if ucode == Key::I as usize || ucode == Key::A as usize {
-                        self.set_mode(Mode::Insert);
-                    }
-                }
-                (Mode::Normal, S_DN, C_UP) => {}
-                (Mode::Normal, S_UP, C_DN) => {
-                    let mut found_key = true;
-                    match code {
-                        Key::H => self.backspace(),
-                        Key::W => self.delete_word(),
-                        Key::U => self.text.clear_to_here(),
-                        Key::K => self.text.clear_from_here(),
-                        Key::A => self.text.move_cursor_horz(-2),
-                        Key::B => self.text.move_cursor_horz(-1),
-                        Key::E => self.text.move_cursor_horz(2),
-                        Key::F => self.text.move_cursor_horz(1),
-                        _ => found_key = false,
-                    }
-                    if found_key {
-                        self.set_mode(Mode::Insert);
-                    }
+        match code {
+            Key::LShift | Key::RShift => return self.shift_pressed(true),
+            Key::LControl | Key::RControl => return self.control_pressed(true),
+            Key::LAlt | Key::RAlt => return self.alt_pressed(true),
+            _ => {}
+        }
+        if self.masked_key_pressed(code) {
+            return;
+        }
+        self.command_key = true;
+        if self.mode == Mode::Insert && self.ctrl_x_key(code) {
+            return;
+        }
+        if let Some(action) = self.default_action(code) {
+            if self.run_action(action) {
+                return;
+            }
+        }
+        // any other key ends a run of kills and `Alt-Y` after a yank
+        self.emacs.end_run();
+        match code {
+            // like `h`
+            Key::Backspace if self.mode.is_visual() => self.visual_key('h'),
+            Key::Backspace => {
+                if self.mode == Mode::Insert {
+                    self.start_insert_run();
+                    self.backspace();
+                } else {
+                    self.with_undo(Self::backspace);
                 }
-                (Mode::Normal, S_DN, C_DN) => {}
+                self.vi.record_backspace();
             }
-        } else {
-            match code {
-                Key::LShift | Key::RShift => self.shift_pressed(true),
-                Key::LControl | Key::RControl => self.control_pressed(true),
-                Key::Backspace => self.backspace(),
-                Key::Escape => self.set_mode(Mode::Normal),
-                _ => {}
+            Key::Escape => self.set_mode(Mode::Normal),
+            Key::Insert if self.shift && !self.control => self.paste_clipboard(),
+            _ if self.control => self.command_key = self.control_key_pressed(code),
+            // AltGr is reported as Alt on some systems, so unhandled Alt keys still type text
+            _ => self.command_key = false,
+        }
+    }
+
+    /// the `EditAction` of `code` with the current modifiers, if default keys are on
+    fn default_action(&self, code: Key) -> Option<EditAction> {
+        use EditAction::*;
+        if !self.default_keys {
+            return None;
+        }
+        let (control, shift, alt) = (self.control, self.shift, self.alt);
+        let action = match (self.mode, code, control, alt) {
+            (Mode::Normal, _, true, _) if !shift => match code {
+                Key::H => BackwardDeleteChar,
+                Key::W => UnixWordRubout,
+                Key::U => UnixLineDiscard,
+                Key::K => KillLine,
+                Key::A => BeginningOfLine,
+                Key::B => BackwardChar,
+                Key::E => EndOfLine,
+                Key::F => ForwardChar,
+                _ => return None,
+            },
+            (Mode::Normal | Mode::Visual | Mode::VisualLine, ..) => return None,
+            (_, Key::A, true, false) | (_, Key::Home, _, false) => BeginningOfLine,
+            (_, Key::E, true, false) | (_, Key::End, _, false) => EndOfLine,
+            (_, Key::B, true, false) | (_, Key::Left, false, false) => BackwardChar,
+            (_, Key::F, true, false) | (_, Key::Right, false, false) => ForwardChar,
+            (_, Key::B, false, true) | (_, Key::Left, true, _) => BackwardWord,
+            (_, Key::F, false, true) | (_, Key::Right, true, _) => ForwardWord,
+            (_, Key::K, true, false) => KillLine,
+            (_, Key::U, true, false) => UnixLineDiscard,
+            (_, Key::W, true, false) => UnixWordRubout,
+            (_, Key::D, false, true) => KillWord,
+            (_, Key::Backspace, false, true) => BackwardKillWord,
+            (_, Key::Y, true, false) => Yank,
+            (_, Key::Y, false, true) => YankPop,
+            (_, Key::T, true, false) => TransposeChars,
+            (_, Key::U, false, true) => UpcaseWord,
+            (_, Key::L, false, true) => DowncaseWord,
+            (_, Key::C, false, true) => CapitalizeWord,
+            (_, Key::H, true, _) if !shift => BackwardDeleteChar,
+            (_, Key::LBracket | Key::J, true, _) if !shift => ViMovementMode,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// run `action` as its key does, the emacs commands in insert mode, in normal mode the
+    /// motions and deletions of `Ctrl-H`, `Ctrl-W`, `Ctrl-U`, `Ctrl-K`, `Ctrl-A`, `Ctrl-B`,
+    /// `Ctrl-E` and `Ctrl-F` are followed by insert mode, returns false if `action` doesn't
+    /// apply in the current mode
+    pub fn run_action(&mut self, action: EditAction) -> bool {
+        use EditAction::*;
+        match (self.mode, action) {
+            (Mode::Insert, ViMovementMode) => self.set_mode(Mode::Normal),
+            (Mode::Insert, BackwardDeleteChar) => {
+                self.emacs.end_run();
+                self.start_insert_run();
+                self.backspace();
             }
+            (Mode::Insert, _) => self.emacs_action(action),
+            (Mode::Normal, BackwardDeleteChar | UnixWordRubout | UnixLineDiscard | KillLine)
+            | (Mode::Normal, BeginningOfLine | BackwardChar | EndOfLine | ForwardChar) => {
+                let before = self.snapshot();
+                match action {
+                    BackwardDeleteChar => self.backspace(),
+                    UnixWordRubout => self.delete_word(),
+                    UnixLineDiscard => self.text.clear_to_here(),
+                    KillLine => self.text.clear_from_here(),
+                    BeginningOfLine => self.text.move_cursor_horz(-2),
+                    BackwardChar => self.text.move_cursor_horz(-1),
+                    EndOfLine => self.text.move_cursor_horz(2),
+                    _ => self.text.move_cursor_horz(1),
+                }
+                self.set_mode(Mode::Insert);
+                // the change and the text typed after it are one undo step
+                self.start_insert_run_from(before);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// returns false if `code` isn't a command
+    fn control_key_pressed(&mut self, code: Key) -> bool {
+        const S_DN: bool = true;
+        const S_UP: bool = false;
+        match (self.mode, self.shift, code) {
+            // Ctrl-_
+            (Mode::Insert, S_DN, Key::Hyphen) => self.undo(1),
+            (_, S_DN, Key::C) => self.copy_clipboard(),
+            (_, S_DN, Key::V) => self.paste_clipboard(),
+            // redo, as a vi key so it takes a count
+            (Mode::Normal, S_UP, Key::R) => self.vi_key('\x12'),
+            _ => return false,
+        }
+        true
+    }
+
+    /// an unfinished vi command or `Ctrl-X` sequence waits for more keys
+    pub fn has_pending_keys(&self) -> bool {
+        self.vi.has_pending() || self.emacs.ctrl_x
+    }
+
+    /// text typed on the keyboard, after the layout, dead keys and compose are applied
+    /// text from a key press that was a command, e.g. `Alt-F`, is ignored
+    pub fn text_entered(&mut self, c: char) {
+        // only Alt and Ctrl commands produce printable text
+        let command_key = std::mem::take(&mut self.command_key) && (self.alt || self.control);
+        if command_key || c.is_control() || self.masked_text_entered(&c.to_string()) {
+            return;
+        }
+        match self.mode {
+            Mode::Insert => self.insert_str(c.encode_utf8(&mut [0; 4])),
+            Mode::Normal => self.vi_key(c),
+            Mode::Visual | Mode::VisualLine => self.visual_key(c),
         }
     }
 
@@ -214,15 +610,14 @@ impl Edit {
         match code {
             Key::LShift | Key::RShift => self.shift_pressed(false),
             Key::LControl | Key::RControl => self.control_pressed(false),
+            Key::LAlt | Key::RAlt => self.alt_pressed(false),
             _ => {}
         }
     }
 
     fn delete_word(&mut self) {
-        let text = &mut self.text.text;
-        let cursor_position = &mut self.text.cursor_position;
-        let line = &mut text[cursor_position.y as usize].chars().collect::<Vec<_>>();
-        let idx = cursor_position.x as usize;
+        let mut line = self.line_chars();
+        let idx = self.cursor_x().min(line.len());
         let mut i = idx;
         loop {
             if i == 0 || !line[i - 1].is_whitespace() {
@@ -238,25 +633,26 @@ impl Edit {
         }
 
         line.drain(i..idx);
-        text[cursor_position.y as usize] = line.iter().collect();
-        cursor_position.x = i as i32;
+        self.set_line_chars(&line);
+        self.set_cursor_x(i);
         self.text.redraw = true;
     }
 
     fn backspace(&mut self) {
         self.text.redraw = true;
-        let text = &mut self.text.text;
-        let cursor_position = &mut self.text.cursor_position;
-        if cursor_position.x > 0 {
-            let line = &mut text[cursor_position.y as usize];
-            let idx = cursor_position.x as usize;
-            line.remove(idx - 1);
-            cursor_position.x -= 1;
-        } else if cursor_position.y > 0 {
-            let line = text.remove(cursor_position.y as usize);
-            cursor_position.y -= 1;
-            cursor_position.x = text[cursor_position.y as usize].len() as i32;
-            text[cursor_position.y as usize] += &line;
+        let mut line = self.line_chars();
+        let x = self.cursor_x().min(line.len());
+        if x > 0 {
+            line.remove(x - 1);
+            self.set_line_chars(&line);
+            self.set_cursor_x(x - 1);
+        } else if self.text.cursor_position.y > 0 {
+            let y = self.text.cursor_position.y as usize;
+            let text = self.text.lines_mut();
+            let line = text.remove(y);
+            let x = text[y - 1].chars().count();
+            text[y - 1] += &line;
+            self.text.cursor_position = Vector2i::new(x as i32, y as i32 - 1);
         }
     }
 
@@ -267,4 +663,216 @@ impl Edit {
     fn control_pressed(&mut self, is_down: bool) {
         self.control = is_down;
     }
+
+    fn alt_pressed(&mut self, is_down: bool) {
+        self.alt = is_down;
+    }
+}
+
+/// pasted text with `\n` line endings and without control chars other than tabs
+///
+/// An escape char could end a bracketed paste early or run as a key, so it is dropped.
+pub fn paste_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| matches!(c, '\n' | '\t') || !c.is_control())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a key press followed by the text it produces, like sfml sends them
+    fn type_key(edit: &mut Edit, code: Key, text: &str) {
+        edit.key_pressed(code);
+        text.chars().for_each(|c| edit.text_entered(c));
+    }
+
+    #[test]
+    fn test_text_entered() {
+        let mut edit = EditBuilder::new().build();
+        // german layout: the keys are where the us layout has other chars
+        type_key(&mut edit, Key::Quote, "ä");
+        type_key(&mut edit, Key::Hyphen, "ß");
+        type_key(&mut edit, Key::Z, "y");
+        // AltGr-Q, reported as Alt on some systems
+        edit.key_pressed(Key::RAlt);
+        type_key(&mut edit, Key::Q, "@");
+        edit.key_released(Key::RAlt);
+        // a dead key produces no text, the next key the composed char
+        type_key(&mut edit, Key::Equal, "");
+        type_key(&mut edit, Key::E, "é");
+        // compose sequences can produce text without a key press
+        edit.text_entered('ü');
+        assert_eq!(edit.get_text(), vec!["äßy@éü"]);
+
+        // commands don't type the text sfml sends for them
+        edit.key_pressed(Key::LAlt);
+        type_key(&mut edit, Key::B, "b");
+        edit.key_released(Key::LAlt);
+        edit.key_pressed(Key::LControl);
+        type_key(&mut edit, Key::A, "\x01");
+        edit.key_released(Key::LControl);
+        type_key(&mut edit, Key::Backspace, "\x08");
+        type_key(&mut edit, Key::Tab, "\t");
+        assert_eq!(edit.get_text(), vec!["äßy@éü"]);
+        assert_eq!(edit.get_cursor().x, 0);
+    }
+
+    #[test]
+    fn test_backspace_multibyte() {
+        let mut edit = EditBuilder::new().build();
+        "äb".chars().for_each(|c| edit.text_entered(c));
+        type_key(&mut edit, Key::Backspace, "\x08");
+        assert_eq!(edit.get_text(), vec!["ä"]);
+        assert_eq!(edit.get_cursor().x, 1);
+
+        // joining lines puts the cursor after the chars of the line above
+        edit.insert_str("ö\nü");
+        type_key(&mut edit, Key::Home, "");
+        type_key(&mut edit, Key::Backspace, "\x08");
+        assert_eq!(edit.get_text(), vec!["äöü"]);
+        assert_eq!(edit.get_cursor().x, 2);
+        type_key(&mut edit, Key::Backspace, "\x08");
+        assert_eq!(edit.get_text(), vec!["äü"]);
+    }
+
+    #[test]
+    fn test_clear_line_multibyte() {
+        let mut edit = EditBuilder::new().build();
+        "äöü".chars().for_each(|c| edit.text_entered(c));
+        // Ctrl-U and Ctrl-K from normal mode
+        type_key(&mut edit, Key::Escape, "\x1b");
+        type_key(&mut edit, Key::Num0, "0");
+        type_key(&mut edit, Key::L, "l");
+        edit.key_pressed(Key::LControl);
+        type_key(&mut edit, Key::U, "\x15");
+        edit.key_released(Key::LControl);
+        assert_eq!(edit.get_text(), vec!["öü"]);
+        assert_eq!(edit.get_cursor().x, 0);
+
+        type_key(&mut edit, Key::Escape, "\x1b");
+        type_key(&mut edit, Key::L, "l");
+        edit.key_pressed(Key::LControl);
+        type_key(&mut edit, Key::K, "\x0b");
+        edit.key_released(Key::LControl);
+        assert_eq!(edit.get_text(), vec!["ö"]);
+    }
+
+    #[test]
+    fn test_text_entered_normal_mode() {
+        let mut edit = EditBuilder::new().build();
+        "grün".chars().for_each(|c| edit.text_entered(c));
+        type_key(&mut edit, Key::Escape, "\x1b");
+        assert!(edit.mode == Mode::Normal);
+        // vi commands with the chars of any layout
+        type_key(&mut edit, Key::Num0, "0");
+        type_key(&mut edit, Key::F, "f");
+        type_key(&mut edit, Key::LBracket, "ü");
+        type_key(&mut edit, Key::R, "r");
+        type_key(&mut edit, Key::Semicolon, "ö");
+        assert_eq!(edit.get_text(), vec!["grön"]);
+        type_key(&mut edit, Key::U, "u");
+        assert_eq!(edit.get_text(), vec!["grün"]);
+        // Ctrl-R is a key press, the control char it produces is ignored
+        edit.key_pressed(Key::LControl);
+        type_key(&mut edit, Key::R, "\x12");
+        edit.key_released(Key::LControl);
+        assert_eq!(edit.get_text(), vec!["grön"]);
+        assert!(edit.mode == Mode::Normal);
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let mut edit = EditBuilder::new().build();
+        "echo \"ab".chars().for_each(|c| edit.text_entered(c));
+        edit.insert_newline();
+        "cd\"".chars().for_each(|c| edit.text_entered(c));
+        assert_eq!(edit.get_text(), vec!["echo \"ab", "cd\""]);
+        assert_eq!(edit.line_count(), 2);
+
+        assert!(edit.move_cursor_line(-1));
+        assert!(!edit.move_cursor_line(-1));
+        assert_eq!(edit.get_cursor(), Vector2i::new(3, 0));
+        edit.insert_newline();
+        assert_eq!(edit.get_text(), vec!["ech", "o \"ab", "cd\""]);
+        // backspace at the start of a line joins it with the line above
+        edit.key_pressed(Key::Backspace);
+        assert_eq!(edit.get_text(), vec!["echo \"ab", "cd\""]);
+        assert!(edit.move_cursor_line(1));
+        assert!(!edit.move_cursor_line(1));
+        assert_eq!(edit.get_cursor(), Vector2i::new(3, 1));
+
+        // splitting and joining the line again is no undo step
+        edit.key_pressed(Key::Escape);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec![""]);
+    }
+
+    #[test]
+    fn test_scroll_to_cursor() {
+        let mut edit = EditBuilder::new().build();
+        edit.replace((0..10).map(|i| i.to_string()).collect());
+        edit.set_cursor(Vector2i::new(5, 0));
+        assert_eq!(edit.get_cursor(), Vector2i::new(1, 0));
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 0);
+        // the window only moves when the cursor leaves it
+        edit.set_cursor(Vector2i::new(0, 3));
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 0);
+        edit.set_cursor(Vector2i::new(0, 6));
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 3);
+        edit.set_cursor(Vector2i::new(0, 20));
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 6);
+        edit.set_cursor(Vector2i::new(0, 4));
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 4);
+        // a text shorter than the window is not scrolled
+        edit.replace(vec!["a".to_string()]);
+        edit.scroll_to_cursor(4);
+        assert_eq!(edit.first_visible_line(4), 0);
+    }
+
+    #[test]
+    fn test_highlighter() {
+        let mut edit = EditBuilder::new().build();
+        edit.set_highlighter(Some(Box::new(|lines: &[String]| {
+            let end = lines[0].find(' ').unwrap_or(lines[0].len());
+            vec![text::Highlight {
+                line: 0,
+                start: 0,
+                end,
+                color: Color::GREEN,
+            }]
+        })));
+        "ls -l".chars().for_each(|c| edit.text_entered(c));
+        edit.update_highlights();
+        assert_eq!(edit.text.highlights()[0].end, 2);
+        assert_eq!(edit.get_text(), vec!["ls -l"]);
+        edit.set_highlighter(None);
+        assert!(edit.text.highlights().is_empty());
+    }
+
+    #[test]
+    fn test_paste() {
+        assert_eq!(paste_text("a\r\nb\rc\x1b[201~\td"), "a\nb\nc[201~\td");
+
+        let mut edit = EditBuilder::new().build();
+        "ls ".chars().for_each(|c| edit.text_entered(c));
+        edit.paste("-l\r\necho\tdone");
+        assert_eq!(edit.get_text(), vec!["ls -l", "echo done"]);
+        "!".chars().for_each(|c| edit.text_entered(c));
+        assert_eq!(edit.get_text(), vec!["ls -l", "echo done!"]);
+        // the paste and the text typed before and after it are separate undo steps
+        edit.key_pressed(Key::Escape);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec!["ls -l", "echo done"]);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec!["ls "]);
+    }
 }
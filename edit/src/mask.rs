@@ -0,0 +1,102 @@
+//! masked input for passwords: the typed text is shown as `*`
+//!
+//! The text is kept apart from the buffer, it is not recorded for undo, `.` or the kill ring and
+//! `get_text` only returns the `*`. Only Backspace and `Ctrl-U` edit it.
+
+use sfml::window::Key;
+
+use crate::{paste_text, Edit, Mode};
+
+const MASK_CHAR: char = '*';
+
+impl Edit {
+    /// start or end masked input, both clear the buffer
+    pub fn set_masked(&mut self, masked: bool) {
+        if masked {
+            self.set_mode(Mode::Insert);
+        }
+        self.masked = masked.then(String::new);
+        self.replace(vec![]);
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.masked.is_some()
+    }
+
+    /// the masked text, it is cleared but masked input goes on
+    pub fn take_masked(&mut self) -> String {
+        let text = self.masked.as_mut().map(std::mem::take).unwrap_or_default();
+        self.update_mask();
+        text
+    }
+
+    /// returns false if not in masked mode, keys other than the editing keys are ignored
+    pub(crate) fn masked_key_pressed(&mut self, code: Key) -> bool {
+        let Some(masked) = &mut self.masked else {
+            return false;
+        };
+        match code {
+            Key::Backspace => {
+                masked.pop();
+            }
+            Key::H if self.control => {
+                masked.pop();
+            }
+            Key::U if self.control => masked.clear(),
+            _ => {}
+        }
+        self.update_mask();
+        true
+    }
+
+    /// returns false if not in masked mode
+    pub(crate) fn masked_text_entered(&mut self, text: &str) -> bool {
+        let Some(masked) = &mut self.masked else {
+            return false;
+        };
+        // a password is a single line
+        masked.extend(paste_text(text).chars().filter(|c| !c.is_control()));
+        self.update_mask();
+        true
+    }
+
+    fn update_mask(&mut self) {
+        let len = self.masked.as_ref().map_or(0, |m| m.chars().count());
+        self.text.replace(vec![MASK_CHAR.to_string().repeat(len)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EditBuilder;
+
+    #[test]
+    fn test_masked() {
+        let mut edit = EditBuilder::new().build();
+        "ls".chars().for_each(|c| edit.text_entered(c));
+        edit.set_masked(true);
+        assert_eq!(edit.get_text(), vec![""]);
+        "sécret".chars().for_each(|c| edit.text_entered(c));
+        edit.key_pressed(Key::Backspace);
+        edit.paste("s\n");
+        assert_eq!(edit.get_text(), vec!["******"]);
+        // vi keys and undo don't see the text
+        edit.key_pressed(Key::Escape);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec!["*******"]);
+        assert!(edit.mode == Mode::Insert);
+
+        assert_eq!(edit.take_masked(), "sécresu");
+        assert_eq!(edit.get_text(), vec![""]);
+        assert!(edit.is_masked());
+        edit.set_masked(false);
+        "a".chars().for_each(|c| edit.text_entered(c));
+        assert_eq!(edit.get_text(), vec!["a"]);
+        edit.key_pressed(Key::Escape);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec![""]);
+        edit.text_entered('u');
+        assert_eq!(edit.get_text(), vec![""]);
+    }
+}
@@ -0,0 +1,177 @@
+//! undo and redo of changes to the edit buffer
+//!
+//! The whole buffer is saved before a change, command lines are short. Text typed in one go in
+//! insert mode is a single undo step, like in vi.
+
+use sfml::system::Vector2i;
+
+use crate::{Edit, Mode};
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct Snapshot {
+    text: Vec<String>,
+    /// where undo puts the cursor
+    pub(crate) cursor: Vector2i,
+}
+
+#[derive(Default)]
+pub(crate) struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// the buffer before the text typed since entering insert mode
+    insert_run: Option<Snapshot>,
+}
+
+impl Edit {
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            text: self.text.get_text(),
+            cursor: self.text.cursor_position,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        *self.text.lines_mut() = snapshot.text;
+        self.text.cursor_position = snapshot.cursor;
+        if self.mode == Mode::Normal {
+            self.vi_clamp_cursor();
+        }
+        self.text.redraw = true;
+    }
+
+    /// make the change since `before` an undo step, unless nothing changed
+    pub(crate) fn commit_undo(&mut self, before: Snapshot) {
+        if before.text != self.text.lines() {
+            self.history.undo.push(before);
+            self.history.redo.clear();
+        }
+    }
+
+    /// run `change` as a single undo step
+    pub(crate) fn with_undo(&mut self, change: impl FnOnce(&mut Self)) {
+        self.finish_insert_run();
+        let before = self.snapshot();
+        change(self);
+        self.commit_undo(before);
+    }
+
+    /// typing in insert mode, the run continues until insert mode is left or the cursor moves
+    pub(crate) fn start_insert_run(&mut self) {
+        if self.history.insert_run.is_none() {
+            self.history.insert_run = Some(self.snapshot());
+        }
+    }
+
+    /// start an insert run that includes the change that entered insert mode
+    pub(crate) fn start_insert_run_from(&mut self, before: Snapshot) {
+        self.history.insert_run = Some(before);
+    }
+
+    pub(crate) fn finish_insert_run(&mut self) {
+        if let Some(before) = self.history.insert_run.take() {
+            self.commit_undo(before);
+        }
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.history = History::default();
+    }
+
+    /// undo the last `count` changes
+    pub fn undo(&mut self, count: usize) {
+        self.finish_insert_run();
+        for _ in 0..count {
+            let Some(snapshot) = self.history.undo.pop() else {
+                break;
+            };
+            self.history.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// redo the last `count` undone changes
+    pub fn redo(&mut self, count: usize) {
+        self.finish_insert_run();
+        for _ in 0..count {
+            let Some(snapshot) = self.history.redo.pop() else {
+                break;
+            };
+            self.history.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// like `replace`, but the old text can be restored with undo
+    pub fn replace_with_undo(&mut self, text: Vec<String>) -> Vec<String> {
+        self.finish_insert_run();
+        let before = self.snapshot();
+        let old_text = self.text.replace(text);
+        self.commit_undo(before);
+        old_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::window::Key;
+
+    use crate::EditBuilder;
+
+    fn text(edit: &crate::Edit) -> String {
+        edit.get_text().join("\n")
+    }
+
+    #[test]
+    fn test_undo_ctrl_keys() {
+        let mut edit = EditBuilder::new().build();
+        edit.replace(vec!["ls -l /tmp".into()]);
+        edit.control = true;
+        edit.key_pressed(Key::W);
+        assert_eq!(text(&edit), "ls -l ");
+        edit.key_pressed(Key::U);
+        assert_eq!(text(&edit), "");
+        edit.shift = true;
+        edit.key_pressed(Key::Hyphen);
+        assert_eq!(text(&edit), "ls -l ");
+        edit.key_pressed(Key::Hyphen);
+        assert_eq!(text(&edit), "ls -l /tmp");
+        assert_eq!(edit.get_cursor().x, 10);
+        edit.key_pressed(Key::Hyphen);
+        assert_eq!(text(&edit), "ls -l /tmp");
+    }
+
+    #[test]
+    fn test_undo_insert_run() {
+        let mut edit = EditBuilder::new().build();
+        "ls ".chars().for_each(|c| edit.text_entered(c));
+        edit.key_pressed(Key::Backspace);
+        edit.key_pressed(Key::Escape);
+        "ax".chars().for_each(|c| edit.text_entered(c));
+        assert_eq!(text(&edit), "lsx");
+        edit.key_pressed(Key::Escape);
+        edit.text_entered('u');
+        assert_eq!(text(&edit), "ls");
+        edit.text_entered('u');
+        assert_eq!(text(&edit), "");
+        edit.control = true;
+        edit.key_pressed(Key::R);
+        edit.key_pressed(Key::R);
+        assert_eq!(text(&edit), "lsx");
+    }
+
+    #[test]
+    fn test_replace_with_undo() {
+        let mut edit = EditBuilder::new().build();
+        edit.replace(vec!["echo".into()]);
+        edit.replace_with_undo(vec!["ls".into()]);
+        edit.replace_with_undo(vec!["pwd".into()]);
+        edit.undo(2);
+        assert_eq!(text(&edit), "echo");
+        edit.redo(1);
+        assert_eq!(text(&edit), "ls");
+        // a new buffer has no history
+        edit.replace(vec![]);
+        edit.undo(1);
+        assert_eq!(text(&edit), "");
+    }
+}
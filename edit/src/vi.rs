@@ -0,0 +1,1060 @@
+//! vi normal mode: motions, counts, operators, text objects and `.` repeat
+//!
+//! Keys are collected until they form a complete command, e.g. `2d3w` or `ci"`. Commands work on
+//! the line of the cursor, columns are counted in chars. Only `j`, `k`, `G` and `gg` go to
+//! another line.
+
+use crate::{Edit, Mode};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    /// `w` and `W`, the flag is set for WORDs
+    WordForward(bool),
+    WordBackward(bool),
+    WordEnd(bool),
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    Find(Find),
+    /// `;` and `,`, the flag is set for `,`
+    RepeatFind(bool),
+}
+
+/// motions to another line, they can't be used with an operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineMotion {
+    Down,
+    Up,
+    /// `G`, to the line of the count or the last line
+    Goto,
+    /// `gg`, to the line of the count or the first line
+    GotoFirst,
+}
+
+/// `f`, `t`, `F` or `T` with the char to find
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Find {
+    kind: char,
+    ch: char,
+}
+
+impl Find {
+    fn reversed(self) -> Self {
+        let kind = match self.kind {
+            'f' => 'F',
+            'F' => 'f',
+            't' => 'T',
+            _ => 't',
+        };
+        Self { kind, ch: self.ch }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextObject {
+    Word {
+        big: bool,
+        around: bool,
+    },
+    Quote {
+        quote: char,
+        around: bool,
+    },
+    Bracket {
+        open: char,
+        close: char,
+        around: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Motion(Motion),
+    /// the operator key twice, e.g. `dd`
+    Line,
+    Object(TextObject),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Move(Motion),
+    MoveLine(LineMotion),
+    Operate(Operator, Target),
+    DeleteChar,
+    DeleteCharBefore,
+    Replace(char),
+    ToggleCase,
+    /// `p` and `P`, the flag is set for `P`
+    Put(bool),
+    /// `i`, `a`, `I` or `A`
+    Insert(char),
+    Repeat,
+    Undo,
+    /// Ctrl-R
+    Redo,
+    /// `v` and `V`, the flag is set for `V`
+    Visual(bool),
+}
+
+impl Command {
+    /// commands that change the text can be repeated with `.`
+    fn is_change(&self) -> bool {
+        !matches!(
+            self,
+            Command::Move(_)
+                | Command::MoveLine(_)
+                | Command::Operate(Operator::Yank, _)
+                | Command::Repeat
+                | Command::Undo
+                | Command::Redo
+                | Command::Visual(_)
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Parse {
+    Incomplete,
+    Invalid,
+    Complete(Option<usize>, Command),
+}
+
+/// the last change, for `.`
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    count: Option<usize>,
+    command: Command,
+    /// the text typed in insert mode after the command
+    inserted: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct ViState {
+    pending: Vec<char>,
+    last_find: Option<Find>,
+    register: Register,
+    last_change: Option<Change>,
+    /// text typed since a change entered insert mode
+    insert_record: Option<String>,
+}
+
+impl ViState {
+    pub(crate) fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// the keys typed so far are a count or nothing, e.g. `3`
+    pub(crate) fn has_only_count(&self) -> bool {
+        self.pending.iter().all(|c| c.is_ascii_digit())
+    }
+
+    /// text deleted or yanked, for `p` and `P`
+    pub(crate) fn set_register(&mut self, text: String, linewise: bool) {
+        self.register = Register { text, linewise };
+    }
+
+    pub(crate) fn record_insert(&mut self, s: &str) {
+        if let Some(record) = &mut self.insert_record {
+            record.push_str(s);
+        }
+    }
+
+    pub(crate) fn record_backspace(&mut self) {
+        if let Some(record) = &mut self.insert_record {
+            record.pop();
+        }
+    }
+
+    /// insert mode was left, the typed text belongs to the last change
+    pub(crate) fn finish_insert(&mut self) {
+        if let (Some(record), Some(change)) = (self.insert_record.take(), &mut self.last_change) {
+            change.inserted = record;
+        }
+    }
+}
+
+fn parse_count(keys: &[char], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    // a leading 0 is the motion to the start of the line
+    if keys.get(*i) == Some(&'0') {
+        return None;
+    }
+    while keys.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+        *i += 1;
+    }
+    if *i == start {
+        None
+    } else {
+        keys[start..*i].iter().collect::<String>().parse().ok()
+    }
+}
+
+/// parse a motion starting with `c`, `next` is the key after it
+/// returns Ok(None) if more keys are needed
+fn parse_motion(c: char, next: Option<char>) -> Result<Option<Motion>, ()> {
+    let motion = match c {
+        'h' => Motion::Left,
+        'l' | ' ' => Motion::Right,
+        'w' => Motion::WordForward(false),
+        'W' => Motion::WordForward(true),
+        'b' => Motion::WordBackward(false),
+        'B' => Motion::WordBackward(true),
+        'e' => Motion::WordEnd(false),
+        'E' => Motion::WordEnd(true),
+        '0' => Motion::LineStart,
+        '^' => Motion::FirstNonBlank,
+        '$' => Motion::LineEnd,
+        ';' => Motion::RepeatFind(false),
+        ',' => Motion::RepeatFind(true),
+        'f' | 't' | 'F' | 'T' => match next {
+            Some(ch) => Motion::Find(Find { kind: c, ch }),
+            None => return Ok(None),
+        },
+        _ => return Err(()),
+    };
+    Ok(Some(motion))
+}
+
+fn parse_object(c: char, around: bool) -> Option<TextObject> {
+    let object = match c {
+        'w' => TextObject::Word { big: false, around },
+        'W' => TextObject::Word { big: true, around },
+        '"' | '\'' | '`' => TextObject::Quote { quote: c, around },
+        '(' | ')' | 'b' => TextObject::Bracket {
+            open: '(',
+            close: ')',
+            around,
+        },
+        '[' | ']' => TextObject::Bracket {
+            open: '[',
+            close: ']',
+            around,
+        },
+        '{' | '}' | 'B' => TextObject::Bracket {
+            open: '{',
+            close: '}',
+            around,
+        },
+        '<' | '>' => TextObject::Bracket {
+            open: '<',
+            close: '>',
+            around,
+        },
+        _ => return None,
+    };
+    Some(object)
+}
+
+fn multiply(count1: Option<usize>, count2: Option<usize>) -> Option<usize> {
+    match (count1, count2) {
+        (None, None) => None,
+        (count1, count2) => Some(count1.unwrap_or(1) * count2.unwrap_or(1)),
+    }
+}
+
+fn parse(keys: &[char]) -> Parse {
+    let mut i = 0;
+    let count = parse_count(keys, &mut i);
+    let Some(&c) = keys.get(i) else {
+        return Parse::Incomplete;
+    };
+    i += 1;
+    let next = keys.get(i).copied();
+
+    let command = match c {
+        'd' | 'c' | 'y' => {
+            let operator = match c {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            let count2 = parse_count(keys, &mut i);
+            let count = multiply(count, count2);
+            let Some(&m) = keys.get(i) else {
+                return Parse::Incomplete;
+            };
+            i += 1;
+            let next = keys.get(i).copied();
+            let target = if m == c {
+                Target::Line
+            } else if m == 'i' || m == 'a' {
+                match next {
+                    None => return Parse::Incomplete,
+                    Some(o) => match parse_object(o, m == 'a') {
+                        Some(object) => Target::Object(object),
+                        None => return Parse::Invalid,
+                    },
+                }
+            } else {
+                match parse_motion(m, next) {
+                    Ok(Some(motion)) => Target::Motion(motion),
+                    Ok(None) => return Parse::Incomplete,
+                    Err(()) => return Parse::Invalid,
+                }
+            };
+            return Parse::Complete(count, Command::Operate(operator, target));
+        }
+        'x' => Command::DeleteChar,
+        'X' => Command::DeleteCharBefore,
+        'r' => match next {
+            Some(ch) => Command::Replace(ch),
+            None => return Parse::Incomplete,
+        },
+        '~' => Command::ToggleCase,
+        'p' => Command::Put(false),
+        'P' => Command::Put(true),
+        'i' | 'a' | 'I' | 'A' => Command::Insert(c),
+        's' => Command::Operate(Operator::Change, Target::Motion(Motion::Right)),
+        'S' => Command::Operate(Operator::Change, Target::Line),
+        'C' => Command::Operate(Operator::Change, Target::Motion(Motion::LineEnd)),
+        'D' => Command::Operate(Operator::Delete, Target::Motion(Motion::LineEnd)),
+        '.' => Command::Repeat,
+        'u' => Command::Undo,
+        '\x12' => Command::Redo,
+        'v' | 'V' => Command::Visual(c == 'V'),
+        'j' => Command::MoveLine(LineMotion::Down),
+        'k' => Command::MoveLine(LineMotion::Up),
+        'G' => Command::MoveLine(LineMotion::Goto),
+        'g' => match next {
+            Some('g') => Command::MoveLine(LineMotion::GotoFirst),
+            Some(_) => return Parse::Invalid,
+            None => return Parse::Incomplete,
+        },
+        _ => match parse_motion(c, next) {
+            Ok(Some(motion)) => Command::Move(motion),
+            Ok(None) => return Parse::Incomplete,
+            Err(()) => return Parse::Invalid,
+        },
+    };
+    Parse::Complete(count, command)
+}
+
+/// 0 for whitespace, 1 for word chars and 2 for other chars; WORDs only know whitespace
+fn char_class(c: char, big: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn word_forward(line: &[char], x: usize, big: bool) -> usize {
+    let len = line.len();
+    let mut x = x;
+    if x < len {
+        let class = char_class(line[x], big);
+        while x < len && class != 0 && char_class(line[x], big) == class {
+            x += 1;
+        }
+    }
+    while x < len && char_class(line[x], big) == 0 {
+        x += 1;
+    }
+    x
+}
+
+fn word_backward(line: &[char], x: usize, big: bool) -> usize {
+    let mut x = x.min(line.len());
+    while x > 0 && char_class(line[x - 1], big) == 0 {
+        x -= 1;
+    }
+    if x > 0 {
+        let class = char_class(line[x - 1], big);
+        while x > 0 && char_class(line[x - 1], big) == class {
+            x -= 1;
+        }
+    }
+    x
+}
+
+fn word_end(line: &[char], x: usize, big: bool) -> usize {
+    let len = line.len();
+    let mut x = x + 1;
+    while x < len && char_class(line[x], big) == 0 {
+        x += 1;
+    }
+    if x >= len {
+        return len.saturating_sub(1);
+    }
+    let class = char_class(line[x], big);
+    while x + 1 < len && char_class(line[x + 1], big) == class {
+        x += 1;
+    }
+    x
+}
+
+fn first_non_blank(line: &[char]) -> usize {
+    line.iter()
+        .position(|c| !c.is_whitespace())
+        .unwrap_or(line.len().saturating_sub(1))
+}
+
+/// `repeat` is set for `;` and `,`, which must not get stuck in front of the char found by `t`
+fn find(line: &[char], x: usize, find: Find, count: usize, repeat: bool) -> Option<usize> {
+    let mut pos = match (find.kind, repeat) {
+        ('t', true) => x + 1,
+        ('T', true) => x.saturating_sub(1),
+        _ => x,
+    };
+    for _ in 0..count {
+        pos = match find.kind {
+            'f' | 't' => pos + 1 + line.get(pos + 1..)?.iter().position(|&c| c == find.ch)?,
+            _ => line[..pos.min(line.len())]
+                .iter()
+                .rposition(|&c| c == find.ch)?,
+        };
+    }
+    match find.kind {
+        't' => Some(pos - 1),
+        'T' => Some(pos + 1),
+        _ => Some(pos),
+    }
+}
+
+/// range of a text object around column `x`, end exclusive
+fn object_range(line: &[char], x: usize, object: TextObject) -> Option<(usize, usize)> {
+    let len = line.len();
+    if len == 0 {
+        return None;
+    }
+    let x = x.min(len - 1);
+    match object {
+        TextObject::Word { big, around } => {
+            let class = char_class(line[x], big);
+            let mut start = x;
+            while start > 0 && char_class(line[start - 1], big) == class {
+                start -= 1;
+            }
+            let mut end = x + 1;
+            while end < len && char_class(line[end], big) == class {
+                end += 1;
+            }
+            if around {
+                // include the whitespace after the word, or before it if there is none after
+                let mut space_end = end;
+                while space_end < len && char_class(line[space_end], big) == 0 {
+                    space_end += 1;
+                }
+                if space_end > end || class == 0 {
+                    end = space_end;
+                } else {
+                    while start > 0 && char_class(line[start - 1], big) == 0 {
+                        start -= 1;
+                    }
+                }
+            }
+            Some((start, end))
+        }
+        TextObject::Quote { quote, around } => {
+            let quotes: Vec<usize> = (0..len).filter(|&i| line[i] == quote).collect();
+            // quotes pair up from the start of the line, use the pair around the cursor or the
+            // first one after it
+            let (open, close) = quotes
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|&(_, close)| close >= x)?;
+            if around {
+                Some((open, close + 1))
+            } else {
+                Some((open + 1, close))
+            }
+        }
+        TextObject::Bracket {
+            open,
+            close,
+            around,
+        } => {
+            let mut depth = 0;
+            let mut start = None;
+            for i in (0..=x).rev() {
+                if line[i] == close && i != x {
+                    depth += 1;
+                } else if line[i] == open {
+                    if depth == 0 {
+                        start = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let start = start?;
+            let mut depth = 0;
+            let mut end = None;
+            for (i, &c) in line.iter().enumerate().skip(start + 1) {
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    if depth == 0 {
+                        end = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let end = end?;
+            if around {
+                Some((start, end + 1))
+            } else {
+                Some((start + 1, end))
+            }
+        }
+    }
+}
+
+impl Edit {
+    /// handle a key typed in normal mode
+    pub(crate) fn vi_key(&mut self, c: char) {
+        self.vi.pending.push(c);
+        match parse(&self.vi.pending) {
+            Parse::Incomplete => return,
+            Parse::Invalid => {}
+            Parse::Complete(count, command) => {
+                let before = self.snapshot();
+                match command {
+                    Command::Repeat => self.vi_repeat(count),
+                    Command::Undo => self.undo(count.unwrap_or(1)),
+                    Command::Redo => self.redo(count.unwrap_or(1)),
+                    _ => {
+                        if command.is_change() {
+                            self.vi.last_change = Some(Change {
+                                count,
+                                command,
+                                inserted: String::new(),
+                            });
+                        }
+                        self.vi_execute(count, command);
+                    }
+                }
+                if command.is_change() || command == Command::Repeat {
+                    if self.mode == Mode::Insert {
+                        self.start_insert_run_from(before);
+                    } else {
+                        self.commit_undo(before);
+                    }
+                }
+            }
+        }
+        self.vi.pending.clear();
+        self.vi_clamp_cursor();
+        self.text.redraw = true;
+    }
+
+    /// a key of a motion in visual mode, e.g. `w`, `2b` or `fx`
+    /// returns false if the keys typed so far are no motion
+    pub(crate) fn vi_visual_motion(&mut self, c: char) -> bool {
+        self.vi.pending.push(c);
+        match parse(&self.vi.pending) {
+            Parse::Incomplete => return true,
+            Parse::Complete(count, command @ (Command::Move(_) | Command::MoveLine(_))) => {
+                self.vi_execute(count, command)
+            }
+            _ => return false,
+        }
+        self.vi.pending.clear();
+        self.vi_clamp_cursor();
+        true
+    }
+
+    /// `.`, a count replaces the count of the change
+    fn vi_repeat(&mut self, count: Option<usize>) {
+        let Some(mut change) = self.vi.last_change.clone() else {
+            return;
+        };
+        if count.is_some() {
+            change.count = count;
+        }
+        self.vi_execute(change.count, change.command);
+        if self.mode == Mode::Insert {
+            self.write(&change.inserted);
+            self.vi.insert_record = None;
+            self.set_mode(Mode::Normal);
+        }
+        self.vi.last_change = Some(change);
+    }
+
+    /// in normal and visual mode the cursor is on a char, not behind the last one
+    pub(crate) fn vi_clamp_cursor(&mut self) {
+        if self.mode != Mode::Insert {
+            let len = self.line_chars().len();
+            self.set_cursor_x(self.cursor_x().min(len.saturating_sub(1)));
+        }
+    }
+
+    fn vi_enter_insert(&mut self) {
+        self.vi.insert_record = Some(String::new());
+        self.set_mode(Mode::Insert);
+    }
+
+    /// column the motion moves to and whether the char at that column is included when it is
+    /// used with an operator
+    fn vi_motion(&mut self, motion: Motion, count: usize) -> Option<(usize, bool)> {
+        let line = self.line_chars();
+        let len = line.len();
+        let x = self.cursor_x();
+        let target = match motion {
+            Motion::Left => (x.saturating_sub(count), false),
+            Motion::Right => ((x + count).min(len), false),
+            Motion::WordForward(big) => (
+                (0..count).fold(x, |x, _| word_forward(&line, x, big)),
+                false,
+            ),
+            Motion::WordBackward(big) => (
+                (0..count).fold(x, |x, _| word_backward(&line, x, big)),
+                false,
+            ),
+            Motion::WordEnd(big) => ((0..count).fold(x, |x, _| word_end(&line, x, big)), true),
+            Motion::LineStart => (0, false),
+            Motion::FirstNonBlank => (first_non_blank(&line), false),
+            Motion::LineEnd => (len.saturating_sub(1), true),
+            Motion::Find(f) => {
+                self.vi.last_find = Some(f);
+                (
+                    find(&line, x, f, count, false)?,
+                    matches!(f.kind, 'f' | 't'),
+                )
+            }
+            Motion::RepeatFind(reverse) => {
+                let f = self.vi.last_find?;
+                let f = if reverse { f.reversed() } else { f };
+                (find(&line, x, f, count, true)?, matches!(f.kind, 'f' | 't'))
+            }
+        };
+        Some(target)
+    }
+
+    fn vi_execute(&mut self, count: Option<usize>, command: Command) {
+        let n = count.unwrap_or(1).max(1);
+        match command {
+            Command::Move(motion) => {
+                if let Some((x, _)) = self.vi_motion(motion, n) {
+                    self.set_cursor_x(x);
+                }
+            }
+            Command::MoveLine(motion) => self.vi_move_line(motion, count),
+            Command::Operate(operator, target) => self.vi_operate(operator, target, n),
+            Command::DeleteChar => {
+                let x = self.cursor_x();
+                let len = self.line_chars().len();
+                if x < len {
+                    self.vi_apply(Operator::Delete, x, (x + n).min(len));
+                }
+            }
+            Command::DeleteCharBefore => {
+                let x = self.cursor_x();
+                if x > 0 {
+                    self.vi_apply(Operator::Delete, x.saturating_sub(n), x);
+                }
+            }
+            Command::Replace(ch) => {
+                let mut line = self.line_chars();
+                let x = self.cursor_x();
+                if x + n <= line.len() {
+                    line[x..x + n].iter_mut().for_each(|c| *c = ch);
+                    self.set_line_chars(&line);
+                    self.set_cursor_x(x + n - 1);
+                }
+            }
+            Command::ToggleCase => {
+                let mut line = self.line_chars();
+                let x = self.cursor_x();
+                let end = (x + n).min(line.len());
+                for c in line[x.min(end)..end].iter_mut() {
+                    *c = if c.is_uppercase() {
+                        c.to_lowercase().next().unwrap_or(*c)
+                    } else {
+                        c.to_uppercase().next().unwrap_or(*c)
+                    };
+                }
+                self.set_line_chars(&line);
+                self.set_cursor_x(end);
+            }
+            Command::Put(before) => self.vi_put(before, n),
+            Command::Insert(kind) => {
+                let line = self.line_chars();
+                let x = match kind {
+                    'i' => self.cursor_x(),
+                    'a' => (self.cursor_x() + 1).min(line.len()),
+                    'I' => first_non_blank(&line).min(line.len()),
+                    _ => line.len(),
+                };
+                self.set_cursor_x(x);
+                self.vi_enter_insert();
+            }
+            Command::Visual(line) => self.start_visual(match line {
+                true => Mode::VisualLine,
+                false => Mode::Visual,
+            }),
+            Command::Repeat | Command::Undo | Command::Redo => {}
+        }
+    }
+
+    fn vi_move_line(&mut self, motion: LineMotion, count: Option<usize>) {
+        let y = self.text.cursor_position.y as usize;
+        let last = self.text.lines().len() - 1;
+        let n = count.unwrap_or(1).max(1);
+        // the count of `G` and `gg` is a line number, starting at 1
+        let line = |default| count.map_or(default, |count: usize| count.clamp(1, last + 1) - 1);
+        let target = match motion {
+            LineMotion::Down => (y + n).min(last),
+            LineMotion::Up => y.saturating_sub(n),
+            LineMotion::Goto => line(last),
+            LineMotion::GotoFirst => line(0),
+        };
+        self.move_cursor_line(target as i32 - y as i32);
+        if matches!(motion, LineMotion::Goto | LineMotion::GotoFirst) {
+            self.set_cursor_x(first_non_blank(&self.line_chars()));
+        }
+    }
+
+    fn vi_operate(&mut self, operator: Operator, target: Target, count: usize) {
+        let x = self.cursor_x();
+        let line = self.line_chars();
+        let (start, end) = match target {
+            Target::Line => return self.vi_operate_lines(operator, count),
+            Target::Object(object) => match object_range(&line, x, object) {
+                Some(range) => range,
+                None => return,
+            },
+            // `cw` works like `ce` if the cursor is on a word
+            Target::Motion(Motion::WordForward(big))
+                if operator == Operator::Change
+                    && line.get(x).is_some_and(|c| !c.is_whitespace()) =>
+            {
+                let end = (0..count).fold(x, |x, i| {
+                    // `ce` from the end of a word would go to the end of the next word
+                    if i == 0 && word_end(&line, x.saturating_sub(1), big) == x {
+                        x
+                    } else {
+                        word_end(&line, x, big)
+                    }
+                });
+                (x, (end + 1).min(line.len()))
+            }
+            Target::Motion(motion) => match self.vi_motion(motion, count) {
+                Some((target, inclusive)) => {
+                    let (start, end) = (x.min(target), x.max(target));
+                    let end = if inclusive { end + 1 } else { end };
+                    (start, end.min(line.len()))
+                }
+                None => return,
+            },
+        };
+        self.vi_apply(operator, start, end);
+    }
+
+    /// apply an operator to the chars `start..end` of the cursor line
+    fn vi_apply(&mut self, operator: Operator, start: usize, end: usize) {
+        let mut line = self.line_chars();
+        let end = end.min(line.len());
+        let start = start.min(end);
+        self.vi.register = Register {
+            text: line[start..end].iter().collect(),
+            linewise: false,
+        };
+        match operator {
+            Operator::Yank => self.set_cursor_x(start),
+            Operator::Delete | Operator::Change => {
+                line.drain(start..end);
+                self.set_line_chars(&line);
+                self.set_cursor_x(start);
+                if operator == Operator::Change {
+                    self.vi_enter_insert();
+                }
+            }
+        }
+    }
+
+    /// `dd`, `cc` and `yy` with a count of lines
+    fn vi_operate_lines(&mut self, operator: Operator, count: usize) {
+        let y = self.text.cursor_position.y as usize;
+        let end = (y + count).min(self.text.lines().len());
+        self.vi.register = Register {
+            text: self.text.lines()[y..end].join("\n"),
+            linewise: true,
+        };
+        match operator {
+            Operator::Yank => {}
+            Operator::Delete => {
+                let text = self.text.lines_mut();
+                text.drain(y..end);
+                if text.is_empty() {
+                    text.push(String::new());
+                }
+                self.text.cursor_position.y = y.min(self.text.lines().len() - 1) as i32;
+                let line = self.line_chars();
+                self.set_cursor_x(first_non_blank(&line));
+            }
+            Operator::Change => {
+                self.text.lines_mut().drain(y + 1..end);
+                self.set_line_chars(&[]);
+                self.set_cursor_x(0);
+                self.vi_enter_insert();
+            }
+        }
+    }
+
+    fn vi_put(&mut self, before: bool, count: usize) {
+        let register = self.vi.register.clone();
+        if register.text.is_empty() && !register.linewise {
+            return;
+        }
+        if register.linewise {
+            let y = self.text.cursor_position.y as usize;
+            let y = if before { y } else { y + 1 };
+            let lines: Vec<String> = (0..count)
+                .flat_map(|_| register.text.split('\n').map(|l| l.to_string()))
+                .collect();
+            self.text.lines_mut().splice(y..y, lines);
+            self.text.cursor_position.y = y as i32;
+            let line = self.line_chars();
+            self.set_cursor_x(first_non_blank(&line));
+        } else {
+            let mut line = self.line_chars();
+            let x = if before || line.is_empty() {
+                self.cursor_x().min(line.len())
+            } else {
+                (self.cursor_x() + 1).min(line.len())
+            };
+            let text: Vec<char> = register.text.repeat(count).chars().collect();
+            let inserted = text.len();
+            line.splice(x..x, text);
+            self.set_line_chars(&line);
+            self.set_cursor_x(x + inserted - 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EditBuilder;
+
+    /// type `keys`, `|` in `before` and `after` marks the cursor, Escape is `\x1b`
+    fn run(before: &str, keys: &str) -> String {
+        let mut edit = EditBuilder::new().build();
+        let x = before.find('|').unwrap();
+        let lines: Vec<String> = before.replace('|', "").lines().map(String::from).collect();
+        let y = before[..x].matches('\n').count();
+        let x = before[..x].rsplit('\n').next().unwrap().chars().count();
+        edit.replace(lines);
+        edit.set_mode(Mode::Normal);
+        edit.text.cursor_position = sfml::system::Vector2i::new(x as i32, y as i32);
+        for c in keys.chars() {
+            match (edit.mode, c) {
+                (Mode::Normal, '\x1b') => edit.vi.clear_pending(),
+                (Mode::Normal, c) => edit.vi_key(c),
+                (Mode::Insert, '\x1b') => edit.set_mode(Mode::Normal),
+                (Mode::Insert, c) => edit.insert_str(&c.to_string()),
+                (Mode::Visual | Mode::VisualLine, '\x1b') => edit.set_mode(Mode::Normal),
+                (Mode::Visual | Mode::VisualLine, c) => edit.visual_key(c),
+            }
+        }
+        let mut lines = edit.get_text();
+        let cursor = edit.get_cursor();
+        let line = &mut lines[cursor.y as usize];
+        let idx = line
+            .char_indices()
+            .nth(cursor.x as usize)
+            .map_or(line.len(), |(i, _)| i);
+        line.insert(idx, '|');
+        let mode = match edit.mode {
+            Mode::Insert => " (insert)",
+            Mode::Visual | Mode::VisualLine => " (visual)",
+            Mode::Normal => "",
+        };
+        format!("{}{}", lines.join("\n"), mode)
+    }
+
+    #[test]
+    fn test_vi() {
+        #[rustfmt::skip]
+        let table = [
+            // motions
+            ("hello |world", "h", "hello| world"),
+            ("hello |world", "3h", "hel|lo world"),
+            ("hello |world", "l", "hello w|orld"),
+            ("hello |world", "9l", "hello worl|d"),
+            ("|one two three", "w", "one |two three"),
+            ("|one two three", "2w", "one two |three"),
+            ("|foo.bar baz", "w", "foo|.bar baz"),
+            ("|foo.bar baz", "W", "foo.bar |baz"),
+            ("one two |three", "b", "one |two three"),
+            ("one two th|ree", "2b", "one |two three"),
+            ("foo.bar |baz", "B", "|foo.bar baz"),
+            ("|one two", "e", "on|e two"),
+            ("on|e two", "e", "one tw|o"),
+            ("|foo.bar baz", "E", "foo.ba|r baz"),
+            ("  hello |world", "0", "|  hello world"),
+            ("  hello |world", "^", "  |hello world"),
+            ("|hello world", "$", "hello worl|d"),
+            ("|a,b,c,d", "f,", "a|,b,c,d"),
+            ("|a,b,c,d", "2f,", "a,b|,c,d"),
+            ("|a,b,c,d", "t,", "|a,b,c,d"),
+            ("|ab,c,d", "t,", "a|b,c,d"),
+            ("a,b,c,|d", "F,", "a,b,c|,d"),
+            ("a,b,c,|d", "T,", "a,b,c,|d"),
+            ("a,b,c|,d", "T,", "a,b,|c,d"),
+            ("|a,b,c,d", "f,;", "a,b|,c,d"),
+            ("|a,b,c,d", "f,;;,", "a,b|,c,d"),
+            ("|a,b,c,d", "fz", "|a,b,c,d"),
+            ("one\nt|wo\nthree", "j", "one\ntwo\nt|hree"),
+            ("one\nt|wo\nthree", "k", "o|ne\ntwo\nthree"),
+            ("|one\ntwo\nthree", "5j", "one\ntwo\n|three"),
+            ("|one\ntwo\nthree", "G", "one\ntwo\n|three"),
+            ("|one\n  two\nthree", "2G", "one\n  |two\nthree"),
+            ("one\ntwo\nth|ree", "gg", "|one\ntwo\nthree"),
+            ("one\ntwo\nth|ree", "gx", "one\ntwo\nth|ree"),
+            ("one\nt|wo\nthree", "dj", "one\nt|wo\nthree"),
+            // operators with motions
+            ("|one two three", "dw", "|two three"),
+            ("|one two three", "d2w", "|three"),
+            ("|one two three", "2dw", "|three"),
+            ("one |two", "dw", "one| "),
+            ("one two |three", "db", "one |three"),
+            ("|one two", "de", "| two"),
+            ("one |two three", "d$", "one| "),
+            ("one |two three", "D", "one| "),
+            ("one |two three", "d0", "|two three"),
+            ("|a,b,c", "df,", "|b,c"),
+            ("|a,b,c", "dt,", "|,b,c"),
+            ("a,b,|c", "dF,", "a,b|c"),
+            ("|one two", "cwsix\x1b", "si|x two"),
+            ("|one two", "c2wsix\x1b", "si|x"),
+            ("one |two three", "Cx\x1b", "one |x"),
+            ("|one two", "ywP", "one| one two"),
+            ("|one two", "yeP", "on|eone two"),
+            ("|one two", "y$", "|one two"),
+            // lines
+            ("one\n|two\nthree", "dd", "one\n|three"),
+            ("one\n|two\nthree", "2dd", "|one"),
+            ("|one", "dd", "|"),
+            ("  |one", "ccx\x1b", "|x"),
+            ("|one", "Sx\x1b", "|x"),
+            ("|one\ntwo", "yyp", "one\n|one\ntwo"),
+            ("one\n|two", "yyP", "one\n|two\ntwo"),
+            ("|one\ntwo", "ddp", "two\n|one"),
+            // text objects
+            ("one t|wo three", "diw", "one | three"),
+            ("one t|wo three", "daw", "one |three"),
+            ("one two t|hree", "daw", "one tw|o"),
+            ("one t|wo three", "ciwsix\x1b", "one si|x three"),
+            ("say \"he|llo\" now", "ci\"bye\x1b", "say \"by|e\" now"),
+            ("say \"he|llo\" now", "da\"", "say | now"),
+            ("|say \"hello\"", "di\"", "say \"|\""),
+            ("f(a, (b|), c)", "di(", "f(a, (|), c)"),
+            ("f(a, |(b), c)", "dib", "f(a, (|), c)"),
+            ("f(a|, (b), c)", "dib", "f(|)"),
+            ("f(a, (b), |c)", "da)", "|f"),
+            ("x = [1, |2]", "ci[0\x1b", "x = [|0]"),
+            ("{ a |}", "diB", "{|}"),
+            ("no |brackets", "di(", "no |brackets"),
+            // simple commands
+            ("|abc", "x", "|bc"),
+            ("a|bc", "5x", "|a"),
+            ("ab|c", "X", "a|c"),
+            ("abc|d", "2X", "a|d"),
+            ("|abc", "rx", "|xbc"),
+            ("|abc", "3rx", "xx|x"),
+            ("|abc", "4rx", "|abc"),
+            ("|abC", "~", "A|bC"),
+            ("|abC", "3~", "AB|c"),
+            ("|abc", "xp", "b|ac"),
+            ("|abc", "x2p", "ba|ac"),
+            ("|abc", "xP", "|abc"),
+            ("|abc", "ix\x1b", "|xabc"),
+            ("|abc", "ax\x1b", "a|xbc"),
+            ("  a|bc", "Ix\x1b", "  |xabc"),
+            ("|abc", "Ax\x1b", "abc|x"),
+            ("|abc", "ax", "ax|bc (insert)"),
+            // repeat
+            ("|one two three four", "dw.", "|three four"),
+            ("|one two three four", "dw2.", "|four"),
+            ("|a b c", "x.", "|b c"),
+            ("|a,b,c,d", "t,;", "a,|b,c,d"),
+            ("a,b,c,|d", "T,;", "a,b,|c,d"),
+            ("|one two three", "ciwX\x1bw.", "X |X three"),
+            ("|abc", "Ax\x1b.", "abcx|x"),
+            ("|aaaa", "rb.", "|baaa"),
+            ("|aaaa", "rbl.", "b|baa"),
+            ("|one two", "yw.", "|one two"),
+            // counts and invalid keys
+            ("|one two three", "2\x1bdw", "|two three"),
+            ("|one two three", "dzdw", "|two three"),
+            ("|", "x", "|"),
+            ("|", "p", "|"),
+            // undo and redo, Ctrl-R is \x12
+            ("one |two three", "dwu", "one |two three"),
+            ("|one two three", "dwdw2u", "|one two three"),
+            ("|one two three", "dwdwuu\x12", "|two three"),
+            ("|one two three", "dwdw2u2\x12", "|three"),
+            ("|abc", "ixyz\x1bu", "|abc"),
+            ("|abc", "ix\x1bay\x1bu", "|xabc"),
+            ("|abc", "cwxyz\x1bu", "|abc"),
+            ("|one two", "dw.uu", "|one two"),
+            ("|one", "yyu", "|one"),
+            ("|abc", "xu\x1bx", "|bc"),
+            ("|abc", "xux", "|bc"),
+            // visual mode, `y` is left out as it needs the clipboard
+            ("|one two", "vl", "o|ne two (visual)"),
+            ("|one two", "vl\x1b", "o|ne two"),
+            ("|one two", "vlv", "o|ne two"),
+            ("|one two three", "vwd", "|wo three"),
+            ("one |two three", "vbd", "|wo three"),
+            ("|one two three", "v2ed", "| three"),
+            ("|one two", "vex", "| two"),
+            ("|one two", "velohd", "|two"),
+            ("|one two", "vecsix\x1b", "si|x two"),
+            ("|one two", "vedp", " on|etwo"),
+            ("on|e\ntwo", "vjd", "o|n"),
+            ("one\ntw|o\nthree", "vkd", "o|n\nthree"),
+            ("one\n|two\nthree", "Vd", "one\n|three"),
+            ("one\n|two\nthree", "Vjd", "|one"),
+            ("|one\ntwo\nthree", "V2jd", "|"),
+            ("one\n|two\nthree", "Vkdp", "three\n|one\ntwo"),
+            ("one\nt|wo\nthree", "Vcx\x1b", "one\n|x\nthree"),
+            ("o|ne two", "vwVd", "|"),
+            ("o|ne\ntwo", "Vvd", "o|e\ntwo"),
+            ("|one Two", "v$~", "|ONE tWO"),
+            ("|one Two", "vwU", "|ONE Two"),
+            ("o|ne\nTWO", "Vju", "|one\ntwo"),
+            ("|one two", "vlldu", "|one two"),
+            ("|one two", "ve~u", "|one two"),
+            ("|one two", "vez", "on|e two (visual)"),
+        ];
+        let failures: Vec<String> = table
+            .iter()
+            .filter(|(before, keys, after)| run(before, keys) != *after)
+            .map(|(before, keys, after)| {
+                format!(
+                    "{:?} with {:?}: expected {:?}, got {:?}",
+                    before,
+                    keys,
+                    after,
+                    run(before, keys)
+                )
+            })
+            .collect();
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
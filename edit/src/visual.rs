@@ -0,0 +1,227 @@
+//! vi visual mode: `v` selects chars and `V` whole lines, shown with the selection of `Text`
+//!
+//! Motions move the cursor end of the selection and `o` goes to the other end. `d`, `c`, `y`,
+//! `~`, `u` and `U` work on the selection, `y` also copies it to the clipboard.
+
+use sfml::system::Vector2i;
+use sfml::window::clipboard;
+
+use crate::{Edit, Mode};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Case {
+    Toggle,
+    Lower,
+    Upper,
+}
+
+impl Case {
+    fn apply(self, c: char) -> char {
+        let upper = match self {
+            Case::Toggle => !c.is_uppercase(),
+            Case::Lower => false,
+            Case::Upper => true,
+        };
+        let converted: String = match upper {
+            true => c.to_uppercase().collect(),
+            false => c.to_lowercase().collect(),
+        };
+        // chars that change their length, like ß, are kept
+        let mut converted = converted.chars();
+        match (converted.next(), converted.next()) {
+            (Some(converted), None) => converted,
+            _ => c,
+        }
+    }
+}
+
+fn selection_mode(mode: Mode) -> text::SelectionMode {
+    match mode {
+        Mode::VisualLine => text::SelectionMode::Line,
+        _ => text::SelectionMode::Char,
+    }
+}
+
+impl Edit {
+    /// `mode` is `Visual` or `VisualLine`, the selection starts at the cursor
+    pub(crate) fn start_visual(&mut self, mode: Mode) {
+        self.text
+            .selection_start(self.text.cursor_position, selection_mode(mode));
+        self.set_mode(mode);
+    }
+
+    /// start and end of the selection, the end is inclusive
+    fn visual_range(&self) -> Option<(Vector2i, Vector2i)> {
+        if !self.mode.is_visual() {
+            return None;
+        }
+        let selection = self.text.selection()?;
+        let (anchor, head) = (selection.anchor, selection.head);
+        Some(match (anchor.y, anchor.x) <= (head.y, head.x) {
+            true => (anchor, head),
+            false => (head, anchor),
+        })
+    }
+
+    /// the selected chars of line `y`, end exclusive
+    fn visual_columns(&self, y: usize, start: Vector2i, end: Vector2i) -> (usize, usize) {
+        let len = self.text.lines()[y].chars().count();
+        if self.mode == Mode::VisualLine {
+            return (0, len);
+        }
+        let from = if y == start.y as usize {
+            start.x as usize
+        } else {
+            0
+        };
+        let to = if y == end.y as usize {
+            end.x as usize + 1
+        } else {
+            len
+        };
+        (from.min(len), to.min(len))
+    }
+
+    /// the selected text and whether it is whole lines
+    pub(crate) fn visual_text(&self) -> Option<(String, bool)> {
+        let (start, end) = self.visual_range()?;
+        let lines: Vec<String> = (start.y as usize..=end.y as usize)
+            .map(|y| {
+                let (from, to) = self.visual_columns(y, start, end);
+                self.text.lines()[y]
+                    .chars()
+                    .skip(from)
+                    .take(to - from)
+                    .collect()
+            })
+            .collect();
+        Some((lines.join("\n"), self.mode == Mode::VisualLine))
+    }
+
+    /// handle a key typed in visual mode, a change is one undo step
+    pub(crate) fn visual_key(&mut self, c: char) {
+        let mut before = self.snapshot();
+        if let Some((start, _)) = self.visual_range() {
+            // like vi, undo of a change goes to the start of the selection
+            before.cursor = start;
+        }
+        let command = self.vi.has_only_count() && self.visual_command(c);
+        if !command && !self.vi_visual_motion(c) {
+            self.vi.clear_pending();
+        }
+        if self.mode.is_visual() {
+            self.vi_clamp_cursor();
+            self.text.selection_extend(self.text.cursor_position);
+        }
+        if self.mode == Mode::Insert {
+            self.start_insert_run_from(before);
+        } else {
+            self.commit_undo(before);
+        }
+        self.text.redraw = true;
+    }
+
+    /// returns false if `c` isn't a command of visual mode, it may be a motion then
+    fn visual_command(&mut self, c: char) -> bool {
+        match c {
+            'v' | 'V' => {
+                let mode = if c == 'v' {
+                    Mode::Visual
+                } else {
+                    Mode::VisualLine
+                };
+                if self.mode == mode {
+                    self.set_mode(Mode::Normal);
+                } else if let Some(selection) = self.text.selection() {
+                    self.text
+                        .selection_start(selection.anchor, selection_mode(mode));
+                    self.set_mode(mode);
+                }
+            }
+            'o' => {
+                if let Some(selection) = self.text.selection() {
+                    self.text
+                        .selection_start(self.text.cursor_position, selection.mode);
+                    self.text.cursor_position = selection.anchor;
+                }
+            }
+            'd' | 'x' => self.visual_delete(false),
+            'c' | 's' => self.visual_delete(true),
+            'y' => {
+                if let Some((start, _)) = self.visual_range() {
+                    let (text, linewise) = self.visual_text().unwrap_or_default();
+                    clipboard::set_string(&text);
+                    self.vi.set_register(text, linewise);
+                    self.set_mode(Mode::Normal);
+                    self.text.cursor_position = Vector2i::new(start.x, start.y);
+                    if linewise {
+                        self.set_cursor_x(0);
+                    }
+                }
+            }
+            '~' => self.visual_case(Case::Toggle),
+            'u' => self.visual_case(Case::Lower),
+            'U' => self.visual_case(Case::Upper),
+            _ => return false,
+        }
+        self.vi.clear_pending();
+        true
+    }
+
+    /// `d`, or `c` if `change` is set
+    fn visual_delete(&mut self, change: bool) {
+        let Some((start, end)) = self.visual_range() else {
+            return;
+        };
+        let (text, linewise) = self.visual_text().unwrap_or_default();
+        self.vi.set_register(text, linewise);
+        let (start_y, end_y) = (start.y as usize, end.y as usize);
+        let line = if linewise {
+            String::new()
+        } else {
+            let (from, _) = self.visual_columns(start_y, start, end);
+            let (_, to) = self.visual_columns(end_y, start, end);
+            let head = self.text.lines()[start_y].chars().take(from);
+            head.chain(self.text.lines()[end_y].chars().skip(to))
+                .collect()
+        };
+        if linewise && !change {
+            let text = self.text.lines_mut();
+            text.drain(start_y..=end_y);
+            if text.is_empty() {
+                text.push(String::new());
+            }
+        } else {
+            self.text.lines_mut().splice(start_y..=end_y, [line]);
+        }
+        let y = start_y.min(self.text.lines().len() - 1);
+        let x = if linewise { 0 } else { start.x };
+        self.text.cursor_position = Vector2i::new(x, y as i32);
+        self.set_mode(if change { Mode::Insert } else { Mode::Normal });
+        self.vi_clamp_cursor();
+    }
+
+    fn visual_case(&mut self, case: Case) {
+        let Some((start, end)) = self.visual_range() else {
+            return;
+        };
+        for y in start.y as usize..=end.y as usize {
+            let (from, to) = self.visual_columns(y, start, end);
+            self.text.lines_mut()[y] = self.text.lines()[y]
+                .chars()
+                .enumerate()
+                .map(|(x, c)| match (from..to).contains(&x) {
+                    true => case.apply(c),
+                    false => c,
+                })
+                .collect();
+        }
+        let x = if self.mode == Mode::VisualLine {
+            0
+        } else {
+            start.x
+        };
+        self.set_mode(Mode::Normal);
+        self.text.cursor_position = Vector2i::new(x, start.y);
+    }
+}
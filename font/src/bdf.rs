@@ -0,0 +1,210 @@
+//! the Glyph Bitmap Distribution Format, the text format of X11 bitmap fonts
+//!
+//! Only what a monospace font needs is read: the ascent and descent, and per glyph its
+//! encoding, advance, ink box and bitmap. Glyphs without a Unicode encoding are skipped.
+
+use sfml::system::Vector2i;
+
+use crate::bitmap::{BitmapFont, InkGlyph};
+
+/// the numbers after the keyword of `line`, at least `count`
+fn numbers(line: &str, count: usize) -> Result<Vec<i32>, String> {
+    let numbers: Vec<i32> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|n| n.parse().map_err(|_| format!("invalid number: {}", line)))
+        .collect::<Result<_, _>>()?;
+    if numbers.len() < count {
+        return Err(format!("expected {} numbers: {}", count, line));
+    }
+    Ok(numbers)
+}
+
+/// parse the text of a BDF file
+pub fn parse(text: &str) -> Result<BitmapFont, String> {
+    let mut lines = text.lines().map(str::trim);
+    let mut bounding_box = None;
+    let (mut ascent, mut descent) = (None, None);
+    let mut glyphs = vec![];
+    while let Some(line) = lines.next() {
+        match line.split_whitespace().next() {
+            Some("FONTBOUNDINGBOX") => bounding_box = Some(numbers(line, 4)?),
+            Some("FONT_ASCENT") => ascent = Some(numbers(line, 1)?[0]),
+            Some("FONT_DESCENT") => descent = Some(numbers(line, 1)?[0]),
+            Some("STARTCHAR") => {
+                if let Some(glyph) = parse_glyph(&mut lines)? {
+                    glyphs.push(glyph);
+                }
+            }
+            _ => {}
+        }
+    }
+    // without the properties, the bounding box of the font gives the ascent and descent
+    let bounding_box = bounding_box.ok_or("no FONTBOUNDINGBOX")?;
+    let descent = descent.unwrap_or(-bounding_box[3]);
+    let ascent = ascent.unwrap_or(bounding_box[1] - descent);
+    for glyph in &mut glyphs {
+        if glyph.advance == 0 {
+            glyph.advance = bounding_box[0];
+        }
+    }
+    BitmapFont::from_ink(&glyphs, ascent, descent)
+}
+
+/// the lines from after `STARTCHAR` to `ENDCHAR`, None if the glyph has no encoding
+fn parse_glyph<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Result<Option<InkGlyph>, String> {
+    let mut glyph = InkGlyph {
+        ch: -1,
+        size: Vector2i::new(0, 0),
+        offset: Vector2i::new(0, 0),
+        advance: 0,
+        rows: vec![],
+    };
+    let mut bitmap = false;
+    for line in lines.by_ref() {
+        let keyword = line.split_whitespace().next();
+        match keyword {
+            Some("ENDCHAR") => return Ok((glyph.ch >= 0).then_some(glyph)),
+            Some("ENCODING") => glyph.ch = numbers(line, 1)?[0],
+            Some("DWIDTH") => glyph.advance = numbers(line, 1)?[0],
+            Some("BBX") => {
+                let bbx = numbers(line, 4)?;
+                glyph.size = Vector2i::new(bbx[0], bbx[1]);
+                glyph.offset = Vector2i::new(bbx[2], bbx[3]);
+                // the rows of the bitmap are as wide as the box
+                glyph.check()?;
+            }
+            Some("BITMAP") => bitmap = true,
+            Some(hex) if bitmap => glyph.rows.push(parse_row(hex, glyph.size.x)?),
+            _ => {}
+        }
+    }
+    Err("missing ENDCHAR".to_string())
+}
+
+/// `width` pixels from the bits of the hex digits, the first pixel is the highest bit
+fn parse_row(hex: &str, width: i32) -> Result<Vec<bool>, String> {
+    let bits: Vec<u32> = hex
+        .chars()
+        .map(|c| c.to_digit(16).ok_or(format!("invalid bitmap row: {}", hex)))
+        .collect::<Result<_, _>>()?;
+    Ok((0..width as usize)
+        .map(|i| {
+            bits.get(i / 4)
+                .is_some_and(|digit| digit & (8 >> (i % 4)) != 0)
+        })
+        .collect())
+}
+
+/// the BDF text of `font`, every glyph fills its cell
+pub fn write(font: &BitmapFont, name: &str) -> String {
+    let (width, height) = (font.char_size.x, font.char_size.y);
+    let descent = height - font.baseline;
+    let mut text = String::new();
+    text += "STARTFONT 2.1\n";
+    text += &format!("FONT {}\n", name);
+    text += &format!("SIZE {} 72 72\n", height);
+    text += &format!("FONTBOUNDINGBOX {} {} 0 {}\n", width, height, -descent);
+    text += "STARTPROPERTIES 3\n";
+    text += "SPACING \"C\"\n";
+    text += &format!("FONT_ASCENT {}\n", font.baseline);
+    text += &format!("FONT_DESCENT {}\n", descent);
+    text += "ENDPROPERTIES\n";
+    text += &format!("CHARS {}\n", font.glyphs.len());
+    for glyph in &font.glyphs {
+        text += &format!("STARTCHAR U+{:04X}\n", glyph.ch);
+        text += &format!("ENCODING {}\n", glyph.ch);
+        text += &format!("SWIDTH {} 0\n", width * 1000 / height);
+        text += &format!("DWIDTH {} 0\n", width);
+        text += &format!("BBX {} {} 0 {}\n", width, height, -descent);
+        text += "BITMAP\n";
+        for row in glyph.pixels.chunks(width as usize) {
+            for byte in row.chunks(8) {
+                let bits = byte
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &p)| p >= 128)
+                    .fold(0u8, |bits, (i, _)| bits | (0x80 >> i));
+                text += &format!("{:02X}", bits);
+            }
+            text += "\n";
+        }
+        text += "ENDCHAR\n";
+    }
+    text += "ENDFONT\n";
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::tests::{fixture, render};
+
+    #[test]
+    fn test_parse() {
+        let text = std::fs::read_to_string(fixture("test_6x10.bdf")).unwrap();
+        let font = parse(&text).unwrap();
+        assert_eq!(font.char_size, Vector2i::new(6, 10));
+        assert_eq!(font.baseline, 8);
+        let chars: Vec<i32> = font.glyphs.iter().map(|glyph| glyph.ch).collect();
+        assert_eq!(chars, [' ' as i32, 'A' as i32, 'g' as i32, '€' as i32]);
+        assert_eq!(render(&font, ' ' as i32), ["......"; 10]);
+        #[rustfmt::skip]
+        assert_eq!(render(&font, 'A' as i32), [
+            "......",
+            "..#...",
+            ".#.#..",
+            "#...#.",
+            "#...#.",
+            "#####.",
+            "#...#.",
+            "#...#.",
+            "......",
+            "......",
+        ]);
+        // smaller than the cell and below the baseline
+        #[rustfmt::skip]
+        assert_eq!(render(&font, 'g' as i32), [
+            "......",
+            "......",
+            "......",
+            "......",
+            "..###.",
+            ".#..#.",
+            ".#..#.",
+            "..###.",
+            "....#.",
+            ".###..",
+        ]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("STARTFONT 2.1\nENDFONT\n").is_err());
+        let text = "FONTBOUNDINGBOX 6 10 0 -2\nSTARTCHAR A\nENCODING 65\nBBX 1 1 0 0\nBITMAP\nZZ\n";
+        assert!(parse(text).is_err());
+        let font = |header: &str, bbx: &str| {
+            let text = format!(
+                "FONTBOUNDINGBOX 6 10 0 -2\n{}\nSTARTCHAR A\nENCODING 65\nBBX {}\nBITMAP\n20\nENDCHAR\n",
+                header, bbx
+            );
+            parse(&text)
+        };
+        assert!(font("", "1 1 0 0").is_ok());
+        assert!(font("FONT_ASCENT -20", "1 1 0 0").is_err());
+        assert!(font("FONT_DESCENT 1000", "1 1 0 0").is_err());
+        assert!(font("", "-1 1 0 0").is_err());
+        assert!(font("", "1 -1 0 0").is_err());
+        assert!(font("", "100000 1 0 0").is_err());
+        assert!(font("", "1 1 -2147483648 0").is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let text = std::fs::read_to_string(fixture("test_6x10.bdf")).unwrap();
+        let font = parse(&text).unwrap();
+        let written = write(&font, "test");
+        assert!(written.contains("BBX 6 10 0 -2\nBITMAP\n00\n20\n50\n88\n"));
+        assert_eq!(parse(&written).unwrap(), font);
+    }
+}
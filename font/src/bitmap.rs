@@ -0,0 +1,227 @@
+//! glyph bitmaps independent of the atlas image, the format parsers produce and consume these
+
+use std::collections::HashMap;
+
+use sfml::system::Vector2i;
+
+/// the pixels of a glyph at the size of a cell, row by row, 0 is background and 255 is ink
+#[derive(Debug, PartialEq, Clone)]
+pub struct Glyph {
+    pub ch: i32,
+    pub pixels: Vec<u8>,
+}
+
+/// the glyphs of a monospace font, each filling a cell of `char_size`
+#[derive(Debug, PartialEq, Clone)]
+pub struct BitmapFont {
+    pub char_size: Vector2i,
+    /// the first row below the baseline, the rows above it are the ascent
+    pub baseline: i32,
+    pub glyphs: Vec<Glyph>,
+}
+
+/// the largest width and height of a glyph or cell, larger ones come from corrupt files
+pub(crate) const MAX_GLYPH_SIZE: i32 = 256;
+
+/// the baseline of fonts that don't know it, most fonts have a quarter of the height below it
+pub(crate) fn guess_baseline(height: i32) -> i32 {
+    height * 3 / 4
+}
+
+/// the advance most glyphs have, the smaller one if there are as many of two
+fn normal_advance(glyphs: &[InkGlyph]) -> i32 {
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for glyph in glyphs {
+        *counts.entry(glyph.advance).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(advance, count)| (count, -advance))
+        .map_or(0, |(advance, _)| advance)
+}
+
+/// a glyph as font files store it: only its ink box, placed relative to the origin on the
+/// baseline with y going up
+pub(crate) struct InkGlyph {
+    pub ch: i32,
+    pub size: Vector2i,
+    /// the bottom left corner of the ink box
+    pub offset: Vector2i,
+    /// the horizontal distance to the origin of the next glyph
+    pub advance: i32,
+    /// `size.y` rows of `size.x` pixels, from top to bottom
+    pub rows: Vec<Vec<bool>>,
+}
+
+impl InkGlyph {
+    /// the size, offset and advance are ones a font can have, without ink the size is 0
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let (size, offset) = (self.size, self.offset);
+        let valid = (0..=MAX_GLYPH_SIZE).contains(&size.x)
+            && (0..=MAX_GLYPH_SIZE).contains(&size.y)
+            && (-MAX_GLYPH_SIZE..=MAX_GLYPH_SIZE).contains(&offset.x)
+            && (-MAX_GLYPH_SIZE..=MAX_GLYPH_SIZE).contains(&offset.y)
+            && (0..=MAX_GLYPH_SIZE).contains(&self.advance);
+        match valid {
+            true => Ok(()),
+            false => Err(format!(
+                "invalid glyph {}: size {}x{}, offset {},{}, advance {}",
+                self.ch, size.x, size.y, offset.x, offset.y, self.advance
+            )),
+        }
+    }
+}
+
+impl BitmapFont {
+    /// place the ink boxes into cells as high as `ascent` + `descent` and as wide as the most
+    /// common advance
+    ///
+    /// Glyphs smaller than the cell stand on the baseline at their offset, glyphs that reach
+    /// left of the origin move the origin of all glyphs to the right. Ink outside the cell is
+    /// cut off. Glyphs that advance further than the cell, like the double width CJK glyphs of
+    /// Unifont, are skipped, so that they can come from a fallback font. Sizes above
+    /// `MAX_GLYPH_SIZE` and negative ones are errors of a corrupt file.
+    pub(crate) fn from_ink(glyphs: &[InkGlyph], ascent: i32, descent: i32) -> Result<Self, String> {
+        for glyph in glyphs {
+            glyph.check()?;
+        }
+        if !(0..=MAX_GLYPH_SIZE).contains(&ascent) || !(0..=MAX_GLYPH_SIZE).contains(&descent) {
+            return Err(format!("invalid ascent {} and descent {}", ascent, descent));
+        }
+        let width = normal_advance(glyphs);
+        let glyphs: Vec<&InkGlyph> = glyphs
+            .iter()
+            .filter(|glyph| glyph.advance <= width)
+            .collect();
+        let left = glyphs
+            .iter()
+            .map(|glyph| glyph.offset.x)
+            .min()
+            .unwrap_or(0)
+            .min(0);
+        let char_size = Vector2i::new(width, ascent + descent);
+        if !(1..=MAX_GLYPH_SIZE).contains(&char_size.x)
+            || !(1..=MAX_GLYPH_SIZE).contains(&char_size.y)
+        {
+            return Err(format!("invalid cell size {}x{}", char_size.x, char_size.y));
+        }
+        let glyphs = glyphs
+            .iter()
+            .map(|glyph| {
+                let mut pixels = vec![0; (char_size.x * char_size.y) as usize];
+                let top = ascent - glyph.offset.y - glyph.size.y;
+                for (j, row) in glyph.rows.iter().enumerate() {
+                    for (i, &ink) in row.iter().enumerate() {
+                        let x = glyph.offset.x - left + i as i32;
+                        let y = top + j as i32;
+                        let inside = (0..char_size.x).contains(&x) && (0..char_size.y).contains(&y);
+                        if ink && inside {
+                            pixels[(y * char_size.x + x) as usize] = 255;
+                        }
+                    }
+                }
+                Glyph {
+                    ch: glyph.ch,
+                    pixels,
+                }
+            })
+            .collect();
+        Ok(Self {
+            char_size,
+            baseline: ascent,
+            glyphs,
+        })
+    }
+
+    /// the glyph for `ch`
+    pub fn glyph(&self, ch: i32) -> Option<&Glyph> {
+        self.glyphs.iter().find(|glyph| glyph.ch == ch)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// a font file in `resources/`
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../resources")
+            .join(name)
+    }
+
+    /// the pixels of `glyph` as lines of `#` and `.`
+    pub(crate) fn render(font: &BitmapFont, ch: i32) -> Vec<String> {
+        let glyph = font.glyph(ch).expect("no glyph");
+        glyph
+            .pixels
+            .chunks(font.char_size.x as usize)
+            .map(|row| row.iter().map(|&p| if p > 0 { '#' } else { '.' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_ink() {
+        let ink = |ch, offset: (i32, i32), rows: &[&str]| InkGlyph {
+            ch,
+            size: Vector2i::new(rows[0].len() as i32, rows.len() as i32),
+            offset: Vector2i::new(offset.0, offset.1),
+            advance: 4,
+            rows: rows
+                .iter()
+                .map(|row| row.chars().map(|c| c == '#').collect())
+                .collect(),
+        };
+        let glyphs = [
+            ink('-' as i32, (0, 1), &["###"]),
+            ink('j' as i32, (-1, -1), &["..#", "..#", "##."]),
+        ];
+        let font = BitmapFont::from_ink(&glyphs, 3, 1).unwrap();
+        assert_eq!(font.char_size, Vector2i::new(4, 4));
+        assert_eq!(font.baseline, 3);
+        assert_eq!(render(&font, '-' as i32), ["....", ".###", "....", "...."]);
+        assert_eq!(render(&font, 'j' as i32), ["....", "..#.", "..#.", "##.."]);
+    }
+
+    #[test]
+    fn test_from_ink_errors() {
+        let ink = |size: (i32, i32), offset: (i32, i32), advance| InkGlyph {
+            ch: 'A' as i32,
+            size: Vector2i::new(size.0, size.1),
+            offset: Vector2i::new(offset.0, offset.1),
+            advance,
+            rows: vec![],
+        };
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 4)], 4, 0).is_ok());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 4)], -20, 4).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 4)], 4, -4).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 4)], 0, 0).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 4)], i32::MAX, 1).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 0)], 4, 0).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), 1000)], 4, 0).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (0, 0), i32::MIN)], 4, 0).is_err());
+        assert!(BitmapFont::from_ink(&[ink((-1, 4), (0, 0), 4)], 4, 0).is_err());
+        assert!(BitmapFont::from_ink(&[ink((4, 4), (i32::MIN, 0), 4)], 4, 0).is_err());
+        assert!(BitmapFont::from_ink(&[], 4, 0).is_err());
+    }
+
+    #[test]
+    fn test_from_ink_mixed_width() {
+        let text = std::fs::read_to_string(fixture("test_mixed_4x6.bdf")).unwrap();
+        let font = crate::bdf::parse(&text).unwrap();
+        assert_eq!(font.char_size, Vector2i::new(4, 6));
+        let chars: Vec<i32> = font.glyphs.iter().map(|glyph| glyph.ch).collect();
+        assert_eq!(chars, ['I' as i32, 'O' as i32, '─' as i32]);
+        #[rustfmt::skip]
+        assert_eq!(render(&font, 'O' as i32), [
+            "###.",
+            "#.#.",
+            "#.#.",
+            "#.#.",
+            "###.",
+            "....",
+        ]);
+    }
+}
@@ -1,59 +1,181 @@
+//! bitmap fonts: glyph atlases of 16x16 cells, loaded from a PNG per font and size, imported
+//! from BDF, PCF and Linux console PSF files, or rasterized from TrueType and OpenType fonts
+//!
+//! Page 0 of the atlas is the PNG file. Chars it doesn't have are looked up in the fallback
+//! fonts by `load_missing_glyphs`, which copies their glyphs into slots of more pages. Until then,
+//! or if no fallback has the char, the missing glyph box is drawn.
+//!
+//! The sidecar of the PNG has the chars of its slots and the metrics, see `metadata`.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
 use sfml::graphics::{
     Color, Image, IntRect, RenderTarget, RenderWindow, Sprite, Texture, Transformable,
 };
 use sfml::system::{Vector2f, Vector2i, Vector2u};
 use sfml::SfBox;
 
+mod bdf;
+mod bitmap;
+mod metadata;
+mod pcf;
+mod psf;
+mod ttf;
+
+pub use bitmap::{BitmapFont, Glyph};
+pub use metadata::Metadata;
+pub use ttf::RasterOptions;
+
 pub const NUM_CHARS: i32 = 256;
 pub const NUM_COLS: i32 = 16;
 pub const NUM_ROWS: i32 = NUM_CHARS / NUM_COLS;
 pub const NUM_CHARS_IGNORED: i32 = 32;
 pub const NUM_ROWS_IGNORED: i32 = NUM_CHARS_IGNORED / NUM_COLS;
+/// the slot of the box drawn for chars without a glyph, the first slot of page 1
+pub const MISSING_GLYPH: i32 = NUM_CHARS;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const PCF_MAGIC: &[u8] = b"\x01fcp";
+const BDF_MAGIC: &[u8] = b"STARTFONT";
+/// TrueType, OpenType with CFF outlines, old Apple TrueType and collections
+const TTF_MAGICS: [&[u8]; 4] = [b"\x00\x01\x00\x00", b"OTTO", b"true", b"ttcf"];
+
+/// unpack `data` if it is gzip compressed
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(data.to_vec());
+    }
+    let mut unpacked = vec![];
+    GzDecoder::new(data)
+        .read_to_end(&mut unpacked)
+        .map_err(|e| format!("gzip: {}", e))?;
+    Ok(unpacked)
+}
+
+/// `NUM_CHARS` glyph slots in one image and its texture
+pub struct Page {
+    pub image: Image,
+    texture: OnceCell<SfBox<Texture>>,
+}
+
+impl Page {
+    fn new(char_size: Vector2i) -> Self {
+        Self::from_image(Image::new(
+            (char_size.x * NUM_COLS) as u32,
+            (char_size.y * NUM_ROWS) as u32,
+        ))
+    }
+
+    fn from_image(image: Image) -> Self {
+        Self {
+            image,
+            texture: OnceCell::new(),
+        }
+    }
+
+    /// The texture is only created when it is first used, so that fonts can be loaded and
+    /// rasterized on the CPU without a GPU context.
+    pub fn texture(&self) -> &Texture {
+        self.texture.get_or_init(|| {
+            let mut texture = Texture::new().expect("Failed to create texture");
+            texture
+                .load_from_image(&self.image, IntRect::default())
+                .expect("Failed to load texture");
+            texture
+        })
+    }
+
+    fn update_texture(&mut self) {
+        if let Some(texture) = self.texture.get_mut() {
+            unsafe { texture.update_from_image(&self.image, 0, 0) };
+        }
+    }
+}
 
 pub struct Font {
     pub name: String,
     pub char_size: Vector2i,
-    pub image: Image,
-    pub texture: SfBox<Texture>,
-    pub char2idx: Vec<i32>,
+    /// the first row below the baseline
+    pub baseline: i32,
+    /// the first row of the underline
+    pub underline_position: i32,
+    pub underline_thickness: i32,
+    /// the width of the vertical strokes
+    pub stroke_width: i32,
+    /// the names of the bold and italic variants in the sidecar, kept so that `metadata` writes
+    /// them back, bold text is drawn by smearing the glyphs by `stroke_width`
+    pub bold: Option<String>,
+    pub italic: Option<String>,
+    pub pages: Vec<Page>,
+    /// the slot of each char, chars without a glyph map to `MISSING_GLYPH` once they were
+    /// looked up
+    pub char2idx: HashMap<i32, i32>,
+    /// the char of each slot, 0 if the slot is unused
     pub idx2char: Vec<i32>,
-    pub max_char: i32,
+    /// searched in order for chars this font has no glyph for
+    fallbacks: Vec<Font>,
+    /// the outlines of a TrueType or OpenType font, its glyphs are rasterized when they are
+    /// first drawn
+    outlines: Option<ttf::Outlines>,
+    /// chars drawn without a glyph since the last `load_missing_glyphs`
+    requested: RefCell<BTreeSet<i32>>,
 }
 
 impl Font {
+    /// a font with the built in layout and metrics
     pub fn new(name: &str, char_size: Vector2i) -> Self {
-        let image = Image::new(
-            (char_size.x * NUM_COLS) as u32,
-            (char_size.y * NUM_ROWS) as u32,
-        );
-        let mut texture = Texture::new().expect("Failed to create texture");
-        texture
-            .load_from_image(&image, IntRect::default())
-            .expect("Failed to load texture");
-
-        let mut char2idx = vec![];
-        let mut idx2char = vec![];
-        for ch in 0..128 {
-            char2idx.push(ch);
-            idx2char.push(ch);
-        }
-        let extended = "äöüÄÖÜß‘•〈〉\u{27e8}\u{27e9}";
-        let max_char = extended.chars().map(|ch| ch as i32).max().unwrap();
-        char2idx.resize(max_char as usize + 1, 0);
-        for (i, ch) in extended.chars().enumerate() {
-            let idx = i as i32 + 128;
-            char2idx[ch as usize] = idx;
-            idx2char.push(ch as i32);
-        }
+        let metadata = Metadata::new(char_size, bitmap::guess_baseline(char_size.y));
+        Self::with_metadata(name, char_size, metadata)
+    }
 
-        Self {
+    /// a font with the slots and metrics of `metadata`
+    pub fn with_metadata(name: &str, char_size: Vector2i, metadata: Metadata) -> Self {
+        let mut idx2char = metadata.slots;
+        idx2char.resize(NUM_CHARS as usize, 0);
+        // slot 0 is char 0, other slots of char 0 are unused
+        let char2idx = idx2char
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &ch)| ch != 0 || idx == 0)
+            .map(|(idx, &ch)| (ch, idx as i32))
+            .collect();
+
+        let mut font = Self {
             name: name.to_string(),
             char_size,
-            image,
-            texture,
+            baseline: metadata.baseline,
+            underline_position: metadata.underline_position,
+            underline_thickness: metadata.underline_thickness,
+            stroke_width: metadata.stroke_width,
+            bold: metadata.bold,
+            italic: metadata.italic,
+            pages: vec![Page::new(char_size)],
             char2idx,
             idx2char,
-            max_char,
+            fallbacks: vec![],
+            outlines: None,
+            requested: RefCell::new(BTreeSet::new()),
+        };
+        let missing = font.allocate_slot(0);
+        font.draw_missing_glyph(missing);
+        font
+    }
+
+    /// the metadata of the font, the slots of page 0 and the metrics
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            baseline: self.baseline,
+            underline_position: self.underline_position,
+            underline_thickness: self.underline_thickness,
+            stroke_width: self.stroke_width,
+            bold: self.bold.clone(),
+            italic: self.italic.clone(),
+            slots: self.idx2char[..NUM_CHARS as usize].to_vec(),
         }
     }
 
@@ -61,78 +183,381 @@ impl Font {
         config::Config::get_resource_path(&format!("{}_{}x{}.png", name, char_size.x, char_size.y))
     }
 
+    fn metadata_filename(name: &str, char_size: Vector2i) -> String {
+        config::Config::get_resource_path(&format!("{}_{}x{}.txt", name, char_size.x, char_size.y))
+    }
+
+    /// the PNG and its sidecar, without the sidecar the font has the built in layout and metrics
     pub fn load(name: &str, char_size: Vector2i) -> std::io::Result<Font> {
-        let mut font = Self::new(name, char_size);
+        let metadata_filename = Self::metadata_filename(name, char_size);
+        let mut font = match std::fs::read_to_string(&metadata_filename) {
+            Ok(text) => {
+                let metadata = Metadata::parse(&text, char_size, &metadata_filename);
+                Self::with_metadata(name, char_size, metadata)
+            }
+            Err(_) => Self::new(name, char_size),
+        };
         let filename = Self::filename(name, char_size);
-        font.image = Image::from_file(&filename).ok_or(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("File not found: {}", filename),
-        ))?;
-        font.texture = Texture::new().expect("Failed to create texture");
-        font.texture
-            .load_from_image(&font.image, IntRect::default())
-            .expect("Failed to load texture");
+        font.pages[0] =
+            Page::from_image(Image::from_file(&filename).ok_or(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("File not found: {}", filename),
+            ))?);
         Ok(font)
     }
 
-    pub fn get_sprite(&self, ch: i32) -> Sprite {
-        //let ch = if !(0..NUM_CHARS).contains(&ch) {
-        let ch = if ch > self.max_char || self.char2idx[ch as usize] == 0 {
-            println!("Invalid character: 0x{:x}='{}'", ch, ch as u8 as char);
-            '?' as i32
+    /// a font with the glyphs of `bitmaps`, the chars of the PNG fonts keep their slot on page 0,
+    /// so that it can be saved as one, the others share a slot with an equal glyph or get one on
+    /// the pages after the missing glyph
+    pub fn from_bitmaps(name: &str, bitmaps: &BitmapFont) -> Font {
+        let metadata = Metadata::new(bitmaps.char_size, bitmaps.baseline);
+        let mut font = Self::with_metadata(name, bitmaps.char_size, metadata);
+        let layout = std::mem::take(&mut font.char2idx);
+        font.idx2char[..NUM_CHARS as usize].fill(0);
+        let mut slots: HashMap<&[u8], i32> = HashMap::new();
+        for glyph in &bitmaps.glyphs {
+            let shared = slots.get(glyph.pixels.as_slice()).copied();
+            let idx = match (layout.get(&glyph.ch), shared) {
+                (Some(&idx), _) => {
+                    font.idx2char[idx as usize] = glyph.ch;
+                    idx
+                }
+                (None, Some(idx)) => idx,
+                (None, None) => font.allocate_slot(glyph.ch),
+            };
+            font.char2idx.insert(glyph.ch, idx);
+            if shared != Some(idx) {
+                font.set_glyph_pixels(idx, &glyph.pixels);
+                slots.entry(&glyph.pixels).or_insert(idx);
+            }
+        }
+        font
+    }
+
+    /// the glyphs of all chars the font has, in the order of the chars
+    pub fn bitmaps(&self) -> BitmapFont {
+        let mut chars: Vec<(i32, i32)> = self
+            .char2idx
+            .iter()
+            .filter(|(_, &idx)| idx != MISSING_GLYPH)
+            .map(|(&ch, &idx)| (ch, idx))
+            .collect();
+        chars.sort();
+        let glyphs = chars
+            .into_iter()
+            .map(|(ch, idx)| Glyph {
+                ch,
+                pixels: self.glyph_pixels(idx, self.char_size),
+            })
+            .collect();
+        BitmapFont {
+            char_size: self.char_size,
+            baseline: self.baseline,
+            glyphs,
+        }
+    }
+
+    /// the name of a font imported from `path`, without the extensions of font files and
+    /// compression, like `6x13` for `6x13.pcf.gz`
+    fn file_name(path: &Path) -> String {
+        let mut name = path
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+        for extension in [".gz", ".psf", ".pcf", ".bdf", ".ttf", ".otf"] {
+            if let Some(stem) = name.strip_suffix(extension) {
+                name = stem.to_string();
+            }
+        }
+        name
+    }
+
+    fn invalid_data(path: &Path, message: String) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), message),
+        )
+    }
+
+    /// import a BDF font, the cell is as wide as the advance of most glyphs and as high as the
+    /// ascent and descent of the font, wider glyphs are skipped
+    pub fn from_bdf(path: &Path) -> std::io::Result<Font> {
+        let text = std::fs::read_to_string(path)?;
+        let bitmaps = bdf::parse(&text).map_err(|e| Self::invalid_data(path, e))?;
+        Ok(Self::from_bitmaps(&Self::file_name(path), &bitmaps))
+    }
+
+    /// import a PCF font, plain or gzip compressed, like `from_bdf`
+    pub fn from_pcf(path: &Path) -> std::io::Result<Font> {
+        let data = std::fs::read(path)?;
+        let data = decompress(&data).map_err(|e| Self::invalid_data(path, e))?;
+        let bitmaps = pcf::parse(&data).map_err(|e| Self::invalid_data(path, e))?;
+        Ok(Self::from_bitmaps(&Self::file_name(path), &bitmaps))
+    }
+
+    /// import a Linux console font, a `.psf` or `.psf.gz` file
+    pub fn from_psf(path: &Path) -> std::io::Result<Font> {
+        let data = std::fs::read(path)?;
+        let bitmaps = psf::parse(&data).map_err(|e| Self::invalid_data(path, e))?;
+        Ok(Self::from_bitmaps(&Self::file_name(path), &bitmaps))
+    }
+
+    /// rasterize a TrueType or OpenType font at `char_size`, ASCII and Latin-1 right away and
+    /// the other chars when they are first drawn
+    pub fn from_ttf(
+        path: &Path,
+        char_size: Vector2i,
+        options: RasterOptions,
+    ) -> std::io::Result<Font> {
+        let data = std::fs::read(path)?;
+        Self::from_ttf_data(&Self::file_name(path), data, char_size, options)
+            .map_err(|e| Self::invalid_data(path, e))
+    }
+
+    fn from_ttf_data(
+        name: &str,
+        data: Vec<u8>,
+        char_size: Vector2i,
+        options: RasterOptions,
+    ) -> Result<Font, String> {
+        let outlines = ttf::Outlines::new(data, options)?;
+        let glyphs = (' ' as i32..NUM_CHARS)
+            .filter_map(|ch| {
+                let pixels = outlines.glyph(ch, char_size)?;
+                Some(Glyph { ch, pixels })
+            })
+            .collect();
+        let bitmaps = BitmapFont {
+            char_size,
+            baseline: outlines.baseline(char_size),
+            glyphs,
+        };
+        let mut font = Self::from_bitmaps(name, &bitmaps);
+        font.outlines = Some(outlines);
+        Ok(font)
+    }
+
+    /// import or rasterize the font file at `path` by the magic bytes at its start, after
+    /// unpacking it if it is gzip compressed, `char_size` is only used for outline fonts, bitmap
+    /// fonts have their own size
+    pub fn from_file(
+        path: &Path,
+        char_size: Vector2i,
+        options: RasterOptions,
+    ) -> std::io::Result<Font> {
+        let data = std::fs::read(path)?;
+        let data = decompress(&data).map_err(|e| Self::invalid_data(path, e))?;
+        let name = Self::file_name(path);
+        let bitmaps = if data.starts_with(PCF_MAGIC) {
+            pcf::parse(&data)
+        } else if data.starts_with(&psf::PSF1_MAGIC) || data.starts_with(&psf::PSF2_MAGIC) {
+            psf::parse(&data)
+        } else if data.starts_with(BDF_MAGIC) {
+            bdf::parse(&String::from_utf8_lossy(&data))
+        } else if TTF_MAGICS.iter().any(|magic| data.starts_with(magic)) {
+            return Self::from_ttf_data(&name, data, char_size, options)
+                .map_err(|e| Self::invalid_data(path, e));
         } else {
-            self.char2idx[ch as usize]
+            Err("unknown font format, expected TrueType, OpenType, BDF, PCF or PSF".to_string())
         };
+        let bitmaps = bitmaps.map_err(|e| Self::invalid_data(path, e))?;
+        Ok(Self::from_bitmaps(&name, &bitmaps))
+    }
 
-        let x = (ch % NUM_COLS) * self.char_size.x;
-        let y = (ch / NUM_COLS) * self.char_size.y;
+    /// export the glyphs of all chars the font has, the glyphs copied from fallback fonts too
+    pub fn to_bdf(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, bdf::write(&self.bitmaps(), &self.name))
+    }
 
-        let mut sprite = Sprite::with_texture(&self.texture);
-        sprite.set_texture_rect(IntRect::new(x, y, self.char_size.x, self.char_size.y));
-        sprite
+    /// the size of the image of one page
+    pub fn page_size(&self) -> Vector2i {
+        Vector2i::new(self.char_size.x * NUM_COLS, self.char_size.y * NUM_ROWS)
     }
 
-    pub fn get_sprite_full(&self) -> Sprite {
-        let mut sprite = Sprite::with_texture(&self.texture);
-        let y_offset = NUM_ROWS_IGNORED * self.char_size.y; // ' '=32 is the first character
+    /// the texture of page 0, the glyphs of the font file
+    pub fn texture(&self) -> &Texture {
+        self.pages[0].texture()
+    }
+
+    /// the page of slot `idx` and the top left corner of the glyph in that page
+    fn slot_position(&self, idx: i32) -> (usize, Vector2i) {
+        let local = idx % NUM_CHARS;
+        (
+            (idx / NUM_CHARS) as usize,
+            Vector2i::new(
+                (local % NUM_COLS) * self.char_size.x,
+                (local / NUM_COLS) * self.char_size.y,
+            ),
+        )
+    }
+
+    /// append a slot for `ch`, a new page is added when the last one is full
+    fn allocate_slot(&mut self, ch: i32) -> i32 {
+        let idx = self.idx2char.len() as i32;
+        if idx / NUM_CHARS >= self.pages.len() as i32 {
+            self.pages.push(Page::new(self.char_size));
+        }
+        self.idx2char.push(ch);
+        idx
+    }
+
+    /// an outlined box in slot `idx`
+    fn draw_missing_glyph(&mut self, idx: i32) {
+        let (w, h) = (self.char_size.x, self.char_size.y);
+        let stroke = (w / 10).max(1);
+        let (left, right) = (w / 8, w - 1 - w / 8);
+        let (top, bottom) = (h / 8, h - 1 - h / 8);
+        for y in top..=bottom {
+            for x in left..=right {
+                let edge = x < left + stroke
+                    || x > right - stroke
+                    || y < top + stroke
+                    || y > bottom - stroke;
+                if edge {
+                    self.set_pixel(idx, x, y, 255);
+                }
+            }
+        }
+    }
+
+    /// the slot of the glyph for `ch`, `MISSING_GLYPH` if the font has none (yet)
+    pub fn glyph_index(&self, ch: i32) -> i32 {
+        match self.char2idx.get(&ch) {
+            Some(&idx) => idx,
+            None => {
+                self.requested.borrow_mut().insert(ch);
+                MISSING_GLYPH
+            }
+        }
+    }
+
+    /// top left corner of the glyph for `ch` in the atlas, with the pages stacked vertically
+    pub fn glyph_position(&self, ch: i32) -> Vector2i {
+        let (page, position) = self.slot_position(self.glyph_index(ch));
+        position + Vector2i::new(0, page as i32 * self.page_size().y)
+    }
+
+    pub fn get_sprite(&self, ch: i32) -> Sprite<'_> {
+        let (page, position) = self.slot_position(self.glyph_index(ch));
+        let mut sprite = Sprite::with_texture(self.pages[page].texture());
         sprite.set_texture_rect(IntRect::new(
-            0,
-            y_offset,
-            self.image.size().x as i32,
-            self.image.size().y as i32,
+            position.x,
+            position.y,
+            self.char_size.x,
+            self.char_size.y,
         ));
         sprite
     }
 
+    pub fn get_sprite_full(&self) -> Sprite<'_> {
+        let mut sprite = Sprite::with_texture(self.texture());
+        let y_offset = NUM_ROWS_IGNORED * self.char_size.y; // ' '=32 is the first character
+        let size = self.page_size();
+        sprite.set_texture_rect(IntRect::new(0, y_offset, size.x, size.y));
+        sprite
+    }
+
+    /// fonts that are searched, in order, for the glyphs this font doesn't have
+    pub fn add_fallback(&mut self, font: Font) {
+        self.fallbacks.push(font);
+    }
+
+    /// the pixels of a glyph for `ch` at `size`, from the atlas or the outlines of this font or
+    /// else from its fallbacks
+    fn find_glyph(&self, ch: i32, size: Vector2i) -> Option<Vec<u8>> {
+        match self.char2idx.get(&ch) {
+            Some(&idx) if idx != MISSING_GLYPH => Some(self.glyph_pixels(idx, size)),
+            _ => self
+                .outlines
+                .as_ref()
+                .and_then(|outlines| outlines.glyph(ch, size))
+                .or_else(|| {
+                    self.fallbacks
+                        .iter()
+                        .find_map(|font| font.find_glyph(ch, size))
+                }),
+        }
+    }
+
+    /// add the glyphs of the chars that were drawn as the missing glyph, rasterized from the
+    /// outlines of the font or copied from the fallback fonts and scaled to the char size of
+    /// this font
+    ///
+    /// Chars that none of the fonts have stay the missing glyph and aren't looked up again.
+    /// Returns true if glyphs were added, text drawn with this font must be redrawn then.
+    pub fn load_missing_glyphs(&mut self) -> bool {
+        let requested = std::mem::take(&mut *self.requested.borrow_mut());
+        let mut loaded = false;
+        for ch in requested {
+            let Some(pixels) = self.find_glyph(ch, self.char_size) else {
+                self.char2idx.insert(ch, MISSING_GLYPH);
+                continue;
+            };
+            let idx = self.allocate_slot(ch);
+            self.char2idx.insert(ch, idx);
+            self.set_glyph_pixels(idx, &pixels);
+            loaded = true;
+        }
+        loaded
+    }
+
+    /// copy `pixels`, a glyph at the char size row by row, into slot `idx`
+    fn set_glyph_pixels(&mut self, idx: i32, pixels: &[u8]) {
+        let (page, position) = self.slot_position(idx);
+        let page = &mut self.pages[page];
+        for (i, &p) in pixels.iter().enumerate() {
+            let x = position.x + i as i32 % self.char_size.x;
+            let y = position.y + i as i32 / self.char_size.x;
+            unsafe {
+                page.image
+                    .set_pixel(x as u32, y as u32, Color::rgb(p, p, p))
+            };
+        }
+        page.update_texture();
+    }
+
+    /// the pixels of slot `idx` row by row, scaled to `size` by nearest neighbour
+    fn glyph_pixels(&self, idx: i32, size: Vector2i) -> Vec<u8> {
+        let (page, position) = self.slot_position(idx);
+        let image = &self.pages[page].image;
+        let mut pixels = Vec::with_capacity((size.x * size.y) as usize);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let source_x = position.x + x * self.char_size.x / size.x;
+                let source_y = position.y + y * self.char_size.y / size.y;
+                pixels.push(unsafe { image.pixel_at(source_x as u32, source_y as u32) }.r);
+            }
+        }
+        pixels
+    }
+
     pub fn set_pixel(&mut self, ch: i32, x: i32, y: i32, color: u8) {
-        let x = x + (ch % NUM_COLS) * self.char_size.x;
-        let y = y + (ch / NUM_COLS) * self.char_size.y;
+        let (page, position) = self.slot_position(ch);
+        let page = &mut self.pages[page];
         unsafe {
-            self.image
-                .set_pixel(x as u32, y as u32, Color::rgb(color, color, color));
-            self.texture.update_from_image(&self.image, 0, 0)
+            page.image.set_pixel(
+                (position.x + x) as u32,
+                (position.y + y) as u32,
+                Color::rgb(color, color, color),
+            );
         };
+        page.update_texture();
     }
 
     pub fn copy_char(&mut self, source_char: i32, destination_char: i32) {
-        let source_x = (source_char % NUM_COLS) * self.char_size.x;
-        let source_y = (source_char / NUM_COLS) * self.char_size.y;
-        let destination_x = (destination_char % NUM_COLS) * self.char_size.x;
-        let destination_y = (destination_char / NUM_COLS) * self.char_size.y;
-
-        let rect = IntRect::new(source_x, source_y, self.char_size.x, self.char_size.y);
-        let copy = self.image.clone();
-        unsafe {
-            self.image.copy_image(
-                &copy,
-                destination_x as u32,
-                destination_y as u32,
-                rect,
-                false,
-            );
+        let (source_page, source) = self.slot_position(source_char);
+        let (destination_page, destination) = self.slot_position(destination_char);
 
-            self.texture.update_from_image(&self.image, 0, 0);
-        }
+        let rect = IntRect::new(source.x, source.y, self.char_size.x, self.char_size.y);
+        let copy = self.pages[source_page].image.clone();
+        let page = &mut self.pages[destination_page];
+        page.image.copy_image(
+            &copy,
+            destination.x as u32,
+            destination.y as u32,
+            rect,
+            false,
+        );
+        page.update_texture();
     }
 
     pub fn make_all_bold(&mut self) {
@@ -142,12 +567,13 @@ impl Font {
     }
 
     pub fn make_bold(&mut self, ch: i32) {
-        let x = (ch % NUM_COLS) * self.char_size.x;
-        let y = (ch / NUM_COLS) * self.char_size.y;
+        let (page, Vector2i { x, y }) = self.slot_position(ch);
         let width = self.char_size.x;
         let height = self.char_size.y;
+        let page = &mut self.pages[page];
 
-        let copy = self.image.clone();
+        let copy = page.image.clone();
+        let size = copy.size();
         unsafe {
             for j in 0..height {
                 for i in 0..(width - 1) {
@@ -157,9 +583,9 @@ impl Font {
                     let dest_x = x + i;
                     let dest_y = y + j;
                     if (source_x < 0)
-                        || (source_x >= self.image.size().x as i32)
+                        || (source_x >= size.x as i32)
                         || (source_y < 0)
-                        || (source_y >= self.image.size().y as i32)
+                        || (source_y >= size.y as i32)
                     {
                         println!(
                             "Invalid source: ch={}, x={}, y={}, i={}, j={}",
@@ -167,15 +593,15 @@ impl Font {
                         );
                     }
                     if (dest_x < 0)
-                        || (dest_x >= self.image.size().x as i32)
+                        || (dest_x >= size.x as i32)
                         || (dest_y < 0)
-                        || (dest_y >= self.image.size().y as i32)
+                        || (dest_y >= size.y as i32)
                     {
                         println!("Invalid dest: {}, {}", dest_x, dest_y);
                     }
                     let color = copy.pixel_at((x + i + 1) as u32, (y + j) as u32);
                     if color.r > 0 {
-                        self.image.set_pixel(
+                        page.image.set_pixel(
                             (x + i) as u32,
                             (y + j) as u32,
                             Color::rgb(color.r, color.g, color.b),
@@ -183,57 +609,56 @@ impl Font {
                     }
                 }
             }
-            self.texture.update_from_image(&self.image, 0, 0);
+            page.update_texture();
         }
     }
 
     pub fn flip_char_horizontal(&mut self, ch: i32) {
-        let x = (ch % NUM_COLS) * self.char_size.x;
-        let y = (ch / NUM_COLS) * self.char_size.y;
+        let (page, Vector2i { x, y }) = self.slot_position(ch);
         let width = self.char_size.x;
         let height = self.char_size.y;
+        let page = &mut self.pages[page];
 
-        let copy = self.image.clone();
+        let copy = page.image.clone();
         unsafe {
             for i in 0..width {
                 for j in 0..height {
                     let color = copy.pixel_at((x + i) as u32, (y + j) as u32);
-                    self.image.set_pixel(
+                    page.image.set_pixel(
                         (x + width - i - 1) as u32,
                         (y + j) as u32,
                         Color::rgb(color.r, color.g, color.b),
                     );
                 }
             }
-            self.texture.update_from_image(&self.image, 0, 0);
+            page.update_texture();
         }
     }
 
     pub fn flip_char_vertical(&mut self, ch: i32) {
-        let x = (ch % NUM_COLS) * self.char_size.x;
-        let y = (ch / NUM_COLS) * self.char_size.y;
+        let (page, Vector2i { x, y }) = self.slot_position(ch);
         let width = self.char_size.x;
         let height = self.char_size.y;
+        let page = &mut self.pages[page];
 
-        let copy = self.image.clone();
+        let copy = page.image.clone();
         unsafe {
             for i in 0..width {
                 for j in 0..height {
                     let color = copy.pixel_at((x + i) as u32, (y + j) as u32);
-                    self.image.set_pixel(
+                    page.image.set_pixel(
                         (x + i) as u32,
                         (y + height - j - 1) as u32,
                         Color::rgb(color.r, color.g, color.b),
                     );
                 }
             }
-            self.texture.update_from_image(&self.image, 0, 0);
+            page.update_texture();
         }
     }
 
     pub fn shift_char(&mut self, ch: i32, dx: i32, dy: i32) {
-        let x = (ch % NUM_COLS) * self.char_size.x;
-        let y = (ch / NUM_COLS) * self.char_size.y;
+        let (page, Vector2i { x, y }) = self.slot_position(ch);
         let mut source = IntRect::new(
             x - dx,
             y - dy,
@@ -245,24 +670,23 @@ impl Font {
             dest.x += (-source.left) as u32;
             source.left = 0;
         }
-        let copy = self.image.clone();
-        unsafe {
-            self.image.copy_image(&copy, dest.x, dest.y, source, false);
-            self.texture.update_from_image(&self.image, 0, 0);
-        }
+        let page = &mut self.pages[page];
+        let copy = page.image.clone();
+        page.image.copy_image(&copy, dest.x, dest.y, source, false);
+        page.update_texture();
     }
 
+    /// write page 0 and the sidecar, the glyphs copied from fallback fonts aren't saved
     pub fn save(&self) -> std::io::Result<()> {
         let filename = Self::filename(&self.name, self.char_size);
 
-        if self.image.save_to_file(&filename) {
-            Ok(())
-        } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to save image",
-            ))
+        if !self.pages[0].image.save_to_file(&filename) {
+            return Err(std::io::Error::other("Failed to save image"));
         }
+        std::fs::write(
+            Self::metadata_filename(&self.name, self.char_size),
+            self.metadata().write(),
+        )
     }
 
     pub fn draw_text(
@@ -290,3 +714,159 @@ impl Font {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font {
+        Font::new("test", Vector2i::new(8, 16))
+    }
+
+    #[test]
+    fn test_glyph_index() {
+        let font = font();
+        assert_eq!(font.glyph_index('A' as i32), 'A' as i32);
+        assert_eq!(font.glyph_index('ä' as i32), 128);
+        assert_eq!(font.glyph_index('€' as i32), MISSING_GLYPH);
+        assert_eq!(font.glyph_index(0x1f600), MISSING_GLYPH);
+        let requested: Vec<i32> = font.requested.borrow().iter().copied().collect();
+        assert_eq!(requested, vec!['€' as i32, 0x1f600]);
+        assert_eq!(font.pages.len(), 2);
+        assert_eq!(font.glyph_position('A' as i32), Vector2i::new(8, 4 * 16));
+        assert_eq!(font.glyph_position('€' as i32), Vector2i::new(0, 16 * 16));
+    }
+
+    #[test]
+    fn test_with_metadata() {
+        let text = "baseline 12\nstroke 2\nbold test_bold\nslot 0 U+0000 U+0041 U+20AC\n";
+        let metadata = Metadata::parse(text, Vector2i::new(8, 16), "test");
+        let font = Font::with_metadata("test", Vector2i::new(8, 16), metadata.clone());
+        assert_eq!(font.baseline, 12);
+        assert_eq!(font.stroke_width, 2);
+        assert_eq!(font.glyph_index('€' as i32), 2);
+        assert_eq!(font.glyph_index('ä' as i32), MISSING_GLYPH);
+        assert_eq!(font.glyph_index(0), 0);
+        assert_eq!(font.metadata(), metadata);
+    }
+
+    #[test]
+    fn test_load_missing_glyphs() {
+        let mut fallback = Font::new("fallback", Vector2i::new(4, 8));
+        fallback.char2idx.insert('€' as i32, 200);
+        let mut font = font();
+        font.add_fallback(fallback);
+
+        assert!(!font.load_missing_glyphs());
+        font.glyph_index('€' as i32);
+        font.glyph_index('☺' as i32);
+        assert!(font.load_missing_glyphs());
+        assert_eq!(font.glyph_index('€' as i32), MISSING_GLYPH + 1);
+        assert_eq!(font.idx2char[MISSING_GLYPH as usize + 1], '€' as i32);
+        // not in any font, it isn't looked up again
+        assert_eq!(font.glyph_index('☺' as i32), MISSING_GLYPH);
+        assert!(font.requested.borrow().is_empty());
+        assert!(!font.load_missing_glyphs());
+    }
+
+    #[test]
+    fn test_pages_grow() {
+        let mut fallback = font();
+        for ch in 0x4e00..0x4e00 + NUM_CHARS {
+            fallback.char2idx.insert(ch, 'x' as i32);
+        }
+        let mut font = font();
+        font.add_fallback(fallback);
+        for ch in 0x4e00..0x4e00 + NUM_CHARS {
+            font.glyph_index(ch);
+        }
+        assert!(font.load_missing_glyphs());
+        assert_eq!(font.pages.len(), 3);
+        assert_eq!(font.glyph_index(0x4e00 + NUM_CHARS - 1), 2 * NUM_CHARS);
+        assert_eq!(
+            font.glyph_position(0x4e00 + NUM_CHARS - 1),
+            Vector2i::new(0, 2 * 16 * 16)
+        );
+    }
+
+    #[test]
+    fn test_from_bdf() {
+        let path = bitmap::tests::fixture("test_6x10.bdf");
+        let font = Font::from_bdf(&path).unwrap();
+        assert_eq!(font.name, "test_6x10");
+        assert_eq!(font.char_size, Vector2i::new(6, 10));
+        assert_eq!(font.baseline, 8);
+        assert_eq!(font.glyph_index('A' as i32), 'A' as i32);
+        assert_eq!(font.glyph_index('€' as i32), MISSING_GLYPH + 1);
+        assert_eq!(font.glyph_index('B' as i32), MISSING_GLYPH);
+        let chars: Vec<i32> = font.bitmaps().glyphs.iter().map(|g| g.ch).collect();
+        assert_eq!(chars, [' ' as i32, 'A' as i32, 'g' as i32, '€' as i32]);
+
+        let font = Font::from_pcf(&bitmap::tests::fixture("test_6x10.pcf")).unwrap();
+        assert_eq!(font.glyph_index('g' as i32), 'g' as i32);
+        assert!(Font::from_pcf(&path).is_err());
+    }
+
+    #[test]
+    fn test_from_psf() {
+        let font = Font::from_psf(&bitmap::tests::fixture("test_10x12.psf.gz")).unwrap();
+        assert_eq!(font.name, "test_10x12");
+        assert_eq!(font.char_size, Vector2i::new(10, 12));
+        assert_eq!(font.glyph_index('I' as i32), 'I' as i32);
+        // the greek capital iota has the same glyph as 'I', '│' gets its own slot
+        assert_eq!(font.glyph_index('Ι' as i32), 'I' as i32);
+        assert_eq!(font.glyph_index('│' as i32), MISSING_GLYPH + 1);
+        assert_eq!(font.idx2char.len(), MISSING_GLYPH as usize + 2);
+    }
+
+    #[test]
+    fn test_from_file() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("font-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // X11 installs PCF fonts gzip compressed
+        let pcf = std::fs::read(bitmap::tests::fixture("test_6x10.pcf")).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&pcf).unwrap();
+        let path = dir.join("test_6x10.pcf.gz");
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+        let options = RasterOptions::default();
+        let font = Font::from_file(&path, Vector2i::new(1, 1), options).unwrap();
+        assert_eq!(font.name, "test_6x10");
+        assert_eq!(font.char_size, Vector2i::new(6, 10));
+        assert_eq!(font.glyph_index('g' as i32), 'g' as i32);
+        assert!(Font::from_pcf(&path).is_ok());
+
+        // the format comes from the content, not the extension
+        let path = dir.join("font.txt");
+        std::fs::copy(bitmap::tests::fixture("test_6x10.bdf"), &path).unwrap();
+        assert!(Font::from_file(&path, Vector2i::new(1, 1), options).is_ok());
+        std::fs::write(&path, "not a font").unwrap();
+        assert!(Font::from_file(&path, Vector2i::new(1, 1), options).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_ttf() {
+        let path = bitmap::tests::fixture("test.ttf");
+        let mut font =
+            Font::from_file(&path, Vector2i::new(6, 10), RasterOptions::default()).unwrap();
+        assert_eq!(font.name, "test");
+        assert_eq!(font.baseline, 8);
+        assert_eq!(font.glyph_index('H' as i32), 'H' as i32);
+        assert_eq!(font.glyph_index('A' as i32), MISSING_GLYPH);
+        // not in page 0, rasterized when it is first drawn
+        font.char2idx.remove(&('o' as i32));
+        assert_eq!(font.glyph_index('o' as i32), MISSING_GLYPH);
+        assert!(font.load_missing_glyphs());
+        assert_eq!(font.glyph_index('o' as i32), MISSING_GLYPH + 1);
+        assert_eq!(font.glyph_index('A' as i32), MISSING_GLYPH);
+        assert!(Font::from_file(
+            &path.with_extension("txt"),
+            Vector2i::new(6, 10),
+            RasterOptions::default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! the sidecar of a font PNG, `NAME_WxH.txt` next to it, with what the image doesn't tell
+//!
+//! `baseline ROW` is the first row below the baseline, `underline ROW THICKNESS` the first row
+//! of the underline and its height, `stroke N` the width of the vertical strokes, which is also
+//! how far bold text is smeared. `bold NAME` and `italic NAME` are the fonts of the variants, at
+//! the same size. `slot INDEX CHARS` sets the chars of the slots from INDEX on, CHARS are code
+//! points like `U+00E4` and ranges like `U+0020-U+007E`. Without slot lines, the slots have the
+//! built in layout.
+
+use sfml::system::Vector2i;
+
+use crate::NUM_CHARS;
+
+/// the chars of page 0 of the built in layout after ASCII
+const EXTENDED: &str = "äöüÄÖÜß‘•〈〉\u{27e8}\u{27e9}";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// the first row below the baseline
+    pub baseline: i32,
+    /// the first row of the underline
+    pub underline_position: i32,
+    pub underline_thickness: i32,
+    pub stroke_width: i32,
+    pub bold: Option<String>,
+    pub italic: Option<String>,
+    /// the char of each slot of page 0, 0 if the slot is unused
+    pub slots: Vec<i32>,
+}
+
+/// the built in layout, ASCII and then `EXTENDED`
+fn default_slots() -> Vec<i32> {
+    let mut slots: Vec<i32> = (0..128)
+        .chain(EXTENDED.chars().map(|ch| ch as i32))
+        .collect();
+    slots.resize(NUM_CHARS as usize, 0);
+    slots
+}
+
+/// a code point like `U+00E4`
+fn parse_char(value: &str) -> Result<i32, String> {
+    value
+        .strip_prefix("U+")
+        .and_then(|hex| i32::from_str_radix(hex, 16).ok())
+        .filter(|&ch| char::from_u32(ch as u32).is_some())
+        .ok_or(format!("expected a code point like U+00E4: {}", value))
+}
+
+fn parse_number(value: &str, min: i32) -> Result<i32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&n| n >= min)
+        .ok_or(format!("expected a number from {}: {}", min, value))
+}
+
+impl Metadata {
+    /// the defaults for a font with `baseline`, the stroke width is guessed from the width of
+    /// the chars and the underline is below the baseline
+    pub fn new(char_size: Vector2i, baseline: i32) -> Self {
+        let stroke_width = if char_size.x > 12 { 2 } else { 1 };
+        Self {
+            baseline,
+            underline_position: baseline.min(char_size.y - stroke_width),
+            underline_thickness: stroke_width,
+            stroke_width,
+            bold: None,
+            italic: None,
+            slots: default_slots(),
+        }
+    }
+
+    /// the defaults with the settings of `text`, the content of `file`
+    pub fn parse(text: &str, char_size: Vector2i, file: &str) -> Self {
+        let mut metadata = Self::new(char_size, crate::bitmap::guess_baseline(char_size.y));
+        let mut slots = vec![];
+        config::parse_lines(text, file, |line| metadata.parse_line(line, &mut slots));
+        if !slots.is_empty() {
+            metadata.slots = slots;
+        }
+        metadata
+    }
+
+    /// one line of the file, the slot lines go to `slots` so that they replace the built in
+    /// layout instead of adding to it
+    fn parse_line(&mut self, line: &str, slots: &mut Vec<i32>) -> Result<(), String> {
+        let Some((key, value)) = config::split_key_value(line)? else {
+            return Ok(());
+        };
+        let values: Vec<&str> = value.split_whitespace().collect();
+        match (key, values.as_slice()) {
+            ("baseline", [row]) => self.baseline = parse_number(row, 0)?,
+            ("underline", [row, thickness]) => {
+                self.underline_position = parse_number(row, 0)?;
+                self.underline_thickness = parse_number(thickness, 1)?;
+            }
+            ("stroke", [width]) => self.stroke_width = parse_number(width, 1)?,
+            ("bold", [name]) => self.bold = Some(name.to_string()),
+            ("italic", [name]) => self.italic = Some(name.to_string()),
+            ("slot", [index, chars @ ..]) if !chars.is_empty() => {
+                let mut idx = parse_number(index, 0)? as usize;
+                for item in chars {
+                    let (first, last) = match item.split_once('-') {
+                        Some((first, last)) => (parse_char(first)?, parse_char(last)?),
+                        None => (parse_char(item)?, parse_char(item)?),
+                    };
+                    for ch in first..=last {
+                        if idx >= NUM_CHARS as usize {
+                            return Err(format!("more than {} slots: {}", NUM_CHARS, line));
+                        }
+                        slots.resize(NUM_CHARS as usize, 0);
+                        slots[idx] = ch;
+                        idx += 1;
+                    }
+                }
+            }
+            ("baseline" | "underline" | "stroke" | "bold" | "italic" | "slot", _) => {
+                return Err(format!("wrong number of values: {}", line))
+            }
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// the text of the file, runs of used slots are written on one line with the consecutive
+    /// chars as ranges
+    pub fn write(&self) -> String {
+        let mut text = String::new();
+        text += &format!("baseline {}\n", self.baseline);
+        text += &format!(
+            "underline {} {}\n",
+            self.underline_position, self.underline_thickness
+        );
+        text += &format!("stroke {}\n", self.stroke_width);
+        if let Some(bold) = &self.bold {
+            text += &format!("bold {}\n", bold);
+        }
+        if let Some(italic) = &self.italic {
+            text += &format!("italic {}\n", italic);
+        }
+        // slot 0 is char 0, other slots of char 0 are unused
+        let used = |idx: usize| self.slots[idx] != 0 || idx == 0;
+        let mut idx = 0;
+        while idx < self.slots.len() {
+            if !used(idx) {
+                idx += 1;
+                continue;
+            }
+            text += &format!("slot {}", idx);
+            while idx < self.slots.len() && used(idx) {
+                let first = self.slots[idx];
+                let mut last = first;
+                while idx + 1 < self.slots.len() && used(idx + 1) && self.slots[idx + 1] == last + 1
+                {
+                    idx += 1;
+                    last += 1;
+                }
+                match first == last {
+                    true => text += &format!(" U+{:04X}", first),
+                    false => text += &format!(" U+{:04X}-U+{:04X}", first, last),
+                }
+                idx += 1;
+            }
+            text += "\n";
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Vector2i = Vector2i::new(20, 40);
+
+    #[test]
+    fn test_defaults() {
+        let metadata = Metadata::new(SIZE, 30);
+        assert_eq!(metadata.stroke_width, 2);
+        assert_eq!(
+            (metadata.underline_position, metadata.underline_thickness),
+            (30, 2)
+        );
+        assert_eq!(metadata.slots['A' as usize], 'A' as i32);
+        assert_eq!(metadata.slots[128], 'ä' as i32);
+        assert_eq!(metadata.slots[200], 0);
+        assert_eq!(Metadata::new(Vector2i::new(8, 16), 16).stroke_width, 1);
+        assert_eq!(
+            Metadata::new(Vector2i::new(8, 16), 16).underline_position,
+            15
+        );
+        assert_eq!(Metadata::parse("", SIZE, "test"), Metadata::new(SIZE, 30));
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "# test\n\
+                    baseline 32\n\
+                    underline 33 3\n\
+                    stroke 4\n\
+                    bold font_bold\n\
+                    slot 0 U+0041-U+0043 U+20AC\n\
+                    slot 16 U+00E4\n";
+        let metadata = Metadata::parse(text, SIZE, "test");
+        assert_eq!(metadata.baseline, 32);
+        assert_eq!(
+            (metadata.underline_position, metadata.underline_thickness),
+            (33, 3)
+        );
+        assert_eq!(metadata.stroke_width, 4);
+        assert_eq!(metadata.bold.as_deref(), Some("font_bold"));
+        assert_eq!(metadata.italic, None);
+        let used: Vec<(usize, i32)> = metadata
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, &ch)| ch != 0)
+            .map(|(idx, &ch)| (idx, ch))
+            .collect();
+        assert_eq!(
+            used,
+            [
+                (0, 'A' as i32),
+                (1, 'B' as i32),
+                (2, 'C' as i32),
+                (3, '€' as i32),
+                (16, 'ä' as i32)
+            ]
+        );
+        assert_eq!(Metadata::parse(&metadata.write(), SIZE, "test"), metadata);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        let mut metadata = Metadata::new(SIZE, 30);
+        let mut slots = vec![];
+        for line in [
+            "baseline",
+            "baseline -1",
+            "underline 30",
+            "stroke 0",
+            "bold",
+            "slot 0",
+            "slot 0 A",
+            "slot 0 U+D800",
+            "slot 255 U+0041-U+0042",
+            "color red",
+        ] {
+            assert!(metadata.parse_line(line, &mut slots).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let text = Metadata::new(SIZE, 30).write();
+        assert_eq!(
+            text,
+            "baseline 30\n\
+             underline 30 2\n\
+             stroke 2\n\
+             slot 0 U+0000-U+007F U+00E4 U+00F6 U+00FC U+00C4 U+00D6 U+00DC U+00DF U+2018 U+2022 \
+             U+3008-U+3009 U+27E8-U+27E9\n"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! the Portable Compiled Format, the binary form of BDF fonts that X11 installs
+//!
+//! The metrics, bitmaps, encodings and accelerators tables are read, in any byte and bit
+//! order, padding and scan unit. Compressed `.pcf.gz` files must be unpacked first.
+
+use std::collections::HashMap;
+
+use sfml::system::Vector2i;
+
+use crate::bitmap::{BitmapFont, InkGlyph};
+
+const PCF_ACCELERATORS: i32 = 1 << 1;
+const PCF_METRICS: i32 = 1 << 2;
+const PCF_BITMAPS: i32 = 1 << 3;
+const PCF_BDF_ENCODINGS: i32 = 1 << 5;
+const PCF_BDF_ACCELERATORS: i32 = 1 << 8;
+
+const PCF_GLYPH_PAD_MASK: i32 = 3;
+const PCF_BYTE_MASK: i32 = 1 << 2;
+const PCF_BIT_MASK: i32 = 1 << 3;
+const PCF_SCAN_UNIT_MASK: i32 = 3 << 4;
+const PCF_COMPRESSED_METRICS: i32 = 0x100;
+
+/// reads numbers in the byte order of a table
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .ok_or("unexpected end of file")?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or("unexpected end of file")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<i32, String> {
+        Ok(self.bytes(1)?[0] as i32)
+    }
+
+    fn i16(&mut self) -> Result<i32, String> {
+        let bytes = self.bytes(2)?.try_into().unwrap();
+        Ok(match self.big_endian {
+            true => i16::from_be_bytes(bytes),
+            false => i16::from_le_bytes(bytes),
+        } as i32)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let bytes = self.bytes(4)?.try_into().unwrap();
+        Ok(match self.big_endian {
+            true => i32::from_be_bytes(bytes),
+            false => i32::from_le_bytes(bytes),
+        })
+    }
+}
+
+/// the metrics of a glyph, relative to the origin on the baseline
+struct Metrics {
+    left: i32,
+    right: i32,
+    width: i32,
+    ascent: i32,
+    descent: i32,
+}
+
+/// the format and offset of each table
+struct Tables<'a> {
+    data: &'a [u8],
+    tables: HashMap<i32, (i32, usize)>,
+}
+
+impl<'a> Tables<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, String> {
+        let mut reader = Reader {
+            data,
+            pos: 0,
+            big_endian: false,
+        };
+        if reader.bytes(4)? != b"\x01fcp" {
+            return Err("not a PCF file".to_string());
+        }
+        let mut tables = HashMap::new();
+        for _ in 0..reader.i32()? {
+            let (kind, format, _size, offset) =
+                (reader.i32()?, reader.i32()?, reader.i32()?, reader.i32()?);
+            tables.insert(kind, (format, offset as usize));
+        }
+        Ok(Self { data, tables })
+    }
+
+    /// a reader after the format of table `kind`, the format repeated at its start is in
+    /// little endian, the rest of the table in the byte order of the format
+    fn table(&self, kind: i32) -> Result<(Reader<'a>, i32), String> {
+        let &(_, offset) = self
+            .tables
+            .get(&kind)
+            .ok_or(format!("missing table 0x{:x}", kind))?;
+        let mut reader = Reader {
+            data: self.data,
+            pos: offset,
+            big_endian: false,
+        };
+        let format = reader.i32()?;
+        reader.big_endian = format & PCF_BYTE_MASK != 0;
+        Ok((reader, format))
+    }
+
+    fn metrics(&self) -> Result<Vec<Metrics>, String> {
+        let (mut reader, format) = self.table(PCF_METRICS)?;
+        let compressed = format & PCF_COMPRESSED_METRICS != 0;
+        let count = match compressed {
+            true => reader.i16()?,
+            false => reader.i32()?,
+        };
+        (0..count)
+            .map(|_| {
+                let mut field = || match compressed {
+                    true => reader.u8().map(|n| n - 0x80),
+                    false => reader.i16(),
+                };
+                let metrics = Metrics {
+                    left: field()?,
+                    right: field()?,
+                    width: field()?,
+                    ascent: field()?,
+                    descent: field()?,
+                };
+                if !compressed {
+                    reader.i16()?; // attributes
+                }
+                Ok(metrics)
+            })
+            .collect()
+    }
+
+    /// the glyph index of each encoding
+    fn encodings(&self) -> Result<Vec<(i32, usize)>, String> {
+        let (mut reader, _) = self.table(PCF_BDF_ENCODINGS)?;
+        let (min_byte2, max_byte2) = (reader.i16()?, reader.i16()?);
+        let (min_byte1, max_byte1) = (reader.i16()?, reader.i16()?);
+        let _default_char = reader.i16()?;
+        let mut encodings = vec![];
+        for byte1 in min_byte1..=max_byte1 {
+            for byte2 in min_byte2..=max_byte2 {
+                let index = reader.i16()? as u16;
+                if index != 0xffff {
+                    encodings.push(((byte1 << 8) | byte2, index as usize));
+                }
+            }
+        }
+        Ok(encodings)
+    }
+
+    /// the ascent and descent of the font
+    fn accelerators(&self) -> Result<(i32, i32), String> {
+        let (mut reader, _) = self
+            .table(PCF_BDF_ACCELERATORS)
+            .or_else(|_| self.table(PCF_ACCELERATORS))?;
+        reader.bytes(8)?; // flags
+        Ok((reader.i32()?, reader.i32()?))
+    }
+}
+
+/// the bytes of a bitmap row with the bits in MSB first order
+fn normalize_row(row: &mut [u8], format: i32) {
+    let msb_bits = format & PCF_BIT_MASK != 0;
+    if !msb_bits {
+        for byte in row.iter_mut() {
+            *byte = byte.reverse_bits();
+        }
+    }
+    let unit = 1 << ((format & PCF_SCAN_UNIT_MASK) >> 4);
+    let msb_bytes = format & PCF_BYTE_MASK != 0;
+    if msb_bytes != msb_bits && unit > 1 {
+        for chunk in row.chunks_mut(unit) {
+            chunk.reverse();
+        }
+    }
+}
+
+/// parse the bytes of a PCF file
+pub fn parse(data: &[u8]) -> Result<BitmapFont, String> {
+    let tables = Tables::new(data)?;
+    let metrics = tables.metrics()?;
+    let (ascent, descent) = tables.accelerators()?;
+
+    let (mut reader, format) = tables.table(PCF_BITMAPS)?;
+    let count = reader.i32()? as usize;
+    let offsets = (0..count)
+        .map(|_| reader.i32().map(|offset| offset as usize))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut sizes = vec![];
+    for _ in 0..4 {
+        sizes.push(reader.i32()? as usize);
+    }
+    let pad = 1 << (format & PCF_GLYPH_PAD_MASK);
+    let bitmaps = reader.bytes(sizes[(format & PCF_GLYPH_PAD_MASK) as usize])?;
+
+    let mut glyphs = vec![];
+    for (ch, index) in tables.encodings()? {
+        let (Some(metrics), Some(&offset)) = (metrics.get(index), offsets.get(index)) else {
+            return Err(format!("invalid glyph index {}", index));
+        };
+        let size = Vector2i::new(
+            metrics.right - metrics.left,
+            metrics.ascent + metrics.descent,
+        );
+        let glyph = InkGlyph {
+            ch,
+            size,
+            offset: Vector2i::new(metrics.left, -metrics.descent),
+            advance: metrics.width,
+            rows: vec![],
+        };
+        glyph.check()?;
+        let row_bytes = ((size.x as usize).div_ceil(8)).div_ceil(pad) * pad;
+        let mut rows = vec![];
+        for y in 0..size.y as usize {
+            let row = offset
+                .checked_add(y * row_bytes)
+                .and_then(|start| bitmaps.get(start..start.checked_add(row_bytes)?))
+                .ok_or("bitmap out of range")?;
+            let mut row = row.to_vec();
+            normalize_row(&mut row, format);
+            rows.push(
+                (0..size.x as usize)
+                    .map(|x| row[x / 8] & (0x80 >> (x % 8)) != 0)
+                    .collect(),
+            );
+        }
+        glyphs.push(InkGlyph { rows, ..glyph });
+    }
+    BitmapFont::from_ink(&glyphs, ascent, descent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf;
+    use crate::bitmap::tests::fixture;
+
+    #[test]
+    fn test_parse() {
+        let font = parse(&std::fs::read(fixture("test_6x10.pcf")).unwrap()).unwrap();
+        let text = std::fs::read_to_string(fixture("test_6x10.bdf")).unwrap();
+        assert_eq!(font, bdf::parse(&text).unwrap());
+        assert!(parse(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(b"").is_err());
+        // a table at offset -1
+        let mut data = b"\x01fcp".to_vec();
+        for n in [1, PCF_METRICS, 0, 0, -1] {
+            data.extend_from_slice(&i32::to_le_bytes(n));
+        }
+        assert!(parse(&data).is_err());
+        // cut off in the table of contents and in the tables
+        let data = std::fs::read(fixture("test_6x10.pcf")).unwrap();
+        for len in [8, 40, data.len() / 2] {
+            assert!(parse(&data[..len]).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn test_normalize_row() {
+        let mut row = [0b0000_0001, 0b1000_0000];
+        normalize_row(&mut row, PCF_BYTE_MASK | PCF_BIT_MASK);
+        assert_eq!(row, [0b0000_0001, 0b1000_0000]);
+        // LSB first bits in 2 byte units of LSB first bytes
+        let mut row = [0b0000_0001, 0b1000_0000];
+        normalize_row(&mut row, 1 << 4);
+        assert_eq!(row, [0b1000_0000, 0b0000_0001]);
+        // LSB first bits, MSB first bytes
+        let mut row = [0b0000_0001, 0b1000_0000, 0, 0];
+        normalize_row(&mut row, PCF_BYTE_MASK | (1 << 4));
+        assert_eq!(row, [0b0000_0001, 0b1000_0000, 0, 0]);
+        let mut row = [0b0000_0011, 0];
+        normalize_row(&mut row, 0);
+        assert_eq!(row, [0b1100_0000, 0]);
+    }
+}
@@ -0,0 +1,240 @@
+//! the PC Screen Font formats of the Linux console, PSF1 and PSF2, plain or gzip compressed
+//!
+//! With a Unicode table, each glyph gets all chars the table lists for it, the sequences of
+//! combining chars are skipped. Without one, glyph `i` is char `i`.
+
+use sfml::system::Vector2i;
+
+use crate::bitmap::{self, BitmapFont, Glyph, MAX_GLYPH_SIZE};
+use crate::decompress;
+
+pub(crate) const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE512: u8 = 0x01;
+const PSF1_MODEHASTAB: u8 = 0x02;
+const PSF1_MODEHASSEQ: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_STARTSEQ: u16 = 0xfffe;
+
+pub(crate) const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_STARTSEQ: u8 = 0xfe;
+
+/// the chars of each glyph
+type UnicodeTable = Vec<Vec<i32>>;
+
+/// the header fields that matter, for both versions
+struct Header {
+    glyph_count: usize,
+    glyph_bytes: usize,
+    char_size: Vector2i,
+    /// the offset of the first glyph
+    start: usize,
+    has_table: bool,
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    let bytes = data.get(offset..offset + 4).ok_or("truncated header")?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// the header of either version, with a glyph size a font can have
+fn header(data: &[u8]) -> Result<Header, String> {
+    let header = parse_header(data)?;
+    let size = header.char_size;
+    if !(1..=MAX_GLYPH_SIZE).contains(&size.x) || !(1..=MAX_GLYPH_SIZE).contains(&size.y) {
+        return Err(format!("invalid glyph size {}x{}", size.x, size.y));
+    }
+    if header.glyph_bytes == 0 {
+        return Err("glyphs have 0 bytes".to_string());
+    }
+    Ok(header)
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, String> {
+    if data.starts_with(&PSF1_MAGIC) {
+        let (mode, height) = match data.get(2..4) {
+            Some(&[mode, height]) => (mode, height as i32),
+            _ => return Err("truncated header".to_string()),
+        };
+        return Ok(Header {
+            glyph_count: if mode & PSF1_MODE512 != 0 { 512 } else { 256 },
+            glyph_bytes: height as usize,
+            char_size: Vector2i::new(8, height),
+            start: 4,
+            has_table: mode & (PSF1_MODEHASTAB | PSF1_MODEHASSEQ) != 0,
+        });
+    }
+    if data.starts_with(&PSF2_MAGIC) {
+        return Ok(Header {
+            start: u32_at(data, 8)? as usize,
+            has_table: u32_at(data, 12)? & PSF2_HAS_UNICODE_TABLE != 0,
+            glyph_count: u32_at(data, 16)? as usize,
+            glyph_bytes: u32_at(data, 20)? as usize,
+            char_size: Vector2i::new(u32_at(data, 28)? as i32, u32_at(data, 24)? as i32),
+        });
+    }
+    Err("not a PSF file".to_string())
+}
+
+/// the PSF1 table: 16 bit chars per glyph, up to a separator
+fn psf1_table(data: &[u8], glyph_count: usize) -> Result<UnicodeTable, String> {
+    let mut values = data
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+    let mut table = vec![];
+    for _ in 0..glyph_count {
+        let mut chars = vec![];
+        let mut sequence = false;
+        loop {
+            match values.next().ok_or("truncated unicode table")? {
+                PSF1_SEPARATOR => break,
+                PSF1_STARTSEQ => sequence = true,
+                ch if !sequence => chars.push(ch as i32),
+                _ => {}
+            }
+        }
+        table.push(chars);
+    }
+    Ok(table)
+}
+
+/// the PSF2 table: UTF-8 chars per glyph, up to a separator
+fn psf2_table(data: &[u8], glyph_count: usize) -> Result<UnicodeTable, String> {
+    let mut entries = data.split(|&b| b == PSF2_SEPARATOR);
+    let mut table = vec![];
+    for _ in 0..glyph_count {
+        let entry = entries.next().ok_or("truncated unicode table")?;
+        // the sequences follow the single chars
+        let single = entry.split(|&b| b == PSF2_STARTSEQ).next().unwrap_or(&[]);
+        let chars = std::str::from_utf8(single).map_err(|_| "invalid UTF-8 in unicode table")?;
+        table.push(chars.chars().map(|ch| ch as i32).collect());
+    }
+    Ok(table)
+}
+
+/// parse the bytes of a PSF or gzip compressed PSF file
+pub fn parse(data: &[u8]) -> Result<BitmapFont, String> {
+    let data = decompress(data)?;
+    let header = header(&data)?;
+    let (width, height) = (header.char_size.x as usize, header.char_size.y as usize);
+    let row_bytes = width.div_ceil(8);
+    if header.glyph_bytes < row_bytes * height {
+        return Err("glyphs are smaller than their size".to_string());
+    }
+    let end = header
+        .glyph_count
+        .checked_mul(header.glyph_bytes)
+        .and_then(|size| size.checked_add(header.start))
+        .ok_or("truncated glyphs")?;
+    let glyph_data = data.get(header.start..end).ok_or("truncated glyphs")?;
+    let table = match (header.has_table, data.starts_with(&PSF1_MAGIC)) {
+        (false, _) => (0..header.glyph_count as i32).map(|ch| vec![ch]).collect(),
+        (true, true) => psf1_table(&data[end..], header.glyph_count)?,
+        (true, false) => psf2_table(&data[end..], header.glyph_count)?,
+    };
+
+    let mut glyphs = vec![];
+    for (bytes, chars) in glyph_data.chunks_exact(header.glyph_bytes).zip(table) {
+        let pixels: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                match bytes[y * row_bytes + x / 8] & (0x80 >> (x % 8)) {
+                    0 => 0,
+                    _ => 255,
+                }
+            })
+            .collect();
+        glyphs.extend(chars.into_iter().map(|ch| Glyph {
+            ch,
+            pixels: pixels.clone(),
+        }));
+    }
+    glyphs.sort_by_key(|glyph| glyph.ch);
+    glyphs.dedup_by_key(|glyph| glyph.ch);
+    Ok(BitmapFont {
+        char_size: header.char_size,
+        baseline: bitmap::guess_baseline(header.char_size.y),
+        glyphs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::tests::{fixture, render};
+
+    #[test]
+    fn test_parse_psf1() {
+        let font = parse(&std::fs::read(fixture("test_8x8.psf")).unwrap()).unwrap();
+        assert_eq!(font.char_size, Vector2i::new(8, 8));
+        assert_eq!(font.baseline, 6);
+        let chars: Vec<i32> = font.glyphs.iter().map(|glyph| glyph.ch).collect();
+        // 'A' is also the greek and cyrillic capital A, '□' is only in a sequence
+        assert_eq!(
+            chars,
+            [' ' as i32, 'A' as i32, 'Α' as i32, 'А' as i32, '█' as i32]
+        );
+        #[rustfmt::skip]
+        assert_eq!(render(&font, 'Α' as i32), [
+            "..##....",
+            ".#..#...",
+            "#....#..",
+            "######..",
+            "#....#..",
+            "#....#..",
+            "........",
+            "........",
+        ]);
+        assert_eq!(render(&font, '█' as i32), ["########"; 8]);
+    }
+
+    #[test]
+    fn test_parse_psf2_gz() {
+        let font = parse(&std::fs::read(fixture("test_10x12.psf.gz")).unwrap()).unwrap();
+        assert_eq!(font.char_size, Vector2i::new(10, 12));
+        assert_eq!(font.baseline, 9);
+        let chars: Vec<i32> = font.glyphs.iter().map(|glyph| glyph.ch).collect();
+        assert_eq!(chars, [' ' as i32, 'I' as i32, 'Ι' as i32, '│' as i32]);
+        #[rustfmt::skip]
+        assert_eq!(render(&font, 'I' as i32), [
+            "..........",
+            "##########",
+            "....##....",
+            "....##....",
+            "....##....",
+            "....##....",
+            "....##....",
+            "....##....",
+            "##########",
+            "..........",
+            "..........",
+            "..........",
+        ]);
+        assert_eq!(render(&font, '│' as i32), ["....##...."; 12]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(b"").is_err());
+        assert!(parse(&[0x36, 0x04, 0x00, 0x08, 0x00]).is_err());
+        // PSF1 with a height of 0
+        assert!(parse(&[0x36, 0x04, 0x00, 0x00]).is_err());
+        let psf2 = |height: u32, width: u32, glyph_bytes: u32, glyph_count: u32| {
+            let mut data = PSF2_MAGIC.to_vec();
+            for n in [0, 32, 0, glyph_count, glyph_bytes, height, width] {
+                data.extend_from_slice(&u32::to_le_bytes(n));
+            }
+            data.resize(64, 0);
+            parse(&data)
+        };
+        assert!(psf2(1, 1, 1, 1).is_ok());
+        assert!(psf2(0, 8, 0, 1).is_err());
+        assert!(psf2(8, 0, 0, 1).is_err());
+        assert!(psf2(1, 1, 0, 1).is_err());
+        assert!(psf2(u32::MAX, 8, 1, 1).is_err());
+        assert!(psf2(1, 1, u32::MAX, u32::MAX).is_err());
+        let text = std::fs::read(fixture("test_6x10.bdf")).unwrap();
+        assert!(parse(&text).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//! rasterize the outlines of TrueType and OpenType fonts into glyphs of any cell size
+//!
+//! The font is scaled so that its advance and its ascent plus descent fit the cell, then centered
+//! in it. Coverage is computed exactly per pixel from the area of the outline, curves are
+//! flattened into lines first.
+//!
+//! Hinting moves the horizontal and vertical edges of the outline, like the stems of `H` and the
+//! tops of `o`, to pixel boundaries and stretches the rest of the outline between them, so that
+//! small sizes stay sharp.
+
+use sfml::system::Vector2i;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// how glyphs are rasterized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterOptions {
+    /// gray pixels at the edges, or only pixels that are covered at least half
+    pub antialias: bool,
+    /// the coverage `c` of a pixel becomes `c^(1/gamma)`, a gamma above 1 makes glyphs bolder
+    pub gamma: f32,
+    /// move edges to pixel boundaries
+    pub hinting: bool,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            antialias: true,
+            gamma: 1.0,
+            hinting: true,
+        }
+    }
+}
+
+/// how the font units map to the pixels of a cell
+struct Layout {
+    scale: f32,
+    /// the pixel position of the origin, y goes down
+    origin: (f32, f32),
+}
+
+impl Layout {
+    fn new(face: &Face, char_size: Vector2i, hinting: bool) -> Self {
+        let advance = ['M', '0']
+            .into_iter()
+            .find_map(|ch| face.glyph_index(ch))
+            .or(Some(GlyphId(0)))
+            .and_then(|id| face.glyph_hor_advance(id))
+            .filter(|&advance| advance > 0)
+            .unwrap_or(face.units_per_em() / 2) as f32;
+        let (ascent, descent) = (face.ascender() as f32, -face.descender() as f32);
+        let scale = (char_size.x as f32 / advance).min(char_size.y as f32 / (ascent + descent));
+        let mut origin = (
+            (char_size.x as f32 - advance * scale) / 2.0,
+            (char_size.y as f32 - (ascent + descent) * scale) / 2.0 + ascent * scale,
+        );
+        if hinting {
+            origin = (origin.0.round(), origin.1.round());
+        }
+        Self { scale, origin }
+    }
+
+    fn to_pixels(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.origin.0 + x * self.scale,
+            self.origin.1 - y * self.scale,
+        )
+    }
+}
+
+/// the outlines of a font file, the glyphs are rasterized when they are needed
+pub(crate) struct Outlines {
+    data: Vec<u8>,
+    options: RasterOptions,
+}
+
+impl Outlines {
+    pub fn new(data: Vec<u8>, options: RasterOptions) -> Result<Self, String> {
+        Face::parse(&data, 0).map_err(|e| e.to_string())?;
+        Ok(Self { data, options })
+    }
+
+    fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, 0).expect("checked in new")
+    }
+
+    /// the first row below the baseline in a cell of `char_size`
+    pub fn baseline(&self, char_size: Vector2i) -> i32 {
+        let layout = Layout::new(&self.face(), char_size, self.options.hinting);
+        layout.origin.1.round() as i32
+    }
+
+    /// the pixels of the glyph for `ch` in a cell of `char_size`, None if the font has no glyph
+    pub fn glyph(&self, ch: i32, char_size: Vector2i) -> Option<Vec<u8>> {
+        let face = self.face();
+        let id = face.glyph_index(char::from_u32(ch as u32)?)?;
+        if id.0 == 0 {
+            return None;
+        }
+        let mut contours = Contours::default();
+        // glyphs without outline, like space, are empty
+        face.outline_glyph(id, &mut contours);
+
+        let layout = Layout::new(&face, char_size, self.options.hinting);
+        let mut lines = contours.lines(&layout);
+        if self.options.hinting {
+            hint(&mut lines, &contours.edges(&layout));
+        }
+        let mut canvas = Canvas::new(char_size);
+        for &(p0, p1) in &lines {
+            canvas.line(p0, p1);
+        }
+        Some(canvas.pixels(&self.options))
+    }
+}
+
+type Point = (f32, f32);
+
+/// a segment of an outline in font units
+enum Segment {
+    Line(Point),
+    Quad(Point, Point),
+    Cubic(Point, Point, Point),
+}
+
+/// collects the outline of a glyph
+#[derive(Default)]
+struct Contours {
+    contours: Vec<(Point, Vec<Segment>)>,
+}
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.contours.push(((x, y), vec![]));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(Segment::Line((x, y)));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.push(Segment::Quad((x1, y1), (x, y)));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.push(Segment::Cubic((x1, y1), (x2, y2), (x, y)));
+    }
+
+    fn close(&mut self) {}
+}
+
+/// the edges to hint, in pixels: the y of horizontal ones and the x of vertical ones
+#[derive(Default)]
+struct Edges {
+    horizontal: Vec<f32>,
+    vertical: Vec<f32>,
+}
+
+impl Contours {
+    fn push(&mut self, segment: Segment) {
+        if let Some((_, segments)) = self.contours.last_mut() {
+            segments.push(segment);
+        }
+    }
+
+    /// the outline flattened into closed polygons, in pixels
+    fn lines(&self, layout: &Layout) -> Vec<(Point, Point)> {
+        let mut lines = vec![];
+        for (start, segments) in &self.contours {
+            let start = layout.to_pixels(*start);
+            let mut from = start;
+            for segment in segments {
+                let points: Vec<Point> = match *segment {
+                    Segment::Line(p) => vec![layout.to_pixels(p)],
+                    Segment::Quad(c, p) => {
+                        let (c, p) = (layout.to_pixels(c), layout.to_pixels(p));
+                        flatten(from, &[c], p, |t| quad(from, c, p, t))
+                    }
+                    Segment::Cubic(c1, c2, p) => {
+                        let (c1, c2) = (layout.to_pixels(c1), layout.to_pixels(c2));
+                        let p = layout.to_pixels(p);
+                        flatten(from, &[c1, c2], p, |t| cubic(from, c1, c2, p, t))
+                    }
+                };
+                for to in points {
+                    lines.push((from, to));
+                    from = to;
+                }
+            }
+            lines.push((from, start));
+        }
+        lines
+    }
+
+    /// the edges where the outline is horizontal or vertical: straight lines and the ends of
+    /// curves whose tangent is horizontal or vertical
+    fn edges(&self, layout: &Layout) -> Edges {
+        let mut edges = Edges::default();
+        let mut add = |a: Point, b: Point| {
+            let (a, b) = (layout.to_pixels(a), layout.to_pixels(b));
+            if a.1 == b.1 {
+                edges.horizontal.push(a.1);
+            }
+            if a.0 == b.0 {
+                edges.vertical.push(a.0);
+            }
+        };
+        for (start, segments) in &self.contours {
+            let mut from = *start;
+            for segment in segments {
+                from = match *segment {
+                    Segment::Line(p) => {
+                        add(from, p);
+                        p
+                    }
+                    Segment::Quad(c, p) => {
+                        add(from, c);
+                        add(c, p);
+                        p
+                    }
+                    Segment::Cubic(c1, c2, p) => {
+                        add(from, c1);
+                        add(c2, p);
+                        p
+                    }
+                };
+            }
+            add(from, *start);
+        }
+        edges
+    }
+}
+
+fn quad(p0: Point, c: Point, p1: Point, t: f32) -> Point {
+    let u = 1.0 - t;
+    (
+        u * u * p0.0 + 2.0 * u * t * c.0 + t * t * p1.0,
+        u * u * p0.1 + 2.0 * u * t * c.1 + t * t * p1.1,
+    )
+}
+
+fn cubic(p0: Point, c1: Point, c2: Point, p1: Point, t: f32) -> Point {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    (
+        a * p0.0 + b * c1.0 + c * c2.0 + d * p1.0,
+        a * p0.1 + b * c1.1 + c * c2.1 + d * p1.1,
+    )
+}
+
+/// points along a curve, about every half pixel, the last one is `to`
+fn flatten(from: Point, controls: &[Point], to: Point, at: impl Fn(f32) -> Point) -> Vec<Point> {
+    let mut length = 0.0;
+    let mut previous = from;
+    for &p in controls.iter().chain([&to]) {
+        length += ((p.0 - previous.0).powi(2) + (p.1 - previous.1).powi(2)).sqrt();
+        previous = p;
+    }
+    let count = ((length * 2.0).ceil() as usize).clamp(1, 100);
+    (1..=count).map(|i| at(i as f32 / count as f32)).collect()
+}
+
+/// move the coordinates on `edges` to the nearest pixel boundary, the coordinates between two
+/// edges are interpolated, the ones outside move with the nearest edge
+fn snap(value: f32, edges: &[(f32, f32)]) -> f32 {
+    let Some(&(first, first_snapped)) = edges.first() else {
+        return value;
+    };
+    if value <= first {
+        return value + first_snapped - first;
+    }
+    for pair in edges.windows(2) {
+        let ((a, a_snapped), (b, b_snapped)) = (pair[0], pair[1]);
+        if value <= b {
+            return a_snapped + (value - a) / (b - a) * (b_snapped - a_snapped);
+        }
+    }
+    let &(last, last_snapped) = edges.last().unwrap();
+    value + last_snapped - last
+}
+
+/// the sorted edges with the positions they snap to
+fn snapped_edges(values: &[f32]) -> Vec<(f32, f32)> {
+    let mut values = values.to_vec();
+    values.sort_by(f32::total_cmp);
+    values.dedup();
+    values.into_iter().map(|v| (v, v.round())).collect()
+}
+
+fn hint(lines: &mut [(Point, Point)], edges: &Edges) {
+    let horizontal = snapped_edges(&edges.horizontal);
+    let vertical = snapped_edges(&edges.vertical);
+    let snap_point = |(x, y): Point| (snap(x, &vertical), snap(y, &horizontal));
+    for (p0, p1) in lines.iter_mut() {
+        *p0 = snap_point(*p0);
+        *p1 = snap_point(*p1);
+    }
+}
+
+/// accumulates the signed area that lines cover, one row per pixel row
+struct Canvas {
+    width: usize,
+    height: usize,
+    /// the change of coverage from each pixel to the next, with a column to spare
+    area: Vec<f32>,
+}
+
+/// the integral of `clamp(v, 0, 1)` from minus infinity to `v`
+fn ramp_integral(v: f32) -> f32 {
+    if v <= 0.0 {
+        0.0
+    } else if v <= 1.0 {
+        v * v / 2.0
+    } else {
+        v - 0.5
+    }
+}
+
+impl Canvas {
+    fn new(size: Vector2i) -> Self {
+        let (width, height) = (size.x.max(0) as usize, size.y.max(0) as usize);
+        Self {
+            width,
+            height,
+            area: vec![0.0; (width + 2) * height],
+        }
+    }
+
+    fn line(&mut self, p0: Point, p1: Point) {
+        if p0.1 == p1.1 {
+            return;
+        }
+        let (direction, top, bottom) = if p0.1 < p1.1 {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+        let dxdy = (bottom.0 - top.0) / (bottom.1 - top.1);
+        let mut y = top.1.max(0.0);
+        let end = bottom.1.min(self.height as f32);
+        let mut x = top.0 + (y - top.1) * dxdy;
+        while y < end {
+            let row = y.floor() as usize;
+            let next_y = ((row + 1) as f32).min(end);
+            let next_x = x + (next_y - y) * dxdy;
+            self.row_segment(row, x, next_x, (next_y - y) * direction);
+            (x, y) = (next_x, next_y);
+        }
+    }
+
+    /// add a part of a line within `row`, from `x0` to `x1`, `height` is its signed height
+    fn row_segment(&mut self, row: usize, x0: f32, x1: f32, height: f32) {
+        // left of the canvas, a line covers the first column completely
+        let width = self.width as f32;
+        let (x0, x1) = (x0.clamp(0.0, width), x1.clamp(0.0, width));
+        let (left, right) = (x0.min(x1), x0.max(x1));
+        // the part of pixel `i` right of the line, averaged over the line
+        let covered = |i: usize| {
+            let edge = i as f32 + 1.0;
+            if right - left < 1e-6 {
+                (edge - left).clamp(0.0, 1.0)
+            } else {
+                (ramp_integral(edge - left) - ramp_integral(edge - right)) / (right - left)
+            }
+        };
+        let area = &mut self.area[row * (self.width + 2)..(row + 1) * (self.width + 2)];
+        let first = left.floor() as usize;
+        let last = (right.ceil() as usize).min(self.width);
+        let mut previous = 0.0;
+        for (i, area) in area.iter_mut().enumerate().take(last + 1).skip(first) {
+            let covered = covered(i);
+            *area += height * (covered - previous);
+            previous = covered;
+        }
+    }
+
+    /// the coverage of each pixel, row by row, as 0 to 255
+    fn pixels(&self, options: &RasterOptions) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for row in self.area.chunks(self.width + 2) {
+            let mut coverage = 0.0;
+            for area in &row[..self.width] {
+                coverage += area;
+                let c = f32::min(f32::abs(coverage), 1.0);
+                let c = match options.antialias {
+                    true => c.powf(1.0 / options.gamma),
+                    false => (c >= 0.5) as u8 as f32,
+                };
+                pixels.push((c * 255.0).round() as u8);
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::tests::fixture;
+
+    fn outlines(options: RasterOptions) -> Outlines {
+        Outlines::new(std::fs::read(fixture("test.ttf")).unwrap(), options).unwrap()
+    }
+
+    /// the pixels as lines of `#` for at least half coverage, `+` for less and `.` for none
+    fn render(pixels: &[u8], width: usize) -> Vec<String> {
+        pixels
+            .chunks(width)
+            .map(|row| {
+                row.iter()
+                    .map(|&p| match p {
+                        0 => '.',
+                        1..128 => '+',
+                        _ => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_glyph() {
+        let size = Vector2i::new(6, 10);
+        let outlines = outlines(RasterOptions::default());
+        assert_eq!(outlines.baseline(size), 8);
+        assert_eq!(outlines.glyph('x' as i32, size), None);
+        assert_eq!(outlines.glyph(' ' as i32, size), Some(vec![0; 60]));
+        // overlapping contours
+        #[rustfmt::skip]
+        assert_eq!(render(&outlines.glyph('H' as i32, size).unwrap(), 6), [
+            "......",
+            "#...#.",
+            "#...#.",
+            "#...#.",
+            "#####.",
+            "#...#.",
+            "#...#.",
+            "#...#.",
+            "......",
+            "......",
+        ]);
+        // quadratic curves with a hole, without hinting as all its edges are on half pixels
+        let outlines = self::outlines(RasterOptions {
+            hinting: false,
+            ..RasterOptions::default()
+        });
+        let o = outlines.glyph('o' as i32, size).unwrap();
+        #[rustfmt::skip]
+        assert_eq!(render(&o, 6), [
+            "......",
+            "......",
+            "......",
+            "+####+",
+            "+####+",
+            "+#++#+",
+            "+####+",
+            "+####+",
+            "......",
+            "......",
+        ]);
+        for (top, bottom) in [(3, 7), (4, 6)] {
+            assert_eq!(o[top * 6..top * 6 + 6], o[bottom * 6..bottom * 6 + 6]);
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let size = Vector2i::new(6, 10);
+        let glyph = |options| {
+            let pixels = outlines(options).glyph('-' as i32, size).unwrap();
+            pixels[4 * 6..6 * 6].to_vec()
+        };
+        let options = RasterOptions {
+            antialias: true,
+            gamma: 1.0,
+            hinting: false,
+        };
+        // half a pixel off the grid on each side
+        #[rustfmt::skip]
+        assert_eq!(glyph(options), [
+            64, 128, 128, 128, 128, 64,
+            64, 128, 128, 128, 128, 64,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(glyph(RasterOptions { gamma: 2.0, ..options }), [
+            128, 180, 180, 180, 180, 128,
+            128, 180, 180, 180, 180, 128,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(glyph(RasterOptions { antialias: false, ..options }), [
+            0, 255, 255, 255, 255, 0,
+            0, 255, 255, 255, 255, 0,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(glyph(RasterOptions { hinting: true, ..options }), [
+            0, 0, 0, 0, 0, 0,
+            0, 255, 255, 255, 255, 255,
+        ]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = std::fs::read(fixture("test_6x10.bdf")).unwrap();
+        assert!(Outlines::new(data, RasterOptions::default()).is_err());
+    }
+}
@@ -13,10 +13,9 @@ pub struct Editor {
     font_size: Vector2i,
     edit_char_scale: i32,
     font_scale: i32,
-    sample_text_scale: i32,
     edit_char_offset: Vector2i,
     font_table_offset: Vector2i,
-    sample_text_offset: Vector2i,
+    sample_text: text::Text<'static>,
     window: RenderWindow,
     display_idx: i32,
     font: Font,
@@ -52,15 +51,33 @@ impl Editor {
             Font::new(font_name, font_size)
         };
 
+        let sample_lines = Self::sample_text_lines();
+        let columns = sample_lines
+            .iter()
+            .map(|line| text::strip_ansi(line).chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut sample_text = text::TextBuilder::new()
+            .font_scale(sample_text_scale)
+            .bg_color(Color::BLACK)
+            .cursor_state(text::CursorState::Hidden)
+            .padding(font_size.x / 2)
+            .border(2, Color::rgb(0x80, 0x80, 0x80))
+            .title(font_name)
+            .build();
+        let sample_text_size =
+            sample_text.size_for_grid(columns as i32, sample_lines.len() as i32, &font);
+        sample_text.set_position_size(sample_text_offset, sample_text_size, font.char_size);
+        sample_text.replace(sample_lines);
+
         let display_idx = 'a' as i32;
         let mut editor = Self {
             font_size,
             edit_char_scale,
             font_scale,
-            sample_text_scale,
             edit_char_offset,
             font_table_offset,
-            sample_text_offset,
+            sample_text,
             window,
             display_idx,
             font,
@@ -234,19 +251,27 @@ impl Editor {
     }
 
     fn draw_sample_text(&mut self) {
-        let mut text_pos = self.sample_text_offset;
-        let mut draw_text = |s: &str, color: Color| {
-            self.font
-                .draw_text(s, text_pos, self.sample_text_scale, color, &mut self.window);
-            text_pos.y += self.font_size.y * self.sample_text_scale;
+        // the glyphs change while they are edited
+        self.sample_text.redraw = true;
+        self.sample_text.draw(&mut self.window, &self.font);
+    }
+
+    /// the lines of the sample text panel, colored with ansi escape codes
+    fn sample_text_lines() -> Vec<String> {
+        let mut lines = vec![];
+        let mut add_line = |s: &str, color: Color| {
+            lines.push(format!(
+                "\x1b[38;2;{};{};{}m{}",
+                color.r, color.g, color.b, s
+            ));
         };
 
         //let text = "The quick brown fox jumps over the lazy dog.";
         let text = "Victor jagt zwölf Boxkämpfer quer über den großen Sylter Deich.";
 
-        draw_text(text, Color::WHITE);
-        draw_text(&text.to_uppercase(), Color::rgb(0xc0, 0xc0, 0xff));
-        draw_text(&text.to_lowercase(), Color::rgb(0xff, 0xc0, 0xc0));
+        add_line(text, Color::WHITE);
+        add_line(&text.to_uppercase(), Color::rgb(0xc0, 0xc0, 0xff));
+        add_line(&text.to_lowercase(), Color::rgb(0xff, 0xc0, 0xc0));
 
         for line in (r#"
 Indeed, the quick brown fox - agile, bold, and cunning - jumped over
@@ -258,7 +283,7 @@ again!'. The fox then looked at its reflection in the river, seeing
 a victorious smile [or was it a smirk?] in the mirror-like surface."#)
             .lines()
         {
-            draw_text(line, Color::rgb(0xc0, 0xff, 0xc0));
+            add_line(line, Color::rgb(0xc0, 0xff, 0xc0));
         }
 
         for line in (r#"
@@ -267,14 +292,14 @@ earned $100, and I'm still #1 in the forest!' before he dashed off
 into the night."#)
             .lines()
         {
-            draw_text(line, Color::rgb(0xff, 0xc0, 0xff));
+            add_line(line, Color::rgb(0xff, 0xc0, 0xff));
         }
 
         for line in (r#"
 0123456789!"\#$%&'()*+,-./:;<=>?@[\]^_`{|}~"#)
             .lines()
         {
-            draw_text(line, Color::rgb(0xc0, 0xff, 0xff));
+            add_line(line, Color::rgb(0xc0, 0xff, 0xff));
         }
 
         for line in (r#"
@@ -286,8 +311,10 @@ fn main() {
 }"#)
         .lines()
         {
-            draw_text(line, Color::rgb(0xff, 0xff, 0xc0));
+            add_line(line, Color::rgb(0xff, 0xff, 0xc0));
         }
+
+        lines
     }
 
     fn draw_full_font_table(&mut self) {
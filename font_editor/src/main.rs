@@ -39,6 +39,8 @@ fn main() {
     // upscaling:
     // convert resources/font_10x16.png -sample 160x320! resources/font_10x20.png
     // (! to no preserve aspect ratio)
+    // or rasterize an outline font at any size, fixing the result here:
+    // font::Font::from_ttf(path, size, font::RasterOptions::default())?.save()
 
     font_editor::Editor::edit(
         font_name,
@@ -7,5 +7,6 @@
 xdg_data_home=${XDG_DATA_HOME:-$HOME/.local/share}
 RQOS_PATH=$xdg_data_home/rqos
 cargo install --path rqsh &&
+    cargo install --path rqed &&
     mkdir -p $RQOS_PATH &&
     cp -a resources $RQOS_PATH/
@@ -0,0 +1,61 @@
+//! the commands typed after `:` in normal mode, like in vi
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `:w [FILE]`
+    Write(Option<String>),
+    /// `:q`, `:q!` quits without saving
+    Quit { force: bool },
+    /// `:wq [FILE]`
+    WriteQuit(Option<String>),
+    /// `:x`, writes only if the buffer changed
+    Exit,
+    /// `:N`, the line starts at 1
+    Goto(usize),
+}
+
+/// parse the text after the `:`
+pub fn parse(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    if let Ok(n) = line.parse() {
+        return Ok(Command::Goto(n));
+    }
+    let (name, arg) = match line.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, Some(arg.trim().to_string())),
+        None => (line, None),
+    };
+    let command = match (name, arg) {
+        ("w" | "write", arg) => Command::Write(arg),
+        ("wq", arg) => Command::WriteQuit(arg),
+        ("q" | "quit", None) => Command::Quit { force: false },
+        ("q!" | "quit!", None) => Command::Quit { force: true },
+        ("x" | "exit", None) => Command::Exit,
+        ("q" | "quit" | "q!" | "quit!" | "x" | "exit", Some(_)) => {
+            return Err(format!("{}: too many arguments", name))
+        }
+        _ => return Err(format!("not a command: {}", line)),
+    };
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("w"), Ok(Command::Write(None)));
+        assert_eq!(
+            parse(" w  copy.txt "),
+            Ok(Command::Write(Some("copy.txt".to_string())))
+        );
+        assert_eq!(parse("wq"), Ok(Command::WriteQuit(None)));
+        assert_eq!(parse("q"), Ok(Command::Quit { force: false }));
+        assert_eq!(parse("q!"), Ok(Command::Quit { force: true }));
+        assert_eq!(parse("x"), Ok(Command::Exit));
+        assert_eq!(parse("12"), Ok(Command::Goto(12)));
+        assert!(parse("q file").is_err());
+        assert!(parse("s/a/b/").is_err());
+        assert!(parse("").is_err());
+    }
+}
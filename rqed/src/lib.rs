@@ -0,0 +1,527 @@
+//! rqed, a text editor built from `text::Text` and `edit::Edit`
+//!
+//! The file is edited with the keys of the rqsh command line: vi normal and visual mode and the
+//! readline keys in insert mode. `:` opens a command line in the status line, see `command`.
+//! Tabs are shown as spaces, the lines that weren't edited are written with their tabs, the
+//! edited lines with spaces.
+//!
+//! `Editor` only draws into a window and takes its events, so rqsh can run it in its own window.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use sfml::graphics::{Color, RenderWindow};
+use sfml::system::Vector2i;
+use sfml::window::Key;
+
+mod command;
+
+use command::Command;
+
+const TAB_WIDTH: usize = 4;
+
+/// the lines of a file, without the newline at the end of the last line
+fn file_lines(content: &str) -> Vec<String> {
+    let content = content.strip_suffix('\n').unwrap_or(content);
+    content.split('\n').map(str::to_string).collect()
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    for c in line.chars() {
+        if c == '\t' {
+            let width = expanded.chars().count();
+            expanded.push_str(&" ".repeat(TAB_WIDTH - width % TAB_WIDTH));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+/// the lines of the buffer as they are written, a line that is equal to a line of `original`,
+/// the lines of the file, with its tabs expanded is written like that line, in the order of
+/// the file if there are more of them
+fn restore_tabs(lines: &[String], original: &[String]) -> Vec<String> {
+    let mut unchanged: HashMap<String, VecDeque<&String>> = HashMap::new();
+    for line in original {
+        unchanged
+            .entry(expand_tabs(line))
+            .or_default()
+            .push_back(line);
+    }
+    lines
+        .iter()
+        .map(|line| {
+            let restored = unchanged.get_mut(line).and_then(VecDeque::pop_front);
+            restored.unwrap_or(line).clone()
+        })
+        .collect()
+}
+
+/// the content of the file for `lines`, every line ends with a newline
+fn file_content(lines: &[String]) -> String {
+    if lines == [""] {
+        return String::new();
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+pub struct Editor {
+    path: PathBuf,
+    /// the lines as they are in the file, with their tabs
+    original: Vec<String>,
+    /// `original` with the tabs expanded, the buffer is changed if it differs
+    saved: Vec<String>,
+    edit: edit::Edit,
+    gutter: text::Text<'static>,
+    status: text::Text<'static>,
+    /// the `:` command line while it is open, its text starts with the `:`
+    command: Option<edit::Edit>,
+    /// shown in the status line until the next key
+    message: Option<String>,
+    position: Vector2i,
+    size: Vector2i,
+    char_size: Vector2i,
+    /// columns of the line numbers
+    gutter_columns: usize,
+    closed: bool,
+}
+
+impl Editor {
+    /// open `path`, a file that doesn't exist is created when it is written
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let (original, message) = match std::fs::read(path) {
+            Ok(bytes) => {
+                let content = String::from_utf8(bytes)
+                    .map_err(|_| format!("{}: not a text file", path.display()))?;
+                let lines = file_lines(&content);
+                let message = format!("\"{}\" {} lines", path.display(), lines.len());
+                (lines, message)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (
+                vec![String::new()],
+                format!("\"{}\" new file", path.display()),
+            ),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let colors = color::AnsiColor::new();
+        let yellow = colors.get_color("Yellow").unwrap();
+        let mut edit = edit::EditBuilder::new()
+            .cursor_colors(Color::BLACK, yellow)
+            .build();
+        let saved: Vec<String> = original.iter().map(|line| expand_tabs(line)).collect();
+        edit.replace(saved.clone());
+        edit.set_mode(edit::Mode::Normal);
+        edit.set_cursor(Vector2i::new(0, 0));
+        let gutter = text::TextBuilder::new()
+            .fg_color(colors.get_color("Light Black").unwrap())
+            .build();
+        let status = text::TextBuilder::new()
+            .fg_color(Color::BLACK)
+            .bg_color(yellow)
+            .build();
+
+        let mut editor = Self {
+            path: path.to_path_buf(),
+            original,
+            saved,
+            edit,
+            gutter,
+            status,
+            command: None,
+            message: Some(message),
+            position: Vector2i::new(0, 0),
+            size: Vector2i::new(1, 1),
+            char_size: Vector2i::new(1, 1),
+            gutter_columns: 0,
+            closed: false,
+        };
+        editor.update();
+        Ok(editor)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// the buffer differs from the file
+    pub fn is_dirty(&self) -> bool {
+        self.edit.get_text() != self.saved
+    }
+
+    /// `:q` or `:wq` was typed, the editor can be dropped
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// close the editor, unless there are unsaved changes and `force` isn't set
+    pub fn close(&mut self, force: bool) {
+        if force || !self.is_dirty() {
+            self.closed = true;
+        } else {
+            self.message = Some("unsaved changes, :q! quits without saving".to_string());
+            self.update_status();
+        }
+    }
+
+    pub fn set_position_size(&mut self, position: Vector2i, size: Vector2i, font: &font::Font) {
+        self.position = position;
+        self.size = size;
+        self.char_size = font.char_size;
+        self.layout();
+        self.update();
+    }
+
+    fn rows(&self) -> usize {
+        (self.size.y / self.char_size.y - 1).max(1) as usize
+    }
+
+    /// the line numbers on the left, the text and the status line at the bottom
+    fn layout(&mut self) {
+        let gutter_width = self.gutter_columns as i32 * self.char_size.x;
+        let text_height = self.rows() as i32 * self.char_size.y;
+        let status_position = self.position + Vector2i::new(0, text_height);
+        let status_size = Vector2i::new(self.size.x, self.char_size.y);
+        self.gutter.set_position_size(
+            self.position,
+            Vector2i::new(gutter_width.max(1), text_height),
+            self.char_size,
+        );
+        self.edit.set_position_size(
+            self.position + Vector2i::new(gutter_width, 0),
+            Vector2i::new((self.size.x - gutter_width).max(1), text_height),
+            self.char_size,
+        );
+        self.status
+            .set_position_size(status_position, status_size, self.char_size);
+        if let Some(command) = &mut self.command {
+            command.set_position_size(status_position, status_size, self.char_size);
+        }
+    }
+
+    /// show the cursor as active or inactive, like the window focus
+    pub fn set_active(&mut self, active: bool) {
+        let state = match (self.edit.mode == edit::Mode::Insert, active) {
+            (true, true) => text::CursorState::InsertActive,
+            (true, false) => text::CursorState::InsertInactive,
+            (false, true) => text::CursorState::NormalActive,
+            (false, false) => text::CursorState::NormalInactive,
+        };
+        self.edit.set_cursor_state(state);
+    }
+
+    pub fn blink_cursor(&mut self, on: bool) {
+        match &mut self.command {
+            Some(command) => command.blink_cursor(on),
+            None => self.edit.blink_cursor(on),
+        }
+    }
+
+    pub fn key_pressed(&mut self, code: Key) {
+        self.message = None;
+        if self.command.is_some() {
+            self.command_key_pressed(code);
+            return self.update();
+        }
+        let insert = self.edit.mode == edit::Mode::Insert;
+        let rows = self.rows() as i32;
+        match code {
+            Key::Enter if insert => self.edit.insert_newline(),
+            Key::Enter => {
+                self.edit.move_cursor_line(1);
+            }
+            Key::Tab if insert && !self.edit.control && !self.edit.alt => {
+                let width = TAB_WIDTH - self.edit.get_cursor().x as usize % TAB_WIDTH;
+                (0..width).for_each(|_| self.edit.text_entered(' '));
+            }
+            Key::Up | Key::Down | Key::PageUp | Key::PageDown => {
+                let delta = match code {
+                    Key::Up => -1,
+                    Key::Down => 1,
+                    Key::PageUp => -rows,
+                    _ => rows,
+                };
+                let y = self.edit.get_cursor().y + delta;
+                let x = self.edit.get_cursor().x;
+                self.edit.set_cursor(Vector2i::new(x, y));
+            }
+            _ => self.edit.key_pressed(code),
+        }
+        self.update();
+    }
+
+    fn command_key_pressed(&mut self, code: Key) {
+        let Some(command) = &mut self.command else {
+            return;
+        };
+        match code {
+            Key::Escape => self.command = None,
+            Key::Enter => {
+                let line = command.get_text().join("");
+                self.command = None;
+                self.run_command(line.strip_prefix(':').unwrap_or(&line));
+            }
+            _ => {
+                command.key_pressed(code);
+                // deleting the `:` closes the command line
+                if command.get_text() == [""] {
+                    self.command = None;
+                }
+            }
+        }
+    }
+
+    pub fn key_released(&mut self, code: Key) {
+        self.edit.key_released(code);
+        if let Some(command) = &mut self.command {
+            command.key_released(code);
+        }
+    }
+
+    pub fn text_entered(&mut self, c: char) {
+        if let Some(command) = &mut self.command {
+            command.text_entered(c);
+            return;
+        }
+        if c == ':' && self.edit.mode == edit::Mode::Normal && !self.edit.has_pending_keys() {
+            self.open_command();
+        } else {
+            self.edit.text_entered(c);
+        }
+        self.update();
+    }
+
+    fn open_command(&mut self) {
+        let mut command = edit::EditBuilder::new().build();
+        command.text_entered(':');
+        // the modifier keys that are held down now are released in this one
+        command.control = self.edit.control;
+        command.shift = self.edit.shift;
+        command.alt = self.edit.alt;
+        self.command = Some(command);
+        self.layout();
+    }
+
+    fn run_command(&mut self, line: &str) {
+        match command::parse(line) {
+            Ok(Command::Write(path)) => {
+                self.write(path);
+            }
+            Ok(Command::WriteQuit(path)) => {
+                if self.write(path) {
+                    self.close(false);
+                }
+            }
+            Ok(Command::Exit) => {
+                if !self.is_dirty() || self.write(None) {
+                    self.close(false);
+                }
+            }
+            Ok(Command::Quit { force }) => self.close(force),
+            Ok(Command::Goto(line)) => {
+                self.edit
+                    .set_cursor(Vector2i::new(0, line.max(1) as i32 - 1));
+            }
+            Err(e) => self.message = Some(e),
+        }
+    }
+
+    /// write the buffer to `path` or the file that was opened, returns false on an error
+    fn write(&mut self, path: Option<String>) -> bool {
+        let path = path.map_or(self.path.clone(), PathBuf::from);
+        let lines = self.edit.get_text();
+        let file_lines = restore_tabs(&lines, &self.original);
+        match std::fs::write(&path, file_content(&file_lines)) {
+            Ok(()) => {
+                self.message = Some(format!(
+                    "\"{}\" {} lines written",
+                    path.display(),
+                    lines.len()
+                ));
+                if path == self.path {
+                    self.saved = lines;
+                    self.original = file_lines;
+                }
+                true
+            }
+            Err(e) => {
+                self.message = Some(format!("{}: {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    /// scroll to the cursor and update the line numbers and the status line
+    fn update(&mut self) {
+        let rows = self.rows();
+        self.edit.scroll_to_cursor(rows);
+
+        let line_count = self.edit.line_count();
+        let gutter_columns = line_count.to_string().len() + 1;
+        if gutter_columns != self.gutter_columns {
+            self.gutter_columns = gutter_columns;
+            self.layout();
+        }
+        let first = self.edit.first_visible_line(rows);
+        let numbers: Vec<String> = (first + 1..=(first + rows).min(line_count))
+            .map(|n| format!("{:>1$} ", n, gutter_columns - 1))
+            .collect();
+        if numbers != self.gutter.lines() {
+            self.gutter.replace(numbers);
+        }
+        self.update_status();
+    }
+
+    fn update_status(&mut self) {
+        let cursor = self.edit.get_cursor();
+        let status = match &self.message {
+            Some(message) => message.clone(),
+            None => {
+                let mode = match self.edit.mode {
+                    edit::Mode::Normal => "",
+                    edit::Mode::Insert => "-- INSERT --  ",
+                    edit::Mode::Visual => "-- VISUAL --  ",
+                    edit::Mode::VisualLine => "-- VISUAL LINE --  ",
+                };
+                format!(
+                    "{}{}{}  {}:{}  {} lines",
+                    mode,
+                    self.path.display(),
+                    if self.is_dirty() { " [+]" } else { "" },
+                    cursor.y + 1,
+                    cursor.x + 1,
+                    self.edit.line_count(),
+                )
+            }
+        };
+        if self.status.lines() != [status.as_str()] {
+            self.status.replace(vec![status]);
+        }
+    }
+
+    pub fn must_draw(&self) -> bool {
+        self.edit.must_draw()
+            || self.gutter.must_draw()
+            || self.status.must_draw()
+            || self
+                .command
+                .as_ref()
+                .is_some_and(|command| command.must_draw())
+    }
+
+    /// draw everything again on the next frame, like after glyphs were added to the font
+    pub fn redraw(&mut self) {
+        self.edit.redraw();
+        self.gutter.redraw = true;
+        self.status.redraw = true;
+        if let Some(command) = &mut self.command {
+            command.redraw();
+        }
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow, font: &font::Font) {
+        self.gutter.draw(window, font);
+        self.edit.draw(window, font);
+        match &mut self.command {
+            Some(command) => command.draw(window, font),
+            None => self.status.draw(window, font),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_lines() {
+        assert_eq!(file_lines("a\nb\n"), vec!["a", "b"]);
+        assert_eq!(file_lines("a\n\nb"), vec!["a", "", "b"]);
+        assert_eq!(file_lines(""), vec![""]);
+        assert_eq!(expand_tabs("\ta"), "    a");
+        assert_eq!(expand_tabs("  \tb\tc"), "    b   c");
+        assert_eq!(file_content(&file_lines("a\n\nb")), "a\n\nb\n");
+        assert_eq!(file_content(&file_lines("")), "");
+    }
+
+    #[test]
+    fn test_editor() {
+        let dir = std::env::temp_dir().join(format!("rqed-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        assert!(!editor.is_dirty());
+        "jdd".chars().for_each(|c| editor.text_entered(c));
+        assert!(editor.is_dirty());
+        editor.run_command("q");
+        assert!(!editor.is_closed());
+        "u".chars().for_each(|c| editor.text_entered(c));
+        assert!(!editor.is_dirty());
+
+        "Ax".chars().for_each(|c| editor.text_entered(c));
+        editor.key_pressed(Key::Enter);
+        "three".chars().for_each(|c| editor.text_entered(c));
+        editor.key_pressed(Key::Escape);
+        ":wq".chars().for_each(|c| editor.text_entered(c));
+        editor.key_pressed(Key::Enter);
+        assert!(editor.is_closed());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "one\ntwox\nthree\n"
+        );
+
+        let mut editor = Editor::open(&dir.join("new.txt")).unwrap();
+        assert!(!editor.is_dirty());
+        editor.run_command("x");
+        assert!(editor.is_closed());
+        assert!(!dir.join("new.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_tabs() {
+        let original = file_lines("all:\n\tcc -o a a.c\n\techo  \n    \techo\n");
+        let lines: Vec<String> = original.iter().map(|line| expand_tabs(line)).collect();
+        assert_eq!(restore_tabs(&lines, &original), original);
+        // edited lines are written with spaces, moved lines keep their tabs
+        let lines = vec![
+            "    echo  ".to_string(),
+            "all: b".to_string(),
+            "    cc -o a a.c".to_string(),
+        ];
+        assert_eq!(
+            restore_tabs(&lines, &original),
+            ["\techo  ", "all: b", "\tcc -o a a.c"]
+        );
+    }
+
+    #[test]
+    fn test_editor_tabs() {
+        let dir = std::env::temp_dir().join(format!("rqed-tabs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Makefile");
+        let content = "all: a\n\ncc:\n\tcc -o a a.c\n";
+        std::fs::write(&path, content).unwrap();
+
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.edit.get_text()[3], "    cc -o a a.c");
+        assert!(!editor.is_dirty());
+        editor.run_command("w");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        "Ab".chars().for_each(|c| editor.text_entered(c));
+        editor.key_pressed(Key::Escape);
+        assert!(editor.is_dirty());
+        editor.run_command("w");
+        assert!(!editor.is_dirty());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "all: ab\n\ncc:\n\tcc -o a a.c\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
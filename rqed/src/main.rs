@@ -0,0 +1,113 @@
+// rqed
+use std::path::Path;
+
+use sfml::graphics::{Color, RenderTarget, RenderWindow};
+use sfml::system::Vector2i;
+use sfml::window::{Event, Style};
+
+const CURSOR_BLINK_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, path] = &args[..] else {
+        eprintln!("usage: rqed FILE");
+        std::process::exit(2);
+    };
+    let mut editor = match rqed::Editor::open(Path::new(path)) {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("rqed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let desktop_mode = sfml::window::VideoMode::desktop_mode();
+    let screen_width = desktop_mode.width;
+    let screen_height = desktop_mode.height;
+
+    let font_name = "font_bold";
+    let font_size = Vector2i::new(20, 40);
+    let mut font = font::Font::load(font_name, font_size).expect("Failed to load font");
+    if let Ok(fallback) = font::Font::load("font", font_size) {
+        font.add_fallback(fallback);
+    }
+
+    let (cols, rows) = (100, 50);
+    let window_width = cols * font_size.x;
+    let window_height = rows * font_size.y;
+    let (window_pos_x, window_pos_y) = (
+        ((screen_width as i32) - (window_width / 2)),
+        ((screen_height as i32 / 2) - (window_height / 2)),
+    );
+
+    let mut window = RenderWindow::new(
+        (window_width as u32, window_height as u32),
+        &format!("rqed {}", path),
+        Style::CLOSE | Style::RESIZE,
+        &Default::default(),
+    );
+    window.set_position(Vector2i::new(window_pos_x, window_pos_y));
+    window.set_vertical_sync_enabled(true);
+    editor.set_position_size(
+        Vector2i::new(0, 0),
+        Vector2i::new(window_width, window_height),
+        &font,
+    );
+    editor.set_active(true);
+
+    let mut cursor_blink_start = std::time::Instant::now();
+    while window.is_open() {
+        let t = std::time::Instant::now();
+        while let Some(event) = window.poll_event() {
+            match event {
+                // like `:q`, unsaved changes keep the window open
+                Event::Closed => editor.close(false),
+                Event::KeyPressed { code, .. } => {
+                    cursor_blink_start = std::time::Instant::now();
+                    editor.key_pressed(code);
+                }
+                Event::KeyReleased { code, .. } => editor.key_released(code),
+                Event::TextEntered { unicode } => {
+                    cursor_blink_start = std::time::Instant::now();
+                    editor.text_entered(unicode);
+                }
+                Event::Resized { width, height } => {
+                    window.set_view(&sfml::graphics::View::new(
+                        sfml::system::Vector2f::new(width as f32 / 2.0, height as f32 / 2.0),
+                        sfml::system::Vector2f::new(width as f32, height as f32),
+                    ));
+                    editor.set_position_size(
+                        Vector2i::new(0, 0),
+                        Vector2i::new(width as i32, height as i32),
+                        &font,
+                    );
+                }
+                Event::GainedFocus => editor.set_active(true),
+                Event::LostFocus => editor.set_active(false),
+                _ => {}
+            }
+        }
+        if editor.is_closed() {
+            window.close();
+            break;
+        }
+
+        let blink_phase = cursor_blink_start.elapsed().as_millis() / CURSOR_BLINK_TIME.as_millis();
+        editor.blink_cursor(blink_phase.is_multiple_of(2));
+        if editor.must_draw() {
+            window.clear(Color::BLACK);
+            editor.draw(&mut window, &font);
+            window.display();
+        }
+        // chars drawn without a glyph are looked up in the fallback fonts
+        if font.load_missing_glyphs() {
+            editor.redraw();
+        }
+
+        let elapsed = t.elapsed();
+        let frame_diff = 16 - elapsed.as_millis() as i32;
+        if frame_diff > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(frame_diff as u64));
+        }
+    }
+}
@@ -4,14 +4,37 @@ use std::sync::Arc;
 
 use sfml::graphics::{Color, RenderTarget, RenderWindow};
 use sfml::system::Vector2i;
-use sfml::window::{Event, Key, Style};
+use sfml::window::{clipboard, mouse, Event, Key, Style};
 
 use crate::args::Args;
 use crate::builtin::Builtin;
 use crate::execute::{BuiltinCommand, ExecMessage, Job};
+use crate::font_config::FontConfig;
 use crate::glob::Glob;
+use crate::highlight;
+use crate::history_search::{HistoryMatch, HistorySearch};
+use crate::keymap::{Action, Binding, Keymap, KeymapMode};
+use crate::settings::Settings;
 use crate::util;
 
+const MULTI_CLICK_TIME: std::time::Duration = std::time::Duration::from_millis(400);
+// time per frame spent on handling the output of jobs, so that a huge output does not block
+// the input
+const EXEC_MESSAGE_TIME_BUDGET: std::time::Duration = std::time::Duration::from_millis(8);
+// output of jobs is collected up to this size before it is written to main_win
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+// lines of info_win, the completions and the history search matches
+const INFO_LINES: i32 = 3;
+const CURSOR_BLINK_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// what the editor was opened for
+enum EditTarget {
+    /// the file of the `edit` builtin, its job ends when the editor is closed
+    Builtin(Job),
+    /// the command line, written to this file and read back when the editor is closed
+    CommandLine(std::path::PathBuf),
+}
+
 enum ScrollType {
     CursorUp,
     CursorDown,
@@ -36,15 +59,36 @@ pub struct App<'a> {
     command_bg_color_normal: Color,
     command_bg_color_running: Color,
     command_is_running: bool,
+    command_win_lines: i32,
     window: RenderWindow,
     dir_plain: Vec<String>,
     jobs: Vec<Job>,
+    job_output_start: usize,
+    output_idx: Option<usize>,
     browse_job_history_idx: usize,
     tx: mpsc::Sender<ExecMessage>,
     rx: mpsc::Receiver<ExecMessage>,
     stdin_tx: Option<mpsc::Sender<String>>,
     stop_thread: Option<Arc<AtomicBool>>,
     colors: color::AnsiColor,
+    search_input_active: bool,
+    history_search: Option<HistorySearch>,
+    search_regex: bool,
+    search_error: bool,
+    search_command_tmp: Vec<String>,
+    search_scroll_pos_tmp: i32,
+    selecting: bool,
+    last_click: Option<(std::time::Instant, Vector2i)>,
+    click_count: usize,
+    cursor_blink_start: std::time::Instant,
+    keymap: Keymap,
+    settings: Settings,
+    /// the last key press ran an action, the text it produces is not typed
+    action_key: bool,
+    /// several lines were pasted, Enter asks before running them
+    confirm_paste: bool,
+    /// the editor of the `edit` builtin or the `edit-command` action, it takes the whole window
+    editor: Option<(rqed::Editor, EditTarget)>,
 }
 
 impl App<'_> {
@@ -53,10 +97,9 @@ impl App<'_> {
         let screen_width = desktop_mode.width;
         let screen_height = desktop_mode.height;
 
-        let font_name = "font_bold";
-        let font_size = Vector2i::new(20, 40);
+        let font = FontConfig::load().font();
+        let font_size = font.char_size;
         let font_scale = 1;
-        let font = font::Font::load(font_name, font_size).expect("Failed to load font");
 
         let font_height = font_size.y * font_scale;
         let (cols, rows) = (160, 80);
@@ -93,15 +136,21 @@ impl App<'_> {
             .bg_color(yellow)
             .build();
 
+        let settings = Settings::load();
         let command_bg_color_normal = Color::WHITE;
         let command_bg_color_running = light_blue;
         let command_win = edit::EditBuilder::new()
             .cursor_colors(Color::BLACK, yellow)
+            .cursor_styles(settings.insert_cursor_style, settings.normal_cursor_style)
+            // its keys are in the keymap
+            .default_keys(false)
             .build();
 
         let info_win = text::TextBuilder::new()
             .fg_color(Color::BLACK)
             .bg_color(Color::WHITE)
+            .padding(2)
+            .border(1, Color::rgb(0xC0, 0xC0, 0xC0))
             .build();
 
         let jobs = App::load_jobs();
@@ -116,6 +165,7 @@ impl App<'_> {
             command_bg_color_normal,
             command_bg_color_running,
             command_is_running: false,
+            command_win_lines: 1,
             info_win,
             info_command_tmp: String::new(),
             info_text: Vec::new(),
@@ -125,15 +175,35 @@ impl App<'_> {
             window,
             dir_plain: Vec::new(),
             jobs,
+            job_output_start: 0,
+            output_idx: None,
             browse_job_history_idx: 0,
             tx,
             rx,
             stdin_tx: None,
             stop_thread: None,
             colors,
+            search_input_active: false,
+            history_search: None,
+            search_regex: false,
+            search_error: false,
+            search_command_tmp: vec![],
+            search_scroll_pos_tmp: 0,
+            selecting: false,
+            last_click: None,
+            click_count: 0,
+            cursor_blink_start: std::time::Instant::now(),
+            keymap: Keymap::load(),
+            settings,
+            action_key: false,
+            confirm_paste: false,
+            editor: None,
         };
 
+        // the grids of the windows are known once they are sized with the font
+        app.set_window_sizes(window_width, window_height);
         app.update_pwd_directory();
+        app.update_highlighter();
         app
     }
 
@@ -142,10 +212,18 @@ impl App<'_> {
         while self.window.is_open() {
             let t = std::time::Instant::now();
             while let Some(event) = self.window.poll_event() {
+                if self.editor.is_some() {
+                    self.editor_event(event);
+                    continue;
+                }
                 match event {
                     Event::Closed => self.exit(),
                     Event::KeyPressed { code, .. } => self.key_pressed(code),
                     Event::KeyReleased { code, .. } => self.command_win.key_released(code),
+                    Event::TextEntered { unicode } => self.text_entered(unicode),
+                    Event::MouseButtonPressed { button, x, y } => self.mouse_pressed(button, x, y),
+                    Event::MouseButtonReleased { button, .. } => self.mouse_released(button),
+                    Event::MouseMoved { x, y } => self.mouse_moved(x, y),
                     Event::MouseWheelScrolled { delta, .. } => {
                         if delta > 0.0 {
                             self.scroll(ScrollType::MouseWheelUp);
@@ -162,11 +240,23 @@ impl App<'_> {
                 }
             }
 
-            while let Ok(message) = self.rx.try_recv() {
-                self.handle_exec_messages(message);
-            }
-
-            if self.main_win.must_draw()
+            self.end_edit();
+            self.handle_exec_messages_budgeted(t);
+            self.update_command_win_height();
+
+            let blink_phase =
+                self.cursor_blink_start.elapsed().as_millis() / CURSOR_BLINK_TIME.as_millis();
+            self.command_win.blink_cursor(blink_phase.is_multiple_of(2));
+
+            if let Some((editor, _)) = &mut self.editor {
+                editor.blink_cursor(blink_phase.is_multiple_of(2));
+                if editor.must_draw() || frame_counter > 200 {
+                    self.window.clear(Color::BLACK);
+                    editor.draw(&mut self.window, &self.font);
+                    self.window.display();
+                    frame_counter = 0;
+                }
+            } else if self.main_win.must_draw()
                 || self.status_win.must_draw()
                 || self.command_win.must_draw()
                 || self.info_win.must_draw()
@@ -182,6 +272,10 @@ impl App<'_> {
                 frame_counter = 0;
             }
             frame_counter += 1;
+            // chars drawn without a glyph are looked up in the fallback fonts
+            if self.font.load_missing_glyphs() {
+                self.redraw();
+            }
 
             let elapsed = t.elapsed();
             let frame_diff = 16 - elapsed.as_millis() as i32;
@@ -191,6 +285,115 @@ impl App<'_> {
         }
     }
 
+    /// the events while the editor of the `edit` builtin is open
+    fn editor_event(&mut self, event: Event) {
+        if let Event::Resized { width, height } = event {
+            return self.resize_event(width as i32, height as i32);
+        }
+        let Some((editor, _)) = &mut self.editor else {
+            return;
+        };
+        match event {
+            // like `:q`, unsaved changes keep the editor open
+            Event::Closed => editor.close(false),
+            Event::KeyPressed { code, .. } => {
+                self.cursor_blink_start = std::time::Instant::now();
+                editor.key_pressed(code);
+            }
+            Event::KeyReleased { code, .. } => editor.key_released(code),
+            Event::TextEntered { unicode } => {
+                self.cursor_blink_start = std::time::Instant::now();
+                editor.text_entered(unicode);
+            }
+            Event::GainedFocus => editor.set_active(true),
+            Event::LostFocus => editor.set_active(false),
+            _ => {}
+        }
+    }
+
+    /// an editor for `path` that takes the whole window
+    fn open_editor(&self, path: &std::path::Path) -> Result<rqed::Editor, String> {
+        let mut editor = rqed::Editor::open(path)?;
+        let size = self.window.size();
+        editor.set_position_size(
+            Vector2i::new(0, 0),
+            Vector2i::new(size.x as i32, size.y as i32),
+            &self.font,
+        );
+        editor.set_active(true);
+        Ok(editor)
+    }
+
+    /// open `path` for the `edit` builtin, its job ends when the editor is closed
+    fn start_edit(&mut self, mut job: Job, path: &str) {
+        match self.open_editor(std::path::Path::new(path)) {
+            Ok(editor) => self.editor = Some((editor, EditTarget::Builtin(job))),
+            Err(e) => {
+                self.tx
+                    .send(ExecMessage::StdErr(format!("edit: {}", e)))
+                    .unwrap();
+                job.return_code = Some(1);
+                job.end();
+                self.tx.send(ExecMessage::JobDone(job)).unwrap();
+            }
+        }
+    }
+
+    /// edit the command line in the editor, the file written with `:w` replaces it when the
+    /// editor is closed
+    fn edit_command(&mut self) {
+        let path = std::env::temp_dir().join(format!("rqsh-command-{}.sh", std::process::id()));
+        let result = std::fs::write(&path, format!("{}\n", self.command_text()))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+            .and_then(|()| self.open_editor(&path));
+        match result {
+            Ok(editor) => {
+                // the editor gets the release of the modifiers held down now
+                self.command_win.control = false;
+                self.command_win.shift = false;
+                self.command_win.alt = false;
+                self.editor = Some((editor, EditTarget::CommandLine(path)));
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                self.tx
+                    .send(ExecMessage::StdErr(format!("edit-command: {}", e)))
+                    .unwrap();
+            }
+        }
+    }
+
+    fn end_edit(&mut self) {
+        if !self
+            .editor
+            .as_ref()
+            .is_some_and(|(editor, _)| editor.is_closed())
+        {
+            return;
+        }
+        match self.editor.take() {
+            Some((_, EditTarget::Builtin(mut job))) => {
+                job.return_code = Some(0);
+                job.end();
+                self.tx.send(ExecMessage::JobDone(job)).unwrap();
+            }
+            Some((_, EditTarget::CommandLine(path))) => {
+                if let Ok(content) = std::fs::read_to_string(&path) {
+                    let command = content.strip_suffix('\n').unwrap_or(&content);
+                    if command != self.command_text() {
+                        self.command_win
+                            .replace_with_undo(Self::command_lines(command));
+                    }
+                }
+                let _ = std::fs::remove_file(&path);
+            }
+            None => {}
+        }
+        // the editor was drawn over all windows
+        self.main_win.redraw = true;
+        self.command_win.redraw();
+    }
+
     fn set_active(&mut self, active: bool) {
         let old_state = self.command_win.get_cursor_state();
         self.command_win
@@ -203,20 +406,183 @@ impl App<'_> {
             });
     }
 
+    /// Left button selects text in `main_win`: drag for characters, double click for words,
+    /// triple click for lines, Alt-drag for a rectangular block. The selection is copied to the
+    /// clipboard when the button is released. Middle button pastes like Shift-Insert.
+    fn mouse_pressed(&mut self, button: mouse::Button, x: i32, y: i32) {
+        match button {
+            mouse::Button::Left if self.main_win.contains(x, y) => {
+                let cell = self.main_win.cell_at(x, y, &self.font);
+                let now = std::time::Instant::now();
+                self.click_count = match self.last_click {
+                    Some((time, last_cell))
+                        if last_cell == cell && now.duration_since(time) < MULTI_CLICK_TIME =>
+                    {
+                        self.click_count % 3 + 1
+                    }
+                    _ => 1,
+                };
+                self.last_click = Some((now, cell));
+
+                let alt = Key::LAlt.is_pressed() || Key::RAlt.is_pressed();
+                let mode = match (alt, self.click_count) {
+                    (true, _) => text::SelectionMode::Block,
+                    (false, 2) => text::SelectionMode::Word,
+                    (false, 3) => text::SelectionMode::Line,
+                    _ => text::SelectionMode::Char,
+                };
+                self.main_win.selection_start(cell, mode);
+                self.selecting = true;
+            }
+            mouse::Button::Middle => self.paste_clipboard(),
+            _ => {}
+        }
+    }
+
+    fn mouse_moved(&mut self, x: i32, y: i32) {
+        if self.selecting {
+            let cell = self.main_win.cell_at(x, y, &self.font);
+            self.main_win.selection_extend(cell);
+        }
+    }
+
+    fn mouse_released(&mut self, button: mouse::Button) {
+        if button == mouse::Button::Left && self.selecting {
+            self.selecting = false;
+            self.copy_selection();
+        }
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some(selection) = self.main_win.selection() {
+            // a single click without dragging only removes the old selection
+            if selection.mode == text::SelectionMode::Char && selection.anchor == selection.head {
+                self.main_win.selection_clear();
+                return;
+            }
+        }
+        if let Some(text) = self.main_win.selected_text() {
+            clipboard::set_string(text.as_str());
+        }
+    }
+
+    /// scroll to the previous or next command output, starting at the last one
+    fn jump_output(&mut self, delta: i32) {
+        let outputs = self.main_win.outputs();
+        if outputs.is_empty() {
+            return;
+        }
+        let last = outputs.len() as i32 - 1;
+        let idx = match self.output_idx {
+            Some(idx) => (idx as i32 + delta).clamp(0, last),
+            None => last,
+        } as usize;
+        self.output_idx = Some(idx);
+        // the line above the output is the command that produced it
+        self.scroll_to_line(outputs[idx].start.saturating_sub(1));
+    }
+
+    /// select the output jumped to, or the last output, and copy it to the clipboard
+    fn select_output(&mut self) {
+        let outputs = self.main_win.outputs();
+        let Some(last) = outputs.len().checked_sub(1) else {
+            return;
+        };
+        let output = &outputs[self.output_idx.unwrap_or(last).min(last)];
+        if output.is_empty() {
+            return;
+        }
+        self.main_win.selection_start(
+            Vector2i::new(0, output.start as i32),
+            text::SelectionMode::Line,
+        );
+        self.main_win
+            .selection_extend(Vector2i::new(0, output.end as i32 - 1));
+        self.scroll_to_line(output.start);
+        self.copy_selection();
+    }
+
+    /// copy the selection in `main_win`, or the command line if nothing is selected
+    fn copy(&mut self) {
+        match self.main_win.selected_text() {
+            Some(text) => clipboard::set_string(text.as_str()),
+            None => self.command_win.copy_clipboard(),
+        }
+    }
+
+    /// a running job that enabled bracketed paste gets the text on its stdin, marked with
+    /// `ESC[200~` and `ESC[201~` so it doesn't run the lines one by one
+    fn paste_clipboard(&mut self) {
+        let text = edit::paste_text(&clipboard::get_string());
+        if text.is_empty() {
+            return;
+        }
+        match &self.stdin_tx {
+            Some(stdin_tx) if self.main_win.bracketed_paste => {
+                // the job may have ended since the last frame
+                let _ = stdin_tx.send(format!("\x1b[200~{}\x1b[201~", text));
+            }
+            _ => {
+                self.command_win.paste(&text);
+                self.confirm_paste |= text.trim_end().contains('\n');
+                self.update_info_win();
+            }
+        }
+    }
+
+    /// the first Enter after pasting several lines only shows how many lines would run
+    fn ask_confirm_paste(&mut self) {
+        self.confirm_paste = false;
+        let lines = self.command_win.line_count();
+        self.status_win.replace(vec![format!(
+            "{} lines pasted, press Enter again to run them",
+            lines
+        )]);
+    }
+
+    /// draw all windows again on the next frame
+    fn redraw(&mut self) {
+        self.main_win.redraw = true;
+        self.status_win.redraw = true;
+        self.command_win.redraw();
+        self.info_win.redraw = true;
+        if let Some((editor, _)) = &mut self.editor {
+            editor.redraw();
+        }
+    }
+
     fn resize_event(&mut self, width: i32, height: i32) {
         self.window.set_view(&sfml::graphics::View::new(
             sfml::system::Vector2f::new(width as f32 / 2.0, height as f32 / 2.0),
             sfml::system::Vector2f::new(width as f32, height as f32),
         ));
         self.set_window_sizes(width, height);
+        if let Some((editor, _)) = &mut self.editor {
+            editor.set_position_size(
+                Vector2i::new(0, 0),
+                Vector2i::new(width, height),
+                &self.font,
+            );
+        }
+    }
+
+    /// resize command_win when the number of lines of the command changed
+    fn update_command_win_height(&mut self) {
+        let lines =
+            (self.command_win.line_count() as i32).clamp(1, self.settings.max_command_lines);
+        if lines != self.command_win_lines {
+            self.command_win_lines = lines;
+            let size = self.window.size();
+            self.set_window_sizes(size.x as i32, size.y as i32);
+        }
     }
 
     fn set_window_sizes(&mut self, width: i32, height: i32) {
         let font_height = self.font.char_size.y * self.font_scale;
 
         let status_win_height = font_height;
-        let command_win_height = font_height;
-        let info_win_height = font_height * 3;
+        let command_win_height = font_height * self.command_win_lines;
+        let info_win_height = self.info_win.size_for_grid(0, INFO_LINES, &self.font).y;
         let main_win_height = height - status_win_height - command_win_height - info_win_height;
 
         let main_win_pos_y = 0;
@@ -227,106 +593,440 @@ impl App<'_> {
         self.main_win.set_position_size(
             Vector2i::new(0, main_win_pos_y),
             Vector2i::new(width, main_win_height),
+            self.font.char_size,
         );
         self.status_win.set_position_size(
             Vector2i::new(0, status_win_pos_y),
             Vector2i::new(width, status_win_height),
+            self.font.char_size,
         );
         self.command_win.set_position_size(
             Vector2i::new(0, command_win_pos_y),
             Vector2i::new(width, command_win_height),
+            self.font.char_size,
         );
         self.info_win.set_position_size(
             Vector2i::new(0, info_win_pos_y),
             Vector2i::new(width, info_win_height),
+            self.font.char_size,
         );
     }
 
     fn key_pressed(&mut self, code: Key) {
-        if self.command_win.mode == edit::Mode::Normal {
-            self.normal_mode_key_pressed(code);
-        } else {
-            self.insert_mode_key_pressed(code);
+        // keep the cursor visible while typing
+        self.cursor_blink_start = std::time::Instant::now();
+        self.action_key = false;
+        if self.search_input_active {
+            self.search_key_pressed(code);
+            return;
+        }
+        if self.history_search.is_some() {
+            self.history_search_key_pressed(code);
+            return;
+        }
+        // the key after `Ctrl-X` goes to the editor
+        let action = match self.command_win.has_pending_keys() {
+            true => None,
+            false => self
+                .keymap
+                .lookup(self.keymap_mode(), self.key_binding(code)),
+        };
+        if let Some(action) = action {
+            // the text of the key, e.g. a space, is not typed as well
+            self.action_key = self.run_action(action);
+        }
+        if !self.action_key {
+            self.command_win.key_pressed(code);
+            if let Some(code) = self.command_win.take_ctrl_x() {
+                self.ctrl_x_key_pressed(code);
+            }
         }
         self.update_info_win();
     }
 
-    fn insert_mode_key_pressed(&mut self, code: Key) {
-        if self.command_win.control {
-            match code {
-                Key::C => self.kill_job(),
-                Key::D => self.send_eof(),
-                Key::N => self.change_selection(1),
-                Key::P => self.change_selection(-1),
-                _ => self.command_win.key_pressed(code),
+    fn key_binding(&self, code: Key) -> Binding {
+        Binding::Key {
+            code,
+            control: self.command_win.control,
+            shift: self.command_win.shift,
+            alt: self.command_win.alt,
+        }
+    }
+
+    /// a key for the command line while searching, only its editing commands are looked up in
+    /// the insert mode keymap
+    fn edit_key_pressed(&mut self, code: Key) {
+        let binding = self.key_binding(code);
+        match self.keymap.lookup(KeymapMode::Insert, binding) {
+            Some(Action::Edit(action)) => self.action_key = self.command_win.run_action(action),
+            _ => self.command_win.key_pressed(code),
+        }
+    }
+
+    /// `Ctrl-X Ctrl-<key>` sequences the command line editor leaves to the shell, like
+    /// `Ctrl-X Ctrl-E` for `edit-command`
+    fn ctrl_x_key_pressed(&mut self, code: Key) {
+        if let Some(action) = self.keymap.lookup(self.keymap_mode(), Binding::CtrlX(code)) {
+            self.run_action(action);
+        }
+    }
+
+    fn text_entered(&mut self, c: char) {
+        self.cursor_blink_start = std::time::Instant::now();
+        if std::mem::take(&mut self.action_key) {
+            return;
+        }
+        if self.search_input_active {
+            self.command_win.text_entered(c);
+            self.update_search();
+            return;
+        }
+        if self.history_search.is_some() {
+            self.command_win.text_entered(c);
+            self.update_history_search();
+            return;
+        }
+        // keys of an unfinished vi command, e.g. `fk`, and passwords go to the editor
+        let action = match self.command_win.has_pending_keys() || self.command_win.is_masked() {
+            true => None,
+            false => self.keymap.lookup(self.keymap_mode(), Binding::Char(c)),
+        };
+        if !action.is_some_and(|action| self.run_action(action)) {
+            self.command_win.text_entered(c);
+        }
+        self.update_info_win();
+    }
+
+    fn keymap_mode(&self) -> KeymapMode {
+        match self.command_win.mode {
+            edit::Mode::Insert => KeymapMode::Insert,
+            edit::Mode::Normal => KeymapMode::Normal,
+            edit::Mode::Visual | edit::Mode::VisualLine => KeymapMode::Visual,
+        }
+    }
+
+    /// returns false if the action doesn't apply now, the key then goes to the command line
+    fn run_action(&mut self, action: Action) -> bool {
+        let has_matches = !self.main_win.search_matches().is_empty();
+        let normal_mode = self.command_win.mode == edit::Mode::Normal;
+        if self.command_win.is_masked() {
+            // nothing may show, copy or complete a password
+            match action {
+                Action::KillJob => self.kill_job(),
+                Action::AcceptLine => self.run_command(),
+                Action::Paste => self.command_win.paste_clipboard(),
+                // the editor handles the keys of a password itself
+                Action::Edit(_) => return false,
+                _ => {}
             }
-        } else {
-            let tab_dir = if self.command_win.shift { -1 } else { 1 };
-            match code {
-                Key::Tab => self.change_selection(tab_dir),
-                Key::Enter => self.on_enter(),
-                Key::Up => self.scroll(ScrollType::CursorUp),
-                Key::Down => self.scroll(ScrollType::CursorDown),
-                Key::PageUp => self.scroll(ScrollType::PageUp),
-                Key::PageDown => self.scroll(ScrollType::PageDown),
-                _ => self.command_win.key_pressed(code),
+            return true;
+        }
+        match action {
+            Action::KillJob => self.kill_job(),
+            Action::SendEof => self.send_eof(),
+            Action::AcceptLine if self.confirm_paste && self.command_win.line_count() > 1 => {
+                self.ask_confirm_paste()
+            }
+            Action::AcceptLine if normal_mode => {
+                self.run_command();
+                self.command_win.set_mode(edit::Mode::Insert);
+            }
+            // an open quote continues the command on a new line, input for a running job is
+            // sent as it is
+            Action::AcceptLine
+                if !self.command_is_running && Args::is_incomplete(&self.command_text()) =>
+            {
+                self.command_win.insert_newline()
+            }
+            Action::AcceptLine => self.on_enter(),
+            Action::Newline if normal_mode => return false,
+            Action::Newline => self.command_win.insert_newline(),
+            Action::CompleteNext => self.change_selection(1),
+            Action::CompletePrev => self.change_selection(-1),
+            // the lines of a multi-line command come first
+            Action::HistoryPrev if !self.command_win.move_cursor_line(-1) => {
+                self.recall_history(-1)
             }
+            Action::HistoryNext if !self.command_win.move_cursor_line(1) => self.recall_history(1),
+            Action::LineUp if !self.command_win.move_cursor_line(-1) => {
+                self.scroll(ScrollType::CursorUp)
+            }
+            Action::LineDown if !self.command_win.move_cursor_line(1) => {
+                self.scroll(ScrollType::CursorDown)
+            }
+            Action::HistoryPrev | Action::HistoryNext | Action::LineUp | Action::LineDown => {}
+            Action::PageUp => self.scroll(ScrollType::PageUp),
+            Action::PageDown => self.scroll(ScrollType::PageDown),
+            Action::Search => self.start_search(false),
+            Action::SearchRegex => self.start_search(true),
+            Action::SearchNext if has_matches => self.search_next(false),
+            Action::SearchPrev if has_matches => self.search_next(true),
+            Action::ClearSearch if has_matches => {
+                self.main_win.search_clear();
+                self.update_status_win();
+            }
+            Action::SearchNext | Action::SearchPrev | Action::ClearSearch => return false,
+            Action::OutputPrev => self.jump_output(-1),
+            Action::OutputNext => self.jump_output(1),
+            Action::SelectOutput => self.select_output(),
+            Action::Undo => self.command_win.undo(1),
+            Action::Redo => self.command_win.redo(1),
+            Action::Copy => self.copy(),
+            Action::Paste => self.paste_clipboard(),
+            Action::HistorySearch => self.start_history_search(),
+            Action::EditCommand => self.edit_command(),
+            // only the history search is left with it, otherwise the key goes to the editor
+            Action::Abort => return false,
+            Action::Edit(action) => return self.command_win.run_action(action),
         }
+        true
     }
 
-    fn normal_mode_key_pressed(&mut self, code: Key) {
+    /// replace the command line with an older (`delta` < 0) or newer command, skipping
+    /// duplicates of the current one
+    fn recall_history(&mut self, delta: i32) {
+        if self.jobs.is_empty() {
+            return;
+        }
         let old_idx = self.browse_job_history_idx as i32;
-        let old_command = self.command_win.get_text()[0].clone();
-        let mut update_job_idx = |delta: i32| {
-            if self.jobs.is_empty() {
-                return;
+        let old_command = self.command_text();
+        if old_command.is_empty() {
+            let command = self.jobs[old_idx as usize].args_printable();
+            self.command_win
+                .replace_with_undo(Self::command_lines(&command));
+            return;
+        }
+        let job_count = self.jobs.len() as i32;
+        let mut idx = old_idx + delta;
+        if idx < 0 {
+            idx = 0;
+        } else if idx >= job_count {
+            idx = job_count - 1;
+        }
+        let mut new_command;
+        loop {
+            self.browse_job_history_idx = idx.min(job_count - 1).max(0) as usize;
+            new_command = self.jobs[self.browse_job_history_idx].args_printable();
+            if new_command.as_str() != old_command.as_str() || (idx == 0 || idx == job_count - 1) {
+                break;
             }
-            if old_command.is_empty() {
-                self.command_win
-                    .replace(vec![self.jobs[old_idx as usize].args_printable()]);
-                return;
+            idx += delta;
+        }
+        // recalling a command can be undone with `u`
+        self.command_win
+            .replace_with_undo(Self::command_lines(&new_command));
+    }
+
+    /// the whole command, a multi-line command is joined with newlines
+    fn command_text(&self) -> String {
+        self.command_win.get_text().join("\n")
+    }
+
+    fn command_lines(command: &str) -> Vec<String> {
+        command.split('\n').map(String::from).collect()
+    }
+
+    /// Search mode: the command line is used to enter the search pattern.
+    /// Enter keeps the matches highlighted and switches to normal mode, so that n/N jump between
+    /// them. Escape restores the command line and the scroll position.
+    fn start_search(&mut self, use_regex: bool) {
+        self.search_input_active = true;
+        self.search_regex = use_regex;
+        self.search_error = false;
+        self.search_command_tmp = self.command_win.replace(vec![]);
+        self.search_scroll_pos_tmp = self.main_win.scroll_pos_y;
+        self.command_win.set_mode(edit::Mode::Insert);
+        self.main_win.search_clear();
+        self.update_search_status();
+        self.update_highlighter();
+    }
+
+    fn search_key_pressed(&mut self, code: Key) {
+        match code {
+            Key::Enter => self.end_search(true),
+            Key::Escape => self.end_search(false),
+            _ => {
+                self.edit_key_pressed(code);
+                self.update_search();
             }
-            let job_count = self.jobs.len() as i32;
-            let mut idx = old_idx + delta;
-            if idx < 0 {
-                idx = 0;
-            } else if idx >= job_count {
-                idx = job_count - 1;
-            }
-            let mut new_command;
-            loop {
-                self.browse_job_history_idx = idx.min(job_count - 1).max(0) as usize;
-                new_command = self.jobs[self.browse_job_history_idx].args_printable();
-                if new_command.as_str() != old_command.as_str()
-                    || (idx == 0 || idx == job_count - 1)
-                {
-                    break;
+        }
+    }
+
+    fn update_search(&mut self) {
+        let pattern = self.command_win.get_text()[0].clone();
+        match self.main_win.search(&pattern, self.search_regex) {
+            Ok(_) => {
+                self.search_error = false;
+                if let Some(current) = self.main_win.search_current() {
+                    let line = self.main_win.search_matches()[current].line;
+                    self.scroll_to_line(line);
                 }
-                idx += delta;
             }
-            self.command_win.replace(vec![new_command]);
+            Err(_) => self.search_error = true,
+        }
+        self.update_search_status();
+    }
+
+    fn end_search(&mut self, keep_matches: bool) {
+        self.search_input_active = false;
+        let command = std::mem::take(&mut self.search_command_tmp);
+        self.command_win.replace(command);
+        if keep_matches && !self.main_win.search_matches().is_empty() {
+            self.command_win.set_mode(edit::Mode::Normal);
+            self.update_search_status();
+        } else {
+            self.main_win.search_clear();
+            self.main_win.scroll_pos_y = self.search_scroll_pos_tmp;
+            self.main_win.redraw = true;
+            self.update_status_win();
+        }
+        self.update_info_win();
+        self.update_highlighter();
+    }
+
+    fn search_next(&mut self, newer: bool) {
+        if let Some(search_match) = self.main_win.search_next(!newer) {
+            self.scroll_to_line(search_match.line);
+        }
+        self.update_search_status();
+    }
+
+    fn update_search_status(&mut self) {
+        let kind = if self.search_regex { "regex" } else { "text" };
+        let count = self.main_win.search_matches().len();
+        let text = if self.search_error {
+            format!("search ({}): invalid regular expression", kind)
+        } else if let Some(current) = self.main_win.search_current() {
+            format!("search ({}): match {} of {}", kind, current + 1, count)
+        } else {
+            format!("search ({}): no matches", kind)
         };
+        self.status_win.replace(vec![text]);
+    }
 
-        match code {
-            Key::Enter => {
-                self.run_command();
-                self.command_win.set_mode(edit::Mode::Insert);
-            }
-            Key::D => {
-                if self.command_win.control {
-                    self.send_eof()
+    /// History search: the command line is used to enter the pattern, the matching commands are
+    /// shown in `info_win`. Ctrl-R selects the next older match, Enter runs it and Escape or
+    /// Ctrl-G restore the command line.
+    fn start_history_search(&mut self) {
+        let original = self.command_win.replace(vec![]);
+        self.history_search = Some(HistorySearch::new(original));
+        self.command_win.set_mode(edit::Mode::Insert);
+        self.update_highlighter();
+        self.update_history_search();
+    }
+
+    /// the keys of `accept-line`, `abort` and `history-search` in insert mode run the selected
+    /// match, cancel and select the next older match
+    fn history_search_key_pressed(&mut self, code: Key) {
+        match self
+            .keymap
+            .lookup(KeymapMode::Insert, self.key_binding(code))
+        {
+            Some(Action::AcceptLine) => self.end_history_search(true),
+            Some(Action::Abort) => self.end_history_search(false),
+            Some(Action::HistorySearch) => {
+                if let Some(search) = &mut self.history_search {
+                    search.next();
                 }
+                self.show_history_search();
+            }
+            _ => {
+                self.edit_key_pressed(code);
+                self.update_history_search();
+            }
+        }
+    }
+
+    fn update_history_search(&mut self) {
+        let pattern = self.command_text();
+        let commands = util::dedup_preserve_order(
+            self.jobs.iter().rev().map(|j| j.args_printable()).collect(),
+        );
+        if let Some(search) = &mut self.history_search {
+            search.update(&commands, &pattern);
+        }
+        self.show_history_search();
+    }
+
+    /// the selected match and the older ones in `info_win`, the matching chars highlighted
+    fn show_history_search(&mut self) {
+        let Some(search) = &self.history_search else {
+            return;
+        };
+        let info_text_lines = INFO_LINES as usize;
+        let lines = search.matches[search.current..]
+            .iter()
+            .take(info_text_lines)
+            .enumerate()
+            .map(|(i, m)| self.history_match_line(m, i == 0))
+            .collect();
+        self.info_win.replace(lines);
+        let text = match search.matches.len() {
+            0 => "history search: no matches".to_string(),
+            count => format!("history search: match {} of {}", search.current + 1, count),
+        };
+        self.status_win.replace(vec![text]);
+    }
+
+    fn history_match_line(&self, history_match: &HistoryMatch, selected: bool) -> String {
+        let (bg, fg) = match selected {
+            true => (self.colors.bg("Light Blue"), self.colors.fg("White")),
+            false => (String::new(), self.colors.fg("Black")),
+        };
+        let match_fg = self.colors.fg("Red");
+        let mut line = format!("{}{}", bg, fg);
+        for (i, c) in history_match.command.chars().enumerate() {
+            // the lines of a multi-line command are shown in one line
+            let c = if c == '\n' { ' ' } else { c };
+            if history_match.positions.contains(&i) {
+                line += &format!("{}{}{}", match_fg, c, fg);
+            } else {
+                line.push(c);
             }
-            Key::K => update_job_idx(-1),
-            Key::J => update_job_idx(1),
-            _ => self.command_win.key_pressed(code),
         }
+        line + &self.colors.reset()
+    }
+
+    fn end_history_search(&mut self, run: bool) {
+        let Some(search) = self.history_search.take() else {
+            return;
+        };
+        let command = match (run, search.current()) {
+            (true, Some(history_match)) => Self::command_lines(&history_match.command),
+            _ => search.original,
+        };
+        self.command_win.replace(command);
+        self.update_highlighter();
+        self.update_status_win();
+        self.reset_info_win();
+        if run && !search.matches.is_empty() {
+            self.run_command();
+        } else {
+            self.update_info_win();
+        }
+    }
+
+    fn main_win_line_count(&self) -> i32 {
+        self.main_win.grid_size().y
+    }
+
+    /// scroll `main_win` so that `line` is visible, centering it if it was not
+    fn scroll_to_line(&mut self, line: usize) {
+        let line = line as i32;
+        let line_count = self.main_win_line_count();
+        let text_line_count = self.main_win.lines().len() as i32;
+        let text_end = text_line_count + self.main_win.scroll_pos_y;
+        if line < text_end - line_count || line >= text_end {
+            let text_end = (line + line_count / 2 + 1)
+                .min(text_line_count)
+                .max(line_count.min(text_line_count));
+            self.main_win.scroll_pos_y = text_end - text_line_count;
+        }
+        self.main_win.redraw = true;
     }
 
     fn scroll(&mut self, scroll_type: ScrollType) {
-        let font_height = self.font.char_size.y * self.font_scale;
-        let window_height = self.window.size().y as i32;
-        let main_window_line_count = (window_height - font_height * 2) / font_height;
+        let main_window_line_count = self.main_win_line_count();
         let scroll_amount = match scroll_type {
             ScrollType::CursorUp => -1,
             ScrollType::CursorDown => 1,
@@ -337,7 +1037,7 @@ impl App<'_> {
         };
 
         self.main_win.scroll_pos_y += scroll_amount;
-        let text_line_count = self.main_win.text.len() as i32;
+        let text_line_count = self.main_win.lines().len() as i32;
 
         if self.main_win.scroll_pos_y > 0 || text_line_count <= main_window_line_count {
             self.main_win.scroll_pos_y = 0;
@@ -349,7 +1049,7 @@ impl App<'_> {
     }
 
     fn create_info_text(&self) -> Vec<String> {
-        let command_text = self.command_win.get_text()[0].clone();
+        let command_text = self.command_text();
         let args = Args::new_notrim(&command_text).args;
 
         let len = args.len();
@@ -379,7 +1079,7 @@ impl App<'_> {
     fn change_selection(&mut self, delta: i32) {
         if !self.info_active {
             self.info_active = true;
-            self.info_command_tmp = self.command_win.get_text()[0].clone();
+            self.info_command_tmp = self.command_text();
             self.info_selection = 0;
             self.info_cursor_x = self.info_command_tmp.len();
         } else {
@@ -396,16 +1096,12 @@ impl App<'_> {
     fn reset_info_win(&mut self) {
         self.info_text = vec![];
         self.info_win.redraw = true;
-        self.info_command_tmp = self.command_win.get_text()[0].clone();
+        self.info_command_tmp = self.command_text();
         self.info_active = false;
     }
 
     fn check_info_change(&mut self) {
-        let command = if self.command_win.get_text().is_empty() {
-            "".to_string()
-        } else {
-            self.command_win.get_text()[0].clone()
-        };
+        let command = self.command_text();
         if command == self.info_command_tmp {
             return;
         }
@@ -413,12 +1109,15 @@ impl App<'_> {
     }
 
     fn update_info_win(&mut self) {
+        if self.history_search.is_some() {
+            return self.show_history_search();
+        }
         self.check_info_change();
         let len = self.info_text.len();
         if len == 0 {
             self.info_text = self.create_info_text();
         }
-        let info_text_lines = 3;
+        let info_text_lines = INFO_LINES as usize;
         let add_empty_lines =
             (info_text_lines - self.info_text.len() % info_text_lines) % info_text_lines;
         self.info_text.extend(vec![String::new(); add_empty_lines]);
@@ -464,7 +1163,7 @@ impl App<'_> {
         self.info_win.replace(lines);
     }
 
-    fn update_pwd_directory(&mut self) {
+    fn update_status_win(&mut self) {
         let pwd = std::env::current_dir().unwrap();
         let text = if let Some(job) = self.jobs.last() {
             let return_code = job.return_code.unwrap_or(0);
@@ -474,6 +1173,11 @@ impl App<'_> {
             format!("{}", pwd.display())
         };
         self.status_win.replace(vec![text]);
+    }
+
+    fn update_pwd_directory(&mut self) {
+        let pwd = std::env::current_dir().unwrap();
+        self.update_status_win();
 
         let mut dir_adorned = String::new();
         let mut dir_plain = String::new();
@@ -508,7 +1212,7 @@ impl App<'_> {
                     args.printable()
                 }
             };
-            self.command_win.replace(vec![command]);
+            self.command_win.replace(Self::command_lines(&command));
         } else {
             self.reset_info_win();
         }
@@ -519,19 +1223,27 @@ impl App<'_> {
         let pwd = std::env::current_dir().unwrap();
 
         self.main_win.scroll_pos_y = 0;
-        let command = self.command_win.replace(vec![]);
-        let command = if command.is_empty() {
-            "".to_string()
-        } else {
-            command[0].trim().to_string()
-        };
-        if self.stdin_tx.is_some() {
+        self.confirm_paste = false;
+        if self.command_win.is_masked() {
+            // sent as typed and not written anywhere
+            let secret = self.command_win.take_masked();
+            self.command_win.set_masked(false);
+            self.update_status_win();
+            if let Some(stdin_tx) = &self.stdin_tx {
+                // the job may have ended since it asked
+                let _ = stdin_tx.send(format!("{}\n", secret));
+            }
+            return;
+        }
+        let command = self.command_win.replace(vec![]).join("\n");
+        let command = command.trim().to_string();
+        if let Some(stdin_tx) = &self.stdin_tx {
             let send_string = if !command.trim().is_empty() {
                 format!("{}\n", command)
             } else {
                 "\n".to_string()
             };
-            self.stdin_tx.as_ref().unwrap().send(send_string).unwrap();
+            stdin_tx.send(send_string).unwrap();
         } else {
             let args = Args::new(&command).args;
             let glob = Glob::from_path(".").unwrap();
@@ -555,6 +1267,11 @@ impl App<'_> {
 
                 let job_id = self.jobs.len();
                 self.browse_job_history_idx = job_id;
+                self.output_idx = None;
+
+                let prompt_line = self.main_win.cursor_position.y as usize;
+                self.main_win.add_mark(prompt_line, text::MarkKind::Prompt);
+                self.main_win.add_mark(prompt_line, text::MarkKind::Command);
 
                 self.main_win.write(&format!(
                     "{}{}{} {}> {}{}\n",
@@ -565,6 +1282,9 @@ impl App<'_> {
                     job.args_printable(),
                     self.colors.reset()
                 ));
+                self.job_output_start = self.main_win.cursor_position.y as usize;
+                self.main_win
+                    .add_mark(self.job_output_start, text::MarkKind::OutputStart);
 
                 self.stop_thread = Builtin::run(self.tx.clone(), job);
             }
@@ -590,8 +1310,7 @@ impl App<'_> {
         let fg = colors.fg("Black");
         let reset = colors.reset();
 
-        let main_win_window_width =
-            self.main_win.get_size().x / (self.font.char_size.x * self.font_scale);
+        let main_win_window_width = self.main_win.grid_size().x;
         let spaces = if main_win_window_width as usize > command.len() {
             " ".repeat(main_win_window_width as usize - command.len())
         } else {
@@ -616,6 +1335,61 @@ impl App<'_> {
         self.update_pwd_directory();
     }
 
+    /// handle messages from jobs until the time budget of the frame started at `frame_start` is
+    /// used, the remaining messages are handled in the next frames
+    /// consecutive output is written to main_win at once
+    fn handle_exec_messages_budgeted(&mut self, frame_start: std::time::Instant) {
+        let mut output = String::new();
+        while frame_start.elapsed() < EXEC_MESSAGE_TIME_BUDGET {
+            let Ok(message) = self.rx.try_recv() else {
+                break;
+            };
+            match message {
+                ExecMessage::StdOut(s) | ExecMessage::StdErr(s) => {
+                    output.push_str(&s);
+                    if output.len() >= OUTPUT_CHUNK_SIZE {
+                        self.write_output(&output);
+                        output.clear();
+                    }
+                }
+                message => {
+                    if !output.is_empty() {
+                        self.write_output(&output);
+                        output.clear();
+                    }
+                    self.handle_exec_messages(message);
+                }
+            }
+        }
+        if !output.is_empty() {
+            self.write_output(&output);
+        }
+    }
+
+    fn write_output(&mut self, output: &str) {
+        let cursor_style = self.main_win.get_cursor_style();
+        self.main_win.write(output);
+        self.main_win.redraw = true;
+        // the running program reads its input from command_win, so that is where a
+        // cursor shape set with `CSI Ps SP q` is shown
+        if self.command_is_running && self.main_win.get_cursor_style() != cursor_style {
+            self.command_win
+                .set_cursor_style(self.main_win.get_cursor_style());
+        }
+        // without a pty the echo of the program can't be seen, but the prompt can
+        let line = self
+            .main_win
+            .plain_line(self.main_win.cursor_position.y as usize);
+        if self.command_is_running
+            && !self.command_win.is_masked()
+            && util::is_password_prompt(&line)
+        {
+            self.command_win.set_masked(true);
+            self.status_win
+                .replace(vec!["password input, the text is hidden".to_string()]);
+        }
+    }
+
     fn handle_exec_messages(&mut self, message: ExecMessage) {
         match message {
             ExecMessage::StdInQueue(tx) => {
@@ -623,13 +1397,15 @@ impl App<'_> {
                 self.command_win
                     .set_background_color(self.command_bg_color_running);
                 self.command_is_running = true;
+                self.update_highlighter();
             }
-            ExecMessage::StdOut(output) | ExecMessage::StdErr(output) => {
-                self.main_win.write(&output);
-                self.main_win.redraw = true;
-            }
-            ExecMessage::JobDone(job) => {
+            ExecMessage::StdOut(output) | ExecMessage::StdErr(output) => self.write_output(&output),
+            ExecMessage::JobDone(mut job) => {
                 self.end_job();
+                let output_end = self.main_win_output_end();
+                self.main_win
+                    .add_mark(output_end, text::MarkKind::OutputEnd(job.return_code));
+                job.output_lines = Some(self.job_output_start..output_end);
                 self.jobs.push(job);
                 self.update_pwd_directory();
                 self.write_intermediate_status_win();
@@ -640,20 +1416,47 @@ impl App<'_> {
         }
     }
 
+    /// index of the line after the last line with output in `main_win`
+    fn main_win_output_end(&self) -> usize {
+        let y = self.main_win.cursor_position.y as usize;
+        match self.main_win.lines().get(y) {
+            Some(line) if !line.is_empty() => y + 1,
+            _ => y,
+        }
+    }
+
     fn handle_builtin_command(&mut self, cmd: BuiltinCommand) {
         match cmd {
             BuiltinCommand::Jobs => Builtin::jobs(self.tx.clone(), &self.jobs),
+            BuiltinCommand::SaveOutput {
+                job,
+                filename,
+                format,
+                job_id,
+            } => Builtin::save_output(
+                self.tx.clone(),
+                job,
+                &self.main_win,
+                &self.jobs,
+                &filename,
+                format,
+                job_id,
+            ),
+            BuiltinCommand::Bind { job } => Builtin::bind(self.tx.clone(), job, &mut self.keymap),
+            BuiltinCommand::Edit { job, path } => self.start_edit(job, &path),
         };
     }
 
     fn send_eof(&mut self) {
-        let user_input = &self.command_win.get_text()[0];
+        let user_input = &self.command_text();
         if self.command_is_running {
             if user_input.is_empty() {
                 self.end_job();
             }
         } else if user_input.is_empty() {
             self.exit();
+        } else if self.confirm_paste && self.command_win.line_count() > 1 {
+            self.ask_confirm_paste();
         } else {
             self.run_command();
         }
@@ -670,27 +1473,73 @@ impl App<'_> {
         self.command_win
             .set_background_color(self.command_bg_color_normal);
         self.command_is_running = false;
+        self.main_win.cursor_style(text::CursorStyle::default());
+        self.main_win.bracketed_paste = false;
+        if self.command_win.is_masked() {
+            self.command_win.set_masked(false);
+            self.update_status_win();
+        }
+        self.command_win.set_mode(self.command_win.mode);
+        // also after a job, it may have changed the directory or created files
+        self.update_highlighter();
+    }
+
+    /// only commands are highlighted, not search patterns or the input of a running job
+    fn update_highlighter(&mut self) {
+        let highlighter: Option<edit::Highlighter> = match self.command_is_running
+            || self.search_input_active
+            || self.history_search.is_some()
+        {
+            true => None,
+            false => Some(Box::new(highlight::highlight)),
+        };
+        self.command_win.set_highlighter(highlighter);
     }
 
     pub fn load_jobs() -> Vec<Job> {
-        let mut jobs = Vec::new();
-        if let Ok(file_content) = config::Config::new().get_file("jobs.csv") {
-            for line in file_content.lines() {
-                let args = Args::new(line).args;
-                let job = Job::new(args);
-                jobs.push(job);
-            }
+        match config::Config::new().get_file("jobs.csv") {
+            Ok(file_content) => Self::parse_jobs(&file_content),
+            Err(_) => vec![],
         }
-        jobs
     }
 
     pub fn save_jobs(&self) {
-        let mut file_content = String::new();
-        for job in self.jobs.iter() {
-            file_content.push_str(&format!("{}\n", job.args_printable()));
-        }
         config::Config::new()
-            .write_file("jobs.csv", &file_content)
+            .write_file("jobs.csv", &Self::jobs_file_content(&self.jobs))
             .unwrap();
     }
+
+    /// one command per line, see `Args::history_line`
+    fn jobs_file_content(jobs: &[Job]) -> String {
+        jobs.iter()
+            .map(|job| format!("{}\n", Args::from_vec(job.args.clone()).history_line()))
+            .collect()
+    }
+
+    fn parse_jobs(file_content: &str) -> Vec<Job> {
+        file_content
+            .lines()
+            .map(|line| Job::new(Args::from_history_line(line).args))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_file() {
+        let commands = ["ls -l", "git commit -m \"a\nb\"", "echo \"a\\\\b\""];
+        let jobs: Vec<Job> = commands
+            .iter()
+            .map(|command| Job::new(Args::new(command).args))
+            .collect();
+        let file_content = App::jobs_file_content(&jobs);
+        assert_eq!(file_content.lines().count(), commands.len());
+        let loaded = App::parse_jobs(&file_content);
+        let args: Vec<&Vec<String>> = loaded.iter().map(|job| &job.args).collect();
+        assert_eq!(args, jobs.iter().map(|job| &job.args).collect::<Vec<_>>());
+        assert_eq!(loaded[1].args[3], "a\nb");
+    }
 }
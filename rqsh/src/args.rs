@@ -4,6 +4,15 @@ pub struct Args {
 
 impl Args {
     pub fn new(s: &str) -> Self {
+        Self::parse(s).0
+    }
+
+    /// the command continues on the next line: a quote is still open or the last char is a `\`
+    pub fn is_incomplete(s: &str) -> bool {
+        Self::parse(s).1
+    }
+
+    fn parse(s: &str) -> (Self, bool) {
         let mut args = vec![];
         let mut arg = String::new();
         let mut in_quote = false;
@@ -11,7 +20,10 @@ impl Args {
 
         for c in s.chars() {
             if escaped {
-                arg.push(c);
+                // an escaped newline continues the command on the next line
+                if c != '\n' {
+                    arg.push(c);
+                }
                 escaped = false;
                 continue;
             }
@@ -23,7 +35,7 @@ impl Args {
                 '"' => {
                     in_quote = !in_quote;
                 }
-                ' ' if !in_quote => {
+                ' ' | '\n' if !in_quote => {
                     if !arg.is_empty() {
                         args.push(arg.clone());
                         arg.clear();
@@ -39,12 +51,12 @@ impl Args {
             args.push(arg);
         }
 
-        Args { args }
+        (Args { args }, in_quote || escaped)
     }
 
     pub fn new_notrim(s: &str) -> Self {
         let mut args = Args::new(s);
-        if s.ends_with(' ') {
+        if s.ends_with(' ') || s.ends_with('\n') {
             args.args.push(String::new());
         }
         args
@@ -59,13 +71,13 @@ impl Args {
         for arg in &self.args {
             let mut arg_quotes = String::new();
             for c in arg.chars() {
-                if c == '"' {
+                if c == '"' || c == '\\' {
                     arg_quotes.push('\\');
                 }
                 arg_quotes.push(c);
             }
 
-            if arg_quotes.contains(' ') {
+            if arg_quotes.contains(' ') || arg_quotes.contains('\n') {
                 s.push_str(&format!("\"{}\" ", arg_quotes));
             } else {
                 s.push_str(&format!("{} ", arg_quotes));
@@ -74,6 +86,38 @@ impl Args {
         s.pop();
         s
     }
+
+    /// `printable` on a single line, for the history file: newlines are written as `\n` and
+    /// backslashes as `\\`
+    pub fn history_line(&self) -> String {
+        let mut line = String::new();
+        for c in self.printable().chars() {
+            match c {
+                '\\' => line.push_str("\\\\"),
+                '\n' => line.push_str("\\n"),
+                _ => line.push(c),
+            }
+        }
+        line
+    }
+
+    /// the args of a line written by `history_line`
+    pub fn from_history_line(line: &str) -> Self {
+        let mut s = String::new();
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => s.push('\n'),
+                ('\\', Some('\\')) => s.push('\\'),
+                _ => {
+                    s.push(c);
+                    continue;
+                }
+            }
+            chars.next();
+        }
+        Self::new(&s)
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +159,39 @@ mod tests {
         let args = Args::new("ls -l \"foo \\\"bar\\\"\"");
         assert_eq!(args.printable(), "ls -l \"foo \\\"bar\\\"\"");
     }
+
+    #[test]
+    fn test_args_multi_line() {
+        let args = Args::new("echo one\ntwo \\\nthree \"four\nfive\"");
+        assert_eq!(args.args, vec!["echo", "one", "two", "three", "four\nfive"]);
+        assert_eq!(args.printable(), "echo one two three \"four\nfive\"");
+    }
+
+    #[test]
+    fn test_history_line() {
+        for command in [
+            "ls -l",
+            "git commit -m \"a\nb\"",
+            "echo \"foo \\\"bar\\\"\"",
+            "echo \"\\\"\\n\\\"\"",
+        ] {
+            let args = Args::new(command);
+            let line = args.history_line();
+            assert!(!line.contains('\n'), "{:?}", line);
+            assert_eq!(Args::from_history_line(&line).args, args.args, "{:?}", line);
+        }
+        assert_eq!(
+            Args::new("git commit -m \"a\nb\"").history_line(),
+            "git commit -m \"a\\nb\""
+        );
+    }
+
+    #[test]
+    fn test_args_is_incomplete() {
+        assert!(!Args::is_incomplete("ls -l"));
+        assert!(Args::is_incomplete("echo \"foo"));
+        assert!(!Args::is_incomplete("echo \"foo\nbar\""));
+        assert!(Args::is_incomplete("ls \\"));
+        assert!(!Args::is_incomplete("ls \\\\"));
+    }
 }
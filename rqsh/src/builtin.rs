@@ -4,6 +4,7 @@ use std::sync::Arc;
 use std::thread;
 
 use crate::execute::{BuiltinCommand, ExecMessage, Execute, Job};
+use crate::keymap::Keymap;
 /*
 1. cd: Change the current directory.
 2. echo: Print arguments to the standard output.
@@ -43,13 +44,19 @@ use crate::execute::{BuiltinCommand, ExecMessage, Execute, Job};
 35. help: Display help for a built-in command.
 */
 
+/// the commands `Builtin::run` doesn't start as a program
+pub const BUILTINS: [&str; 6] = ["bind", "cd", "edit", "jobs", "save-output", "yes"];
+
 pub struct Builtin {}
 
 impl Builtin {
     pub fn run(tx: mpsc::Sender<ExecMessage>, job: Job) -> Option<Arc<AtomicBool>> {
         match job.args[0].as_str() {
+            "bind" => Builtin::cmd_bind(tx, job),
             "cd" => Builtin::cmd_cd(tx, job),
+            "edit" => Builtin::cmd_edit(tx, job),
             "jobs" => Builtin::cmd_jobs(tx, job),
+            "save-output" => Builtin::cmd_save_output(tx, job),
             "yes" => Builtin::cmd_yes(tx, job),
             _ => Builtin::cmd_run_exec(tx, job),
         }
@@ -76,11 +83,8 @@ impl Builtin {
             let path = &job.args[1];
             let path = if path == "-" {
                 std::env::var("OLDPWD").unwrap()
-            } else if path.starts_with('~') {
-                let home = std::env::var("HOME").unwrap();
-                path.replace('~', &home)
             } else {
-                path.to_string()
+                config::expand_home(path)
             };
             let path = std::path::Path::new(&path);
             if path.exists() {
@@ -98,6 +102,36 @@ impl Builtin {
         None
     }
 
+    fn cmd_bind(tx: mpsc::Sender<ExecMessage>, mut job: Job) -> Option<Arc<AtomicBool>> {
+        job.start();
+        tx.send(ExecMessage::BuiltinCommand(BuiltinCommand::Bind { job }))
+            .unwrap();
+        None
+    }
+
+    /// the editor runs in the window of rqsh, see `App::start_edit`
+    fn cmd_edit(tx: mpsc::Sender<ExecMessage>, mut job: Job) -> Option<Arc<AtomicBool>> {
+        job.start();
+        match &job.args[1..] {
+            [path] => {
+                let path = config::expand_home(path);
+                tx.send(ExecMessage::BuiltinCommand(BuiltinCommand::Edit {
+                    job,
+                    path,
+                }))
+                .unwrap();
+            }
+            _ => {
+                tx.send(ExecMessage::StdErr("usage: edit FILE".to_string()))
+                    .unwrap();
+                job.return_code = Some(1);
+                job.end();
+                tx.send(ExecMessage::JobDone(job)).unwrap();
+            }
+        }
+        None
+    }
+
     fn cmd_jobs(tx: mpsc::Sender<ExecMessage>, mut job: Job) -> Option<Arc<AtomicBool>> {
         job.start();
         tx.send(ExecMessage::BuiltinCommand(BuiltinCommand::Jobs))
@@ -106,6 +140,53 @@ impl Builtin {
         None
     }
 
+    fn cmd_save_output(tx: mpsc::Sender<ExecMessage>, mut job: Job) -> Option<Arc<AtomicBool>> {
+        const USAGE: &str = "usage: save-output [-f plain|ansi|html] [-j JOB] FILE";
+
+        job.start();
+        let mut format = None;
+        let mut job_id = None;
+        let mut filename = None;
+        let mut error = None;
+        let mut args = job.args[1..].iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-f" => match args.next().and_then(|f| text::ExportFormat::from_name(f)) {
+                    Some(f) => format = Some(f),
+                    None => error = Some(USAGE.to_string()),
+                },
+                "-j" => match args.next().and_then(|j| j.parse::<usize>().ok()) {
+                    Some(j) => job_id = Some(j),
+                    None => error = Some(USAGE.to_string()),
+                },
+                _ if filename.is_none() => filename = Some(arg.to_string()),
+                _ => error = Some(USAGE.to_string()),
+            }
+        }
+
+        match (filename, error) {
+            (Some(filename), None) => {
+                let filename = config::expand_home(&filename);
+                let format = format.unwrap_or_else(|| text::ExportFormat::from_filename(&filename));
+                tx.send(ExecMessage::BuiltinCommand(BuiltinCommand::SaveOutput {
+                    job,
+                    filename,
+                    format,
+                    job_id,
+                }))
+                .unwrap();
+            }
+            (_, error) => {
+                tx.send(ExecMessage::StdErr(error.unwrap_or(USAGE.to_string())))
+                    .unwrap();
+                job.return_code = Some(1);
+                job.end();
+                tx.send(ExecMessage::JobDone(job)).unwrap();
+            }
+        }
+        None
+    }
+
     fn cmd_yes(tx: mpsc::Sender<ExecMessage>, mut job: Job) -> Option<Arc<AtomicBool>> {
         job.start();
         tx.send(ExecMessage::StdOut("y".to_string())).unwrap();
@@ -126,9 +207,9 @@ impl Builtin {
     pub fn jobs(tx: mpsc::Sender<ExecMessage>, jobs: &[Job]) {
         let mut v = vec![];
         for job in jobs {
-            if job.start_time.is_some() && job.end_time.is_none() {
+            if let (Some(start_time), None) = (job.start_time, job.end_time) {
                 let t = std::time::SystemTime::now()
-                    .duration_since(job.start_time.unwrap())
+                    .duration_since(start_time)
                     .unwrap();
                 let time_human_readable = format!("{}.{:03}s", t.as_secs(), t.subsec_millis());
                 v.push(format!(
@@ -140,4 +221,56 @@ impl Builtin {
         }
         tx.send(ExecMessage::StdOut(v.join("\n"))).unwrap();
     }
+
+    /// write the scrollback of `main_win`, or the output of job `job_id`, to `filename`
+    pub fn save_output(
+        tx: mpsc::Sender<ExecMessage>,
+        mut job: Job,
+        main_win: &text::Text,
+        jobs: &[Job],
+        filename: &str,
+        format: text::ExportFormat,
+        job_id: Option<usize>,
+    ) {
+        let output = match job_id {
+            None => Ok(main_win.export(format)),
+            Some(id) => match jobs.get(id).map(|job| job.output_lines.clone()) {
+                Some(Some(lines)) => Ok(main_win.export_lines(lines, format)),
+                Some(None) => Err(format!("save-output: job {}: output not available", id)),
+                None => Err(format!("save-output: job {}: no such job", id)),
+            },
+        };
+        let result = output.and_then(|output| {
+            std::fs::write(filename, output)
+                .map_err(|e| format!("save-output: {}: {}", filename, e))
+        });
+        job.return_code = Some(match result {
+            Ok(()) => 0,
+            Err(e) => {
+                tx.send(ExecMessage::StdErr(e)).unwrap();
+                1
+            }
+        });
+        job.end();
+        tx.send(ExecMessage::JobDone(job)).unwrap();
+    }
+
+    /// list or change the key bindings, see `Keymap::bind_command`
+    pub fn bind(tx: mpsc::Sender<ExecMessage>, mut job: Job, keymap: &mut Keymap) {
+        job.return_code = Some(match keymap.bind_command(&job.args[1..]) {
+            Ok(output) => {
+                if !output.is_empty() {
+                    tx.send(ExecMessage::StdOut(output)).unwrap();
+                }
+                0
+            }
+            Err(e) => {
+                tx.send(ExecMessage::StdErr(format!("bind: {}", e)))
+                    .unwrap();
+                1
+            }
+        });
+        job.end();
+        tx.send(ExecMessage::JobDone(job)).unwrap();
+    }
 }
@@ -12,6 +12,8 @@ pub struct Job {
     pub return_code: Option<i32>,
     pub start_time: Option<std::time::SystemTime>,
     pub end_time: Option<std::time::SystemTime>,
+    /// lines of the main window that hold the output of the job
+    pub output_lines: Option<std::ops::Range<usize>>,
 }
 
 impl Job {
@@ -21,6 +23,7 @@ impl Job {
             return_code: None,
             start_time: None,
             end_time: None,
+            output_lines: None,
         }
     }
 
@@ -40,6 +43,19 @@ impl Job {
 
 pub enum BuiltinCommand {
     Jobs,
+    Bind {
+        job: Job,
+    },
+    Edit {
+        job: Job,
+        path: String,
+    },
+    SaveOutput {
+        job: Job,
+        filename: String,
+        format: text::ExportFormat,
+        job_id: Option<usize>,
+    },
 }
 
 pub enum ExecMessage {
@@ -0,0 +1,141 @@
+//! the font of rqsh, set in the font file in the config directory
+//!
+//! `file PATH` is a TrueType, OpenType, BDF, PCF or PSF font, like
+//! `/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf`, without it the built in font is used.
+//! `size WxH` is the cell size TrueType and OpenType fonts are rasterized at, `antialias on|off`,
+//! `gamma N` and `hinting on|off` set how. The built in font is the fallback for the chars a
+//! font file doesn't have.
+
+use std::path::PathBuf;
+
+use sfml::system::Vector2i;
+
+const FONT_FILE: &str = "font";
+const BUILTIN_FONT: &str = "font_bold";
+const BUILTIN_FALLBACK_FONT: &str = "font";
+const BUILTIN_FONT_SIZE: Vector2i = Vector2i::new(20, 40);
+
+#[derive(Debug, PartialEq)]
+pub struct FontConfig {
+    file: Option<PathBuf>,
+    size: Vector2i,
+    options: font::RasterOptions,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            size: BUILTIN_FONT_SIZE,
+            options: font::RasterOptions::default(),
+        }
+    }
+}
+
+fn parse_on_off(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off: {}", value)),
+    }
+}
+
+impl FontConfig {
+    pub fn load() -> Self {
+        let mut font_config = Self::default();
+        config::Config::new().parse_file(FONT_FILE, |line| font_config.parse_line(line));
+        font_config
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let Some((key, value)) = config::split_key_value(line)? else {
+            return Ok(());
+        };
+        match key {
+            "file" => self.file = Some(PathBuf::from(config::expand_home(value))),
+            "size" => {
+                self.size = value
+                    .split_once('x')
+                    .and_then(|(w, h)| Some(Vector2i::new(w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|size| size.x > 0 && size.y > 0)
+                    .ok_or(format!("expected WIDTHxHEIGHT: {}", value))?
+            }
+            "antialias" => self.options.antialias = parse_on_off(value)?,
+            "hinting" => self.options.hinting = parse_on_off(value)?,
+            "gamma" => {
+                self.options.gamma = value
+                    .parse()
+                    .ok()
+                    .filter(|&gamma: &f32| gamma > 0.0)
+                    .ok_or(format!("expected a number above 0: {}", value))?
+            }
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// the font file, or the built in font if there is none or it can't be loaded
+    pub fn font(&self) -> font::Font {
+        let builtin = || {
+            let mut font =
+                font::Font::load(BUILTIN_FONT, BUILTIN_FONT_SIZE).expect("Failed to load font");
+            if let Ok(fallback) = font::Font::load(BUILTIN_FALLBACK_FONT, BUILTIN_FONT_SIZE) {
+                font.add_fallback(fallback);
+            }
+            font
+        };
+        let Some(file) = &self.file else {
+            return builtin();
+        };
+        match font::Font::from_file(file, self.size, self.options) {
+            Ok(mut font) => {
+                font.add_fallback(builtin());
+                font
+            }
+            Err(e) => {
+                eprintln!("{}: {}", FONT_FILE, e);
+                builtin()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let mut config = FontConfig::default();
+        for line in [
+            "# DejaVu",
+            "",
+            "file /usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+            "size 10x20",
+            "antialias off",
+            "gamma 1.8",
+            "hinting off",
+        ] {
+            assert_eq!(config.parse_line(line), Ok(()));
+        }
+        let file = PathBuf::from("/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf");
+        assert_eq!(
+            config,
+            FontConfig {
+                file: Some(file),
+                size: Vector2i::new(10, 20),
+                options: font::RasterOptions {
+                    antialias: false,
+                    gamma: 1.8,
+                    hinting: false,
+                },
+            }
+        );
+        assert!(config.parse_line("size 10").is_err());
+        assert!(config.parse_line("size 0x20").is_err());
+        assert!(config.parse_line("gamma -1").is_err());
+        assert!(config.parse_line("antialias yes").is_err());
+        assert!(config.parse_line("color red").is_err());
+        assert!(config.parse_line("file").is_err());
+    }
+}
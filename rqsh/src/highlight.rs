@@ -0,0 +1,256 @@
+//! colors for the command line, see `edit::Edit::set_highlighter`
+//!
+//! The command line is split into words like `Args` does it. The command name is green if it is
+//! a builtin or found on `$PATH`, red otherwise. Quoted strings, glob chars that expand, the
+//! redirection operators and paths that don't exist have their own colors.
+
+use std::path::Path;
+
+use sfml::graphics::Color;
+use text::Highlight;
+
+use crate::builtin::BUILTINS;
+use crate::glob::Glob;
+
+const COMMAND_COLOR: &str = "Green";
+const UNKNOWN_COMMAND_COLOR: &str = "Red";
+const QUOTE_COLOR: &str = "Purple";
+const GLOB_COLOR: &str = "Light Blue";
+const REDIRECTION_COLOR: &str = "Blue";
+const MISSING_PATH_COLOR: &str = "Light Red";
+
+/// a word of the command line, the positions are line and column in the editor
+#[derive(Default)]
+struct Word {
+    /// the word as `Args` returns it, without quotes and escapes
+    text: String,
+    /// all chars of the word, including quotes and escapes
+    chars: Vec<(usize, usize)>,
+    /// the quoted chars and the quotes around them
+    quoted: Vec<(usize, usize)>,
+    /// the `*` and `?` chars
+    glob_chars: Vec<(usize, usize)>,
+}
+
+/// split the lines into words, with the rules of `Args::new`
+fn words(lines: &[String]) -> Vec<Word> {
+    let mut words = vec![];
+    let mut word = Word::default();
+    let mut in_quote = false;
+    let mut escaped = false;
+    for (y, line) in lines.iter().enumerate() {
+        // the lines are joined with '\n', it is at the column after the last char
+        for (x, c) in line.chars().chain(std::iter::once('\n')).enumerate() {
+            if y == lines.len() - 1 && c == '\n' {
+                break;
+            }
+            let pos = (y, x);
+            if escaped {
+                escaped = false;
+                if c != '\n' {
+                    word.text.push(c);
+                    word.chars.push(pos);
+                }
+                continue;
+            }
+            match c {
+                '\\' => escaped = true,
+                '"' => in_quote = !in_quote,
+                ' ' | '\n' if !in_quote => {
+                    if !word.chars.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                    continue;
+                }
+                '*' | '?' => word.glob_chars.push(pos),
+                _ => {}
+            }
+            if c != '\\' && c != '"' {
+                word.text.push(c);
+            }
+            word.chars.push(pos);
+            if in_quote || c == '"' {
+                word.quoted.push(pos);
+            }
+        }
+    }
+    if !word.chars.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// the length of the operator at the start of a redirection like `>out`, `2>&1` or `<in`
+fn redirection_len(word: &str) -> Option<usize> {
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &word[digits..];
+    let op = if rest.starts_with(">>") {
+        2
+    } else if rest.starts_with('>') || rest.starts_with('<') {
+        1
+    } else {
+        return None;
+    };
+    let fd = match rest[op..].strip_prefix('&') {
+        Some(fd) => 1 + fd.chars().take_while(|c| c.is_ascii_digit()).count(),
+        None => 0,
+    };
+    Some(digits + op + fd)
+}
+
+/// a builtin, a path to a file or a program in one of the directories of `$PATH`
+fn is_command(name: &str, dir: &Path) -> bool {
+    if BUILTINS.contains(&name) {
+        return true;
+    }
+    if name.contains('/') {
+        return dir.join(name).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+/// the highlights of the lines of the command line, sorted by position
+pub fn highlight(lines: &[String]) -> Vec<Highlight> {
+    highlight_in(lines, Path::new("."))
+}
+
+/// relative paths and globs are looked up in `dir`
+fn highlight_in(lines: &[String], dir: &Path) -> Vec<Highlight> {
+    let colors = color::AnsiColor::new();
+    let color = |name: &str| colors.get_color(name).unwrap();
+    let glob = dir.to_str().and_then(|dir| Glob::from_path(dir).ok());
+
+    // one color per char, the later colors have precedence
+    let mut char_colors: Vec<((usize, usize), Color)> = vec![];
+    for (i, word) in words(lines).iter().enumerate() {
+        let expands = !word.glob_chars.is_empty()
+            && glob.as_ref().is_some_and(|glob| match i {
+                0 => !glob.match_path_single(&word.text).is_empty(),
+                _ => !glob.match_path_multiple(&word.text).is_empty(),
+            });
+        if let Some(len) = redirection_len(&word.text).filter(|_| word.quoted.is_empty()) {
+            let op = word.chars.iter().take(len);
+            char_colors.extend(op.map(|pos| (*pos, color(REDIRECTION_COLOR))));
+            continue;
+        }
+        let word_color = if i == 0 && is_command(&word.text, dir) {
+            Some(COMMAND_COLOR)
+        } else if i == 0 {
+            Some(UNKNOWN_COMMAND_COLOR)
+        } else if word.text.contains('/') && !expands && !dir.join(&word.text).exists() {
+            Some(MISSING_PATH_COLOR)
+        } else {
+            None
+        };
+        if let Some(word_color) = word_color {
+            char_colors.extend(word.chars.iter().map(|pos| (*pos, color(word_color))));
+        }
+        char_colors.extend(word.quoted.iter().map(|pos| (*pos, color(QUOTE_COLOR))));
+        if expands {
+            char_colors.extend(word.glob_chars.iter().map(|pos| (*pos, color(GLOB_COLOR))));
+        }
+    }
+    // stable, so the last color of a char comes last
+    char_colors.sort_by_key(|(pos, _)| *pos);
+
+    let mut highlights: Vec<Highlight> = vec![];
+    for (i, ((line, column), color)) in char_colors.iter().enumerate() {
+        if char_colors
+            .get(i + 1)
+            .is_some_and(|(next, _)| next == &(*line, *column))
+        {
+            continue;
+        }
+        match highlights.last_mut() {
+            Some(h) if h.line == *line && h.end == *column && h.color == *color => h.end += 1,
+            _ => highlights.push(Highlight {
+                line: *line,
+                start: *column,
+                end: column + 1,
+                color: *color,
+            }),
+        }
+    }
+    highlights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(|s| s.to_string()).collect()
+    }
+
+    fn spans(text: &str) -> Vec<(usize, usize, usize, &'static str)> {
+        let colors = color::AnsiColor::new();
+        let names = [
+            COMMAND_COLOR,
+            UNKNOWN_COMMAND_COLOR,
+            QUOTE_COLOR,
+            GLOB_COLOR,
+            REDIRECTION_COLOR,
+            MISSING_PATH_COLOR,
+        ];
+        // other tests change the current directory
+        highlight_in(&lines(text), Path::new(env!("CARGO_MANIFEST_DIR")))
+            .iter()
+            .map(|h| {
+                let name = names
+                    .iter()
+                    .find(|name| colors.get_color(name) == Some(h.color))
+                    .unwrap();
+                (h.line, h.start, h.end, *name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_words() {
+        let words = words(&lines("echo \"a b\"c \\\nd\ne*"));
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, vec!["echo", "a bc", "d", "e*"]);
+        assert_eq!(
+            words[1].quoted,
+            vec![(0, 5), (0, 6), (0, 7), (0, 8), (0, 9)]
+        );
+        assert_eq!(words[2].chars, vec![(0, 12), (1, 0)]);
+        assert_eq!(words[3].glob_chars, vec![(2, 1)]);
+    }
+
+    #[test]
+    fn test_redirection_len() {
+        assert_eq!(redirection_len(">out"), Some(1));
+        assert_eq!(redirection_len(">>"), Some(2));
+        assert_eq!(redirection_len("2>&1"), Some(4));
+        assert_eq!(redirection_len("<in"), Some(1));
+        assert_eq!(redirection_len("a>b"), None);
+        assert_eq!(redirection_len("12"), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            spans("cd src/ \"x y\""),
+            vec![(0, 0, 2, COMMAND_COLOR), (0, 8, 13, QUOTE_COLOR)]
+        );
+        assert_eq!(
+            spans("no-such-command-rqsh /no/such/path >out"),
+            vec![
+                (0, 0, 20, UNKNOWN_COMMAND_COLOR),
+                (0, 21, 34, MISSING_PATH_COLOR),
+                (0, 35, 36, REDIRECTION_COLOR)
+            ]
+        );
+        assert_eq!(
+            spans("jobs *.toml\nsrc/*.rs x*y*z"),
+            vec![
+                (0, 0, 4, COMMAND_COLOR),
+                (0, 5, 6, GLOB_COLOR),
+                (1, 4, 5, GLOB_COLOR)
+            ]
+        );
+        assert!(spans("").is_empty());
+    }
+}
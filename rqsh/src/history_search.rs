@@ -0,0 +1,132 @@
+//! incremental search of the command history, like `Ctrl-R` in bash
+//!
+//! Commands that contain the pattern come first, then the commands that contain its chars in
+//! order (a fuzzy match). Both are sorted from the newest to the oldest command.
+
+/// a command of the history and the chars of it that match the pattern
+#[derive(Debug, PartialEq)]
+pub struct HistoryMatch {
+    pub command: String,
+    /// char indices into `command`
+    pub positions: Vec<usize>,
+}
+
+pub struct HistorySearch {
+    /// the command line before the search, restored if it is cancelled
+    pub original: Vec<String>,
+    pub matches: Vec<HistoryMatch>,
+    /// the selected match, higher is older
+    pub current: usize,
+}
+
+/// the char indices of the first occurrence of `pattern` in `command`
+fn substring_positions(command: &str, pattern: &str) -> Option<Vec<usize>> {
+    let start = command.find(pattern)?;
+    let start = command[..start].chars().count();
+    Some((start..start + pattern.chars().count()).collect())
+}
+
+/// the char indices of the chars of `pattern` in `command`, in order and ignoring case
+fn fuzzy_positions(command: &str, pattern: &str) -> Option<Vec<usize>> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut positions = vec![];
+    for (i, c) in command.chars().enumerate() {
+        let Some(p) = pattern.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(*p)) {
+            positions.push(i);
+            pattern.next();
+        }
+    }
+    pattern.peek().is_none().then_some(positions)
+}
+
+impl HistorySearch {
+    pub fn new(original: Vec<String>) -> Self {
+        HistorySearch {
+            original,
+            matches: vec![],
+            current: 0,
+        }
+    }
+
+    /// find `pattern` in `commands`, they are the newest first and without duplicates
+    pub fn update(&mut self, commands: &[String], pattern: &str) {
+        let mut matches = vec![];
+        let mut fuzzy_matches = vec![];
+        for command in commands {
+            if let Some(positions) = substring_positions(command, pattern) {
+                matches.push(HistoryMatch {
+                    command: command.clone(),
+                    positions,
+                });
+            } else if let Some(positions) = fuzzy_positions(command, pattern) {
+                fuzzy_matches.push(HistoryMatch {
+                    command: command.clone(),
+                    positions,
+                });
+            }
+        }
+        matches.extend(fuzzy_matches);
+        self.matches = matches;
+        self.current = 0;
+    }
+
+    /// select the next older match, if there is one
+    pub fn next(&mut self) {
+        if self.current + 1 < self.matches.len() {
+            self.current += 1;
+        }
+    }
+
+    pub fn current(&self) -> Option<&HistoryMatch> {
+        self.matches.get(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(substring_positions("grep -r für", "ür"), Some(vec![9, 10]));
+        assert_eq!(substring_positions("ls", "lsd"), None);
+        assert_eq!(fuzzy_positions("git Commit", "gcm"), Some(vec![0, 4, 6]));
+        assert_eq!(fuzzy_positions("git commit", "gmc"), None);
+        assert_eq!(fuzzy_positions("ls", ""), Some(vec![]));
+    }
+
+    #[test]
+    fn test_history_search() {
+        let commands: Vec<String> = ["cargo test", "git commit", "cat todo.md", "cargo build"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut search = HistorySearch::new(vec!["ls".to_string()]);
+        search.update(&commands, "ca");
+        let found: Vec<&str> = search.matches.iter().map(|m| m.command.as_str()).collect();
+        assert_eq!(found, vec!["cargo test", "cat todo.md", "cargo build"]);
+
+        // substring matches before fuzzy matches
+        search.update(&commands, "co");
+        let found: Vec<&str> = search.matches.iter().map(|m| m.command.as_str()).collect();
+        assert_eq!(
+            found,
+            vec!["git commit", "cargo test", "cat todo.md", "cargo build"]
+        );
+        assert_eq!(search.current().unwrap().positions, vec![4, 5]);
+        search.next();
+        assert_eq!(search.current().unwrap().positions, vec![0, 4]);
+        search.next();
+        search.next();
+        search.next();
+        assert_eq!(search.current().unwrap().command, "cargo build");
+
+        search.update(&commands, "xyz");
+        assert_eq!(search.current(), None);
+        search.next();
+        assert_eq!(search.current(), None);
+    }
+}
@@ -0,0 +1,679 @@
+//! named actions bound to keys, with a table per mode
+//!
+//! The keymap file in the config directory has one binding per line, `MODE KEY ACTION`, the same
+//! as the arguments of the `bind` builtin, e.g. `insert Ctrl-C kill-job`. Lines starting with `#`
+//! are comments. Its bindings are added to the defaults below. Keys that are not bound go to the
+//! command line editor.
+//!
+//! The editing commands of the command line, like `kill-word` on `Alt-D`, are actions as well, so
+//! their keys can be changed. What the editor keeps to itself are the vi commands of normal mode,
+//! Backspace, Escape, `Ctrl-_` for undo and `Ctrl-X` as the start of a sequence.
+
+use edit::EditAction;
+use sfml::window::Key;
+
+const DEFAULT_KEYMAP: &str = "\
+global Ctrl-Shift-Up output-prev
+global Ctrl-Shift-Down output-next
+global Ctrl-Shift-O select-output
+global Ctrl-Shift-C copy
+global Ctrl-Shift-V paste
+global Shift-Insert paste
+insert Ctrl-C kill-job
+insert Ctrl-D send-eof
+insert Ctrl-Shift-F search
+insert Ctrl-R history-search
+insert Ctrl-G abort
+insert Escape abort
+insert Ctrl-X,Ctrl-E edit-command
+insert Ctrl-N complete-next
+insert Ctrl-P complete-prev
+insert Tab complete-next
+insert Shift-Tab complete-prev
+insert Enter accept-line
+insert Shift-Enter newline
+insert Up line-up
+insert Down line-down
+insert PageUp page-up
+insert PageDown page-down
+insert Ctrl-A beginning-of-line
+insert Home beginning-of-line
+insert Ctrl-E end-of-line
+insert End end-of-line
+insert Ctrl-B backward-char
+insert Left backward-char
+insert Ctrl-F forward-char
+insert Right forward-char
+insert Alt-B backward-word
+insert Ctrl-Left backward-word
+insert Alt-F forward-word
+insert Ctrl-Right forward-word
+insert Ctrl-H backward-delete-char
+insert Ctrl-K kill-line
+insert Ctrl-U unix-line-discard
+insert Ctrl-W unix-word-rubout
+insert Alt-D kill-word
+insert Alt-Backspace backward-kill-word
+insert Ctrl-Y yank
+insert Alt-Y yank-pop
+insert Ctrl-T transpose-chars
+insert Alt-U upcase-word
+insert Alt-L downcase-word
+insert Alt-C capitalize-word
+insert Ctrl-LBracket vi-movement-mode
+insert Ctrl-J vi-movement-mode
+normal Enter accept-line
+normal Ctrl-D send-eof
+normal Escape clear-search
+normal k history-prev
+normal K history-prev
+normal j history-next
+normal J history-next
+normal / search-regex
+normal n search-next
+normal N search-prev
+normal Ctrl-H backward-delete-char
+normal Ctrl-W unix-word-rubout
+normal Ctrl-U unix-line-discard
+normal Ctrl-K kill-line
+normal Ctrl-A beginning-of-line
+normal Ctrl-B backward-char
+normal Ctrl-E end-of-line
+normal Ctrl-F forward-char
+";
+
+const KEYMAP_FILE: &str = "keymap";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeymapMode {
+    /// checked before the table of the current mode
+    Global,
+    Insert,
+    Normal,
+    /// `v` and `V` in normal mode, there are no default bindings
+    Visual,
+}
+
+const MODES: [(&str, KeymapMode); 4] = [
+    ("global", KeymapMode::Global),
+    ("insert", KeymapMode::Insert),
+    ("normal", KeymapMode::Normal),
+    ("visual", KeymapMode::Visual),
+];
+
+impl KeymapMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        MODES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, mode)| *mode)
+    }
+
+    pub fn name(&self) -> &'static str {
+        MODES.iter().find(|(_, mode)| mode == self).unwrap().0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    KillJob,
+    SendEof,
+    AcceptLine,
+    Newline,
+    CompleteNext,
+    CompletePrev,
+    HistoryPrev,
+    HistoryNext,
+    LineUp,
+    LineDown,
+    PageUp,
+    PageDown,
+    Search,
+    SearchRegex,
+    SearchNext,
+    SearchPrev,
+    ClearSearch,
+    OutputPrev,
+    OutputNext,
+    SelectOutput,
+    Undo,
+    Redo,
+    Copy,
+    Paste,
+    HistorySearch,
+    EditCommand,
+    Abort,
+    /// a command of the command line editor
+    Edit(edit::EditAction),
+}
+
+#[rustfmt::skip]
+const ACTIONS: [(&str, Action, &str); 46] = [
+    ("kill-job", Action::KillJob, "stop the running job"),
+    ("send-eof", Action::SendEof, "end the input of the running job, or exit"),
+    ("accept-line", Action::AcceptLine, "run the command, a new line if a quote is open"),
+    ("newline", Action::Newline, "continue the command on a new line"),
+    ("complete-next", Action::CompleteNext, "select the next completion"),
+    ("complete-prev", Action::CompletePrev, "select the previous completion"),
+    ("history-prev", Action::HistoryPrev, "previous line, or recall an older command"),
+    ("history-next", Action::HistoryNext, "next line, or recall a newer command"),
+    ("line-up", Action::LineUp, "previous line, or move the output cursor up"),
+    ("line-down", Action::LineDown, "next line, or move the output cursor down"),
+    ("page-up", Action::PageUp, "scroll the output a page up"),
+    ("page-down", Action::PageDown, "scroll the output a page down"),
+    ("search", Action::Search, "search the output for text"),
+    ("search-regex", Action::SearchRegex, "search the output for a regular expression"),
+    ("search-next", Action::SearchNext, "jump to the next older match"),
+    ("search-prev", Action::SearchPrev, "jump to the next newer match"),
+    ("clear-search", Action::ClearSearch, "remove the search highlights"),
+    ("output-prev", Action::OutputPrev, "jump to the previous command output"),
+    ("output-next", Action::OutputNext, "jump to the next command output"),
+    ("select-output", Action::SelectOutput, "select and copy the current command output"),
+    ("undo", Action::Undo, "undo the last change of the command line"),
+    ("redo", Action::Redo, "redo the last undone change"),
+    ("copy", Action::Copy, "copy the selected output, or the command line"),
+    ("paste", Action::Paste, "paste into the command line, or to a job using bracketed paste"),
+    ("history-search", Action::HistorySearch, "search older commands as you type, again for older matches"),
+    ("edit-command", Action::EditCommand, "edit the command line in rqed, :w and :q put it back"),
+    ("abort", Action::Abort, "leave the history search with the command line as it was"),
+    ("beginning-of-line", Action::Edit(EditAction::BeginningOfLine), "move to the start of the line"),
+    ("end-of-line", Action::Edit(EditAction::EndOfLine), "move to the end of the line"),
+    ("backward-char", Action::Edit(EditAction::BackwardChar), "move a char left"),
+    ("forward-char", Action::Edit(EditAction::ForwardChar), "move a char right"),
+    ("backward-word", Action::Edit(EditAction::BackwardWord), "move to the start of the word"),
+    ("forward-word", Action::Edit(EditAction::ForwardWord), "move to the end of the word"),
+    ("backward-delete-char", Action::Edit(EditAction::BackwardDeleteChar), "delete the char before the cursor"),
+    ("kill-line", Action::Edit(EditAction::KillLine), "kill to the end of the line"),
+    ("unix-line-discard", Action::Edit(EditAction::UnixLineDiscard), "kill to the start of the line"),
+    ("unix-word-rubout", Action::Edit(EditAction::UnixWordRubout), "kill to the whitespace before the cursor"),
+    ("kill-word", Action::Edit(EditAction::KillWord), "kill to the end of the word"),
+    ("backward-kill-word", Action::Edit(EditAction::BackwardKillWord), "kill to the start of the word"),
+    ("yank", Action::Edit(EditAction::Yank), "insert the last killed text"),
+    ("yank-pop", Action::Edit(EditAction::YankPop), "replace the text just yanked with older killed text"),
+    ("transpose-chars", Action::Edit(EditAction::TransposeChars), "swap the chars before the cursor"),
+    ("upcase-word", Action::Edit(EditAction::UpcaseWord), "uppercase to the end of the word"),
+    ("downcase-word", Action::Edit(EditAction::DowncaseWord), "lowercase to the end of the word"),
+    ("capitalize-word", Action::Edit(EditAction::CapitalizeWord), "capitalize to the end of the word"),
+    ("vi-movement-mode", Action::Edit(EditAction::ViMovementMode), "go to normal mode"),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, action, _)| *action)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter().find(|(_, a, _)| a == self).unwrap().0
+    }
+}
+
+/// keys that can be bound with modifiers, by their sfml name
+const KEYS: [Key; 79] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Escape,
+    Key::Menu,
+    Key::LBracket,
+    Key::RBracket,
+    Key::Semicolon,
+    Key::Comma,
+    Key::Period,
+    Key::Quote,
+    Key::Slash,
+    Key::Backslash,
+    Key::Tilde,
+    Key::Equal,
+    Key::Hyphen,
+    Key::Space,
+    Key::Enter,
+    Key::Backspace,
+    Key::Tab,
+    Key::PageUp,
+    Key::PageDown,
+    Key::End,
+    Key::Home,
+    Key::Insert,
+    Key::Delete,
+    Key::Add,
+    Key::Subtract,
+    Key::Multiply,
+    Key::Divide,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Down,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key {
+        code: Key,
+        control: bool,
+        shift: bool,
+        alt: bool,
+    },
+    /// typed text, e.g. `k` in normal mode, whichever key produces it in the keyboard layout
+    Char(char),
+    /// a key pressed with Ctrl after `Ctrl-X`, written `Ctrl-X,Ctrl-E`
+    CtrlX(Key),
+}
+
+impl Binding {
+    /// `Ctrl-Shift-F`, `Alt-Enter` or `PageUp` for a key, a single char like `k` or `/` for text,
+    /// `Ctrl-X,Ctrl-E` for a `Ctrl-X` sequence
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Some(second) = s.strip_prefix("Ctrl-X,") {
+            return match Self::parse(second)? {
+                Binding::Key {
+                    code,
+                    control: true,
+                    shift: false,
+                    alt: false,
+                } => Some(Binding::CtrlX(code)),
+                _ => None,
+            };
+        }
+        let (mut control, mut shift, mut alt) = (false, false, false);
+        let mut name = s;
+        loop {
+            if let Some(rest) = name.strip_prefix("Ctrl-") {
+                control = true;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("Shift-") {
+                shift = true;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("Alt-") {
+                alt = true;
+                name = rest;
+            } else {
+                break;
+            }
+        }
+        let mut chars = name.chars();
+        if let (Some(c), None, false) = (chars.next(), chars.next(), control || shift || alt) {
+            return Some(Binding::Char(c));
+        }
+        let name = match name.len() {
+            // `Ctrl-1` is `Ctrl-Num1`
+            1 if name.as_bytes()[0].is_ascii_digit() => format!("Num{}", name),
+            _ => name.to_string(),
+        };
+        KEYS.iter()
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+            .map(|&code| Binding::Key {
+                code,
+                control,
+                shift,
+                alt,
+            })
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Binding::Key {
+                code,
+                control,
+                shift,
+                alt,
+            } => {
+                let mut name = String::new();
+                for (on, prefix) in [(control, "Ctrl-"), (shift, "Shift-"), (alt, "Alt-")] {
+                    if *on {
+                        name.push_str(prefix);
+                    }
+                }
+                write!(f, "{}{:?}", name, code)
+            }
+            Binding::Char(c) => write!(f, "{}", c),
+            Binding::CtrlX(code) => write!(f, "Ctrl-X,Ctrl-{:?}", code),
+        }
+    }
+}
+
+pub struct Keymap {
+    global: Vec<(Binding, Action)>,
+    insert: Vec<(Binding, Action)>,
+    normal: Vec<(Binding, Action)>,
+    visual: Vec<(Binding, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self::empty();
+        for (i, line) in DEFAULT_KEYMAP.lines().enumerate() {
+            if let Err(e) = keymap.parse_line(line) {
+                panic!("default keymap, line {}: {}", i + 1, e);
+            }
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    fn empty() -> Self {
+        Self {
+            global: vec![],
+            insert: vec![],
+            normal: vec![],
+            visual: vec![],
+        }
+    }
+
+    /// the bindings of the keymap file on top of the default bindings
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        config::Config::new().parse_file(KEYMAP_FILE, |line| keymap.parse_line(line));
+        keymap
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [mode, key, action] => {
+                let (mode, binding) = Self::parse_mode_key(mode, key)?;
+                let action =
+                    Action::from_name(action).ok_or(format!("unknown action: {}", action))?;
+                self.bind(mode, binding, action);
+                Ok(())
+            }
+            _ => Err(format!("expected MODE KEY ACTION: {}", line)),
+        }
+    }
+
+    fn parse_mode_key(mode: &str, key: &str) -> Result<(KeymapMode, Binding), String> {
+        let mode = KeymapMode::from_name(mode).ok_or(format!("unknown mode: {}", mode))?;
+        let binding = Binding::parse(key).ok_or(format!("unknown key: {}", key))?;
+        Ok((mode, binding))
+    }
+
+    fn table(&self, mode: KeymapMode) -> &Vec<(Binding, Action)> {
+        match mode {
+            KeymapMode::Global => &self.global,
+            KeymapMode::Insert => &self.insert,
+            KeymapMode::Normal => &self.normal,
+            KeymapMode::Visual => &self.visual,
+        }
+    }
+
+    fn table_mut(&mut self, mode: KeymapMode) -> &mut Vec<(Binding, Action)> {
+        match mode {
+            KeymapMode::Global => &mut self.global,
+            KeymapMode::Insert => &mut self.insert,
+            KeymapMode::Normal => &mut self.normal,
+            KeymapMode::Visual => &mut self.visual,
+        }
+    }
+
+    /// bind `binding` in `mode`, replacing its old action
+    pub fn bind(&mut self, mode: KeymapMode, binding: Binding, action: Action) {
+        let table = self.table_mut(mode);
+        match table.iter_mut().find(|(b, _)| *b == binding) {
+            Some(entry) => entry.1 = action,
+            None => table.push((binding, action)),
+        }
+    }
+
+    pub fn unbind(&mut self, mode: KeymapMode, binding: Binding) -> Option<Action> {
+        let table = self.table_mut(mode);
+        let idx = table.iter().position(|(b, _)| *b == binding)?;
+        Some(table.remove(idx).1)
+    }
+
+    /// the action of `binding` in `mode`, global bindings come first
+    pub fn lookup(&self, mode: KeymapMode, binding: Binding) -> Option<Action> {
+        [KeymapMode::Global, mode]
+            .iter()
+            .flat_map(|mode| self.table(*mode))
+            .find(|(b, _)| *b == binding)
+            .map(|(_, action)| *action)
+    }
+
+    fn list(&self, mode: KeymapMode) -> Vec<String> {
+        self.table(mode)
+            .iter()
+            .map(|(binding, action)| format!("{} {} {}", mode.name(), binding, action.name()))
+            .collect()
+    }
+
+    /// run the `bind` builtin, returns its output
+    ///
+    /// `bind [MODE]` lists the bindings, `bind MODE KEY` shows one, `bind MODE KEY ACTION`
+    /// changes one, `bind -r MODE KEY` removes one and `bind -l` lists the actions.
+    pub fn bind_command(&mut self, args: &[String]) -> Result<String, String> {
+        const USAGE: &str = "usage: bind [-l] [-r] [MODE [KEY [ACTION]]]";
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        let output = match args[..] {
+            [] => MODES
+                .iter()
+                .flat_map(|(_, mode)| self.list(*mode))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ["-l"] => ACTIONS
+                .iter()
+                .map(|(name, _, help)| format!("{:<14} {}", name, help))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ["-r", mode, key] => {
+                let (mode, binding) = Self::parse_mode_key(mode, key)?;
+                self.unbind(mode, binding).ok_or(format!(
+                    "{} is not bound in {} mode",
+                    key,
+                    mode.name()
+                ))?;
+                String::new()
+            }
+            [mode] => {
+                let mode = KeymapMode::from_name(mode).ok_or(format!("unknown mode: {}", mode))?;
+                self.list(mode).join("\n")
+            }
+            [mode, key] => {
+                let (mode, binding) = Self::parse_mode_key(mode, key)?;
+                match self.table(mode).iter().find(|(b, _)| *b == binding) {
+                    Some((binding, action)) => {
+                        format!("{} {} {}", mode.name(), binding, action.name())
+                    }
+                    None => format!("{} {} is not bound", mode.name(), binding),
+                }
+            }
+            [mode, key, action] => {
+                self.parse_line(&format!("{} {} {}", mode, key, action))?;
+                String::new()
+            }
+            _ => return Err(USAGE.to_string()),
+        };
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_binding_parse() {
+        let ctrl_shift_f = Binding::Key {
+            code: Key::F,
+            control: true,
+            shift: true,
+            alt: false,
+        };
+        assert_eq!(Binding::parse("Ctrl-Shift-F"), Some(ctrl_shift_f));
+        assert_eq!(Binding::parse("Ctrl-Shift-f"), Some(ctrl_shift_f));
+        assert_eq!(Binding::parse("k"), Some(Binding::Char('k')));
+        assert_eq!(Binding::parse("/"), Some(Binding::Char('/')));
+        assert_eq!(Binding::parse("ä"), Some(Binding::Char('ä')));
+        assert_eq!(
+            Binding::parse("Alt-1"),
+            Some(Binding::Key {
+                code: Key::Num1,
+                control: false,
+                shift: false,
+                alt: true,
+            })
+        );
+        assert_eq!(Binding::parse("Ctrl-Foo"), None);
+        assert_eq!(
+            Binding::parse("Ctrl-X,Ctrl-E"),
+            Some(Binding::CtrlX(Key::E))
+        );
+        assert_eq!(Binding::parse("Ctrl-X,E"), None);
+        assert_eq!(Binding::parse("Ctrl-X,Ctrl-X,Ctrl-E"), None);
+        for s in [
+            "Ctrl-Shift-F",
+            "Alt-Enter",
+            "PageUp",
+            "Shift-Tab",
+            "N",
+            "Ctrl-X,Ctrl-E",
+        ] {
+            assert_eq!(Binding::parse(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_keymap_lookup() {
+        let keymap = Keymap::default();
+        let key = |code, control, shift| Binding::Key {
+            code,
+            control,
+            shift,
+            alt: false,
+        };
+        assert_eq!(
+            keymap.lookup(KeymapMode::Insert, key(Key::C, true, false)),
+            Some(Action::KillJob)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapMode::Normal, key(Key::C, true, false)),
+            None
+        );
+        // global bindings are in every mode
+        assert_eq!(
+            keymap.lookup(KeymapMode::Normal, key(Key::O, true, true)),
+            Some(Action::SelectOutput)
+        );
+        assert_eq!(
+            keymap.lookup(KeymapMode::Normal, Binding::Char('k')),
+            Some(Action::HistoryPrev)
+        );
+        // the keys of the command line editor
+        assert_eq!(
+            keymap.lookup(KeymapMode::Insert, Binding::parse("Alt-D").unwrap()),
+            Some(Action::Edit(EditAction::KillWord))
+        );
+        assert_eq!(
+            keymap.lookup(KeymapMode::Normal, key(Key::W, true, false)),
+            Some(Action::Edit(EditAction::UnixWordRubout))
+        );
+        assert_eq!(
+            Action::from_name("kill-word"),
+            Some(Action::Edit(EditAction::KillWord))
+        );
+        assert_eq!(Action::Edit(EditAction::Yank).name(), "yank");
+        assert_eq!(
+            keymap.lookup(KeymapMode::Insert, key(Key::G, true, false)),
+            Some(Action::Abort)
+        );
+    }
+
+    #[test]
+    fn test_bind_command() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.bind_command(&args("insert Ctrl-C")),
+            Ok("insert Ctrl-C kill-job".to_string())
+        );
+        assert_eq!(
+            keymap.bind_command(&args("insert Ctrl-C undo")),
+            Ok(String::new())
+        );
+        assert_eq!(
+            keymap.bind_command(&args("insert Ctrl-c")),
+            Ok("insert Ctrl-C undo".to_string())
+        );
+        assert_eq!(
+            keymap.bind_command(&args("-r insert Ctrl-C")),
+            Ok(String::new())
+        );
+        assert_eq!(
+            keymap.bind_command(&args("insert Ctrl-C")),
+            Ok("insert Ctrl-C is not bound".to_string())
+        );
+        assert!(keymap.bind_command(&args("-r insert Ctrl-C")).is_err());
+        assert!(keymap
+            .bind_command(&args("replace k history-prev"))
+            .is_err());
+        assert!(keymap.bind_command(&args("normal k fly")).is_err());
+        assert!(keymap
+            .bind_command(&args("normal k history-prev x"))
+            .is_err());
+
+        let all = keymap.bind_command(&[]).unwrap();
+        assert!(all.starts_with("global Ctrl-Shift-Up output-prev\n"));
+        assert!(all.contains("\nnormal / search-regex\n"));
+        let normal = keymap.bind_command(&args("normal")).unwrap();
+        assert!(normal.lines().all(|line| line.starts_with("normal ")));
+        assert_eq!(
+            keymap.bind_command(&args("-l")).unwrap().lines().count(),
+            ACTIONS.len()
+        );
+    }
+}
@@ -3,7 +3,12 @@ mod app;
 mod args;
 mod builtin;
 mod execute;
+mod font_config;
 mod glob;
+mod highlight;
+mod history_search;
+mod keymap;
+mod settings;
 mod util;
 
 use app::App;
@@ -0,0 +1,103 @@
+//! settings of rqsh, in the settings file in the config directory
+//!
+//! `command-lines N` is how many lines the command line grows to before it scrolls.
+//! `cursor-insert SHAPE [blink]` and `cursor-normal SHAPE [blink]` are the cursors of the
+//! command line in insert and normal mode, SHAPE is `block`, `underline` or `bar`.
+
+const SETTINGS_FILE: &str = "settings";
+const DEFAULT_MAX_COMMAND_LINES: i32 = 10;
+
+#[derive(Debug, PartialEq)]
+pub struct Settings {
+    /// command_win grows with the lines of the command up to this height, main_win shrinks to
+    /// match
+    pub max_command_lines: i32,
+    pub insert_cursor_style: text::CursorStyle,
+    pub normal_cursor_style: text::CursorStyle,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_command_lines: DEFAULT_MAX_COMMAND_LINES,
+            insert_cursor_style: edit::INSERT_CURSOR_STYLE,
+            normal_cursor_style: edit::NORMAL_CURSOR_STYLE,
+        }
+    }
+}
+
+/// `SHAPE [blink]`
+fn parse_cursor_style(value: &str) -> Result<text::CursorStyle, String> {
+    let (shape, blink) = match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [shape] => (shape, false),
+        [shape, "blink"] => (shape, true),
+        _ => return Err(format!("expected SHAPE [blink]: {}", value)),
+    };
+    let shape = match shape {
+        "block" => text::CursorShape::Block,
+        "underline" => text::CursorShape::Underline,
+        "bar" => text::CursorShape::Bar,
+        _ => return Err(format!("expected block, underline or bar: {}", shape)),
+    };
+    Ok(text::CursorStyle::new(shape, blink))
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        config::Config::new().parse_file(SETTINGS_FILE, |line| settings.parse_line(line));
+        settings
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let Some((key, value)) = config::split_key_value(line)? else {
+            return Ok(());
+        };
+        match key {
+            "command-lines" => {
+                self.max_command_lines = value
+                    .parse()
+                    .ok()
+                    .filter(|&lines: &i32| lines > 0)
+                    .ok_or(format!("expected a number above 0: {}", value))?
+            }
+            "cursor-insert" => self.insert_cursor_style = parse_cursor_style(value)?,
+            "cursor-normal" => self.normal_cursor_style = parse_cursor_style(value)?,
+            _ => return Err(format!("unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let mut settings = Settings::default();
+        for line in [
+            "# taller command line",
+            "",
+            "command-lines 20",
+            "cursor-insert underline blink",
+            "cursor-normal bar",
+        ] {
+            assert_eq!(settings.parse_line(line), Ok(()));
+        }
+        assert_eq!(
+            settings,
+            Settings {
+                max_command_lines: 20,
+                insert_cursor_style: text::CursorStyle::new(text::CursorShape::Underline, true),
+                normal_cursor_style: text::CursorStyle::new(text::CursorShape::Bar, false),
+            }
+        );
+        assert!(settings.parse_line("command-lines 0").is_err());
+        assert!(settings.parse_line("command-lines ten").is_err());
+        assert!(settings.parse_line("command-lines").is_err());
+        assert!(settings.parse_line("cursor-insert beam").is_err());
+        assert!(settings.parse_line("cursor-normal block fast").is_err());
+        assert!(settings.parse_line("color red").is_err());
+    }
+}
@@ -8,6 +8,32 @@ pub fn dedup_preserve_order(job_names: Vec<String>) -> Vec<String> {
         .collect()
 }
 
+/// a line a program prints when it reads a password, e.g. `Password: ` or
+/// `[sudo] password for user: `
+pub fn is_password_prompt(line: &str) -> bool {
+    let line = line.trim_end().to_lowercase();
+    line.ends_with(':')
+        && ["password", "passphrase", "passwort", "pin"]
+            .iter()
+            .any(|word| {
+                line.split(|c: char| !c.is_alphanumeric())
+                    .any(|w| w == *word)
+            })
+}
+
+#[test]
+fn test_is_password_prompt() {
+    assert!(is_password_prompt("Password: "));
+    assert!(is_password_prompt("[sudo] password for user:"));
+    assert!(is_password_prompt(
+        "Enter passphrase for key '/home/user/.ssh/id_ed25519': "
+    ));
+    assert!(is_password_prompt("Enter PIN:"));
+    assert!(!is_password_prompt("Password changed."));
+    assert!(!is_password_prompt("passwords:"));
+    assert!(!is_password_prompt("Username: "));
+}
+
 #[test]
 fn test_dedup_preserve_order() {
     let jobs = vec![
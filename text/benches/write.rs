@@ -0,0 +1,79 @@
+//! throughput of `Text::write`, run with `cargo bench -p text`
+//!
+//! The output of a job reaches `Text::write` in chunks of up to 1 KiB (see `Execute::run`),
+//! which rqsh collects into larger chunks per frame.
+
+use std::time::{Duration, Instant};
+
+use text::Text;
+
+const SIZE: usize = 64 * 1024 * 1024;
+
+fn log_output() -> String {
+    let mut output = String::with_capacity(SIZE + 256);
+    let mut i = 0;
+    while output.len() < SIZE {
+        if i % 10 == 0 {
+            output.push_str(&format!(
+                "\x1b[31m2024-01-01 12:00:{:02} ERROR\x1b[0m request {} failed: connection reset\n",
+                i % 60,
+                i
+            ));
+        } else {
+            output.push_str(&format!(
+                "2024-01-01 12:00:{:02} INFO request {} handled in {} ms\n",
+                i % 60,
+                i,
+                i % 1000
+            ));
+        }
+        i += 1;
+    }
+    output
+}
+
+fn chunks(s: &str, size: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < s.len() {
+        let mut end = (start + size).min(s.len());
+        while !s.is_char_boundary(end) {
+            end += 1;
+        }
+        chunks.push(&s[start..end]);
+        start = end;
+    }
+    chunks
+}
+
+fn bench(name: &str, input: &str, chunk_size: usize) {
+    let chunks = chunks(input, chunk_size);
+    let mut text = Text::new();
+    let t = Instant::now();
+    for chunk in chunks {
+        text.write(chunk);
+    }
+    let elapsed = t.elapsed().max(Duration::from_micros(1));
+    println!(
+        "{:<40} {:>8.1} MiB in {:>8.3}s = {:>8.1} MiB/s ({} lines)",
+        name,
+        input.len() as f64 / (1024.0 * 1024.0),
+        elapsed.as_secs_f64(),
+        input.len() as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64(),
+        text.lines().len()
+    );
+}
+
+fn main() {
+    let log = log_output();
+    bench("log, 1 KiB chunks", &log, 1024);
+    bench("log, 64 KiB chunks", &log, 64 * 1024);
+
+    let long_line = "x".repeat(SIZE / 4);
+    bench("single line, 1 KiB chunks", &long_line, 1024);
+
+    let progress: String = (0..SIZE / 64)
+        .map(|i| format!("\rprogress {:>10} of {:>10}", i, SIZE / 64))
+        .collect();
+    bench("progress bar with \\r, 1 KiB chunks", &progress, 1024);
+}
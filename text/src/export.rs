@@ -0,0 +1,265 @@
+//! export the text buffer, e.g. to attach a terminal session to a bug report
+
+use std::ops::Range;
+
+use sfml::graphics::Color;
+
+use crate::{strip_ansi, CellStyle, Text};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ExportFormat {
+    /// text without any escape codes
+    Plain,
+    /// text with the ansi color codes, as in a `.ans` file
+    Ansi,
+    /// a self-contained html page with inline styles
+    Html,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plain" | "txt" | "text" => Some(Self::Plain),
+            "ansi" | "ans" => Some(Self::Ansi),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// guess the format from the extension of `filename`, plain text if unknown
+    pub fn from_filename(filename: &str) -> Self {
+        std::path::Path::new(filename)
+            .extension()
+            .and_then(|ext| Self::from_name(&ext.to_string_lossy().to_lowercase()))
+            .unwrap_or(Self::Plain)
+    }
+}
+
+fn html_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn html_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Text<'_> {
+    /// export the whole text buffer
+    pub fn export(&self, format: ExportFormat) -> String {
+        self.export_lines(0..self.text.len(), format)
+    }
+
+    /// export the given lines of the text buffer, the range is clamped to the buffer
+    /// the colors set in the lines before the range carry over to it
+    pub fn export_lines(&self, lines: Range<usize>, format: ExportFormat) -> String {
+        let end = lines.end.min(self.text.len());
+        let start = lines.start.min(end);
+        let lines = &self.text[start..end];
+        match format {
+            ExportFormat::Plain => lines
+                .iter()
+                .map(|line| format!("{}\n", strip_ansi(line).trim_end()))
+                .collect(),
+            ExportFormat::Ansi => {
+                let mut ansi = self.ansi_style(&self.style_at(start));
+                ansi.extend(lines.iter().map(|line| format!("{}\n", line)));
+                ansi.push_str("\x1b[0m");
+                ansi
+            }
+            ExportFormat::Html => self.export_html(lines, self.style_at(start)),
+        }
+    }
+
+    /// the style at the start of line `y`, after the color codes of the lines before it
+    fn style_at(&self, y: usize) -> CellStyle {
+        // the codes before a reset don't matter, so the lines before the last reset are skipped
+        let from = self.text[..y]
+            .iter()
+            .rposition(|line| line.contains("\x1b[0m") || line.contains("\x1b[m"))
+            .unwrap_or(0);
+        let mut style = self.default_style();
+        for line in &self.text[from..y] {
+            self.line_cells(line, &mut style);
+        }
+        style
+    }
+
+    /// the escape code that sets `style`, empty for the default style
+    fn ansi_style(&self, style: &CellStyle) -> String {
+        let mut codes = vec![];
+        if style.bold != self.bold {
+            codes.push(if style.bold { "1" } else { "22" }.to_string());
+        }
+        if style.fg != self.fg_color {
+            let Color { r, g, b, .. } = style.fg;
+            codes.push(format!("38;2;{};{};{}", r, g, b));
+        }
+        if style.bg != self.bg_color {
+            let Color { r, g, b, .. } = style.bg;
+            codes.push(format!("48;2;{};{};{}", r, g, b));
+        }
+        match codes.is_empty() {
+            true => String::new(),
+            false => format!("\x1b[{}m", codes.join(";")),
+        }
+    }
+
+    fn html_style(&self, style: &CellStyle) -> String {
+        let mut css = vec![];
+        if style.fg != self.fg_color {
+            css.push(format!("color:{}", html_color(style.fg)));
+        }
+        if style.bg != self.bg_color {
+            css.push(format!("background-color:{}", html_color(style.bg)));
+        }
+        if style.bold != self.bold {
+            css.push(format!(
+                "font-weight:{}",
+                if style.bold { "bold" } else { "normal" }
+            ));
+        }
+        css.join(";")
+    }
+
+    /// `style` is the style at the first line
+    fn export_html(&self, lines: &[String], mut style: CellStyle) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>rqsh output</title>\n</head>\n<body style=\"margin:0;background-color:{}\">\n<pre style=\"margin:0;padding:4px;font-family:monospace;color:{};font-weight:{}\">",
+            html_color(self.bg_color),
+            html_color(self.fg_color),
+            if self.bold { "bold" } else { "normal" },
+        );
+
+        for line in lines {
+            let cells = self.line_cells(line, &mut style);
+            // group characters with the same style into one span
+            let mut start = 0;
+            while start < cells.len() {
+                let run_style = cells[start].style;
+                let end = cells[start..]
+                    .iter()
+                    .position(|cell| cell.style != run_style)
+                    .map_or(cells.len(), |len| start + len);
+                let run: String = cells[start..end].iter().map(|cell| cell.ch).collect();
+                let css = self.html_style(&run_style);
+                if css.is_empty() {
+                    html.push_str(&html_escape(&run));
+                } else {
+                    html.push_str(&format!(
+                        "<span style=\"{}\">{}</span>",
+                        css,
+                        html_escape(&run)
+                    ));
+                }
+                start = end;
+            }
+            html.push('\n');
+        }
+
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text() -> Text<'static> {
+        let mut text = Text::new();
+        text.write("plain  \n\x1b[31mred\x1b[0m <b>\n\x1b[1;42mbold\x1b[0m");
+        text
+    }
+
+    #[test]
+    fn test_export_format() {
+        assert_eq!(ExportFormat::from_filename("out.ans"), ExportFormat::Ansi);
+        assert_eq!(ExportFormat::from_filename("out.HTML"), ExportFormat::Html);
+        assert_eq!(ExportFormat::from_filename("out.log"), ExportFormat::Plain);
+        assert_eq!(ExportFormat::from_filename("out"), ExportFormat::Plain);
+        assert_eq!(ExportFormat::from_name("htm"), Some(ExportFormat::Html));
+        assert_eq!(ExportFormat::from_name("pdf"), None);
+    }
+
+    #[test]
+    fn test_export_plain() {
+        let text = text();
+        assert_eq!(text.export(ExportFormat::Plain), "plain\nred <b>\nbold\n");
+        assert_eq!(
+            text.export_lines(1..2, ExportFormat::Plain),
+            "red <b>\n".to_string()
+        );
+        assert_eq!(text.export_lines(2..10, ExportFormat::Plain), "bold\n");
+        assert_eq!(text.export_lines(5..10, ExportFormat::Plain), "");
+    }
+
+    #[test]
+    fn test_export_ansi() {
+        let text = text();
+        // write() stores the color codes as rgb, those are exported unchanged
+        assert_eq!(
+            text.export_lines(1..3, ExportFormat::Ansi),
+            format!("{}\n{}\n\x1b[0m", text.text[1], text.text[2])
+        );
+        assert!(text.text[1].starts_with("\x1b[38;2;178;34;34mred"));
+    }
+
+    #[test]
+    fn test_export_html() {
+        let text = text();
+        let red = html_color(color::AnsiColor::new().get_color_from_ansi(31).unwrap());
+        let green = html_color(color::AnsiColor::new().get_color_from_ansi(42).unwrap());
+        let html = text.export(ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("plain  \n"));
+        assert!(html.contains(&format!(
+            "<span style=\"color:{}\">red</span> &lt;b&gt;\n",
+            red
+        )));
+        assert!(html.contains(&format!(
+            "<span style=\"background-color:{};font-weight:bold\">bold</span>\n",
+            green
+        )));
+        assert!(html.ends_with("</pre>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_export_carries_style() {
+        let mut text = Text::new();
+        text.write("\x1b[1;31mred\nstill red\x1b[0m\nplain\n\x1b[42mgreen\nstill green");
+        let red = color::AnsiColor::new().get_color_from_ansi(31).unwrap();
+        let green = color::AnsiColor::new().get_color_from_ansi(42).unwrap();
+        let html = text.export_lines(1..2, ExportFormat::Html);
+        assert!(html.contains(&format!(
+            "<span style=\"color:{};font-weight:bold\">still red</span>\n",
+            html_color(red)
+        )));
+        let html = text.export_lines(4..5, ExportFormat::Html);
+        assert!(html.contains(&format!(
+            "<span style=\"background-color:{}\">still green</span>\n",
+            html_color(green)
+        )));
+        assert_eq!(
+            text.export_lines(1..2, ExportFormat::Ansi),
+            format!(
+                "\x1b[1;38;2;{};{};{}m{}\n\x1b[0m",
+                red.r, red.g, red.b, text.text[1]
+            )
+        );
+        // the reset at the end of line 1 ends the red
+        assert_eq!(
+            text.export_lines(2..3, ExportFormat::Ansi),
+            "plain\n\x1b[0m"
+        );
+    }
+}
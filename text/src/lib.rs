@@ -1,13 +1,18 @@
 use glsl::{Vec2, Vec4};
 use sfml::graphics::{
-    glsl, Color, RenderStates, RenderTarget, RenderTexture, RenderWindow, Shader, ShaderType,
-    Sprite, Transformable,
+    glsl, Color, IntRect, RectangleShape, RenderStates, RenderTarget, RenderTexture, RenderWindow,
+    Shader, ShaderType, Shape, Sprite, Transformable,
 };
 use sfml::system::{Vector2f, Vector2i};
 
 use ansi::{Ansi, AnsiCode};
 use color::ColorType;
 
+mod export;
+mod raster;
+pub use export::ExportFormat;
+pub use raster::{Atlas, RgbaImage};
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum CursorState {
     Hidden,
@@ -17,6 +22,58 @@ pub enum CursorState {
     InsertInactive,
 }
 
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blink: bool,
+}
+
+impl CursorStyle {
+    pub const fn new(shape: CursorShape, blink: bool) -> Self {
+        Self { shape, blink }
+    }
+
+    /// the style set by `CSI Ps SP q` (DECSCUSR)
+    pub fn from_decscusr(ps: i32) -> Option<Self> {
+        let shape = match ps {
+            0..=2 => CursorShape::Block,
+            3 | 4 => CursorShape::Underline,
+            5 | 6 => CursorShape::Bar,
+            _ => return None,
+        };
+        // odd numbers blink, 0 is the default which is a blinking block
+        Some(Self::new(shape, ps % 2 == 1 || ps == 0))
+    }
+}
+
+impl Default for CursorStyle {
+    fn default() -> Self {
+        Self::new(CursorShape::Block, false)
+    }
+}
+
+/// semantic prompt marks, as set by rqsh or with OSC 133 by a nested shell
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MarkKind {
+    Prompt,
+    Command,
+    OutputStart,
+    OutputEnd(Option<i32>),
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Mark {
+    pub line: usize,
+    pub kind: MarkKind,
+}
+
 pub enum VerticalAlignment {
     AlwaysTop,
     AlwaysBottom,
@@ -29,6 +86,97 @@ pub enum InsertMode {
     Insert,
 }
 
+/// A search match in the text buffer.
+/// `start` and `end` are columns on screen, i.e. char indices with ansi escape codes removed.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A foreground color for the columns `start..end` of a line, e.g. for syntax highlighting.
+/// The columns are counted like in `SearchMatch`, the text itself is not changed.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Highlight {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub color: Color,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SelectionMode {
+    Char,
+    Word,
+    Line,
+    Block,
+}
+
+/// A mouse selection. `anchor` is where the selection was started, `head` where it currently
+/// ends. Both are cells on screen: x is the column without ansi escape codes, y is the line.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Selection {
+    pub anchor: Vector2i,
+    pub head: Vector2i,
+    pub mode: SelectionMode,
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"\"'`()[]{}<>,;|".contains(c)
+}
+
+/// start and end (exclusive) of the word around `col`
+fn word_bounds(line: &[char], col: usize) -> (usize, usize) {
+    if col >= line.len() {
+        return (col, col + 1);
+    }
+    let word = is_word_char(line[col]);
+    let same_kind = |c: &char| is_word_char(*c) == word && (word || c.is_whitespace());
+    let start = line[..col]
+        .iter()
+        .rposition(|c| !same_kind(c))
+        .map_or(0, |i| i + 1);
+    let end = line[col..]
+        .iter()
+        .position(|c| !same_kind(c))
+        .map_or(line.len(), |i| col + i);
+    (start, end.max(col + 1))
+}
+
+/// foreground, background and bold state of a character, set by ansi color codes
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct CellStyle {
+    fg: Color,
+    bg: Color,
+    bold: bool,
+}
+
+/// a character as it is drawn
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Cell {
+    ch: char,
+    style: CellStyle,
+}
+
+/// remove ansi escape codes from a line, leaving only the characters that are drawn
+pub fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut in_escape = false;
+    for c in line.chars() {
+        if in_escape {
+            if c.is_ascii_alphabetic() {
+                in_escape = false;
+            }
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
 pub struct TextBuilder {
     position: Option<Vector2i>,
     size: Option<Vector2i>,
@@ -40,7 +188,11 @@ pub struct TextBuilder {
     cursor_normal_color: Option<Color>,
     bold: Option<bool>,
     cursor_state: Option<CursorState>,
+    cursor_style: Option<CursorStyle>,
     insert_mode: Option<InsertMode>,
+    padding: Option<i32>,
+    border: Option<(i32, Color)>,
+    title: Option<String>,
 }
 
 impl TextBuilder {
@@ -56,7 +208,11 @@ impl TextBuilder {
             cursor_normal_color: None,
             bold: None,
             cursor_state: None,
+            cursor_style: None,
             insert_mode: None,
+            padding: None,
+            border: None,
+            title: None,
         }
     }
 
@@ -115,6 +271,28 @@ impl TextBuilder {
         self
     }
 
+    pub fn cursor_style(mut self, cursor_style: CursorStyle) -> Self {
+        self.cursor_style = Some(cursor_style);
+        self
+    }
+
+    /// space in pixels between the border and the text
+    pub fn padding(mut self, padding: i32) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    pub fn border(mut self, thickness: i32, color: Color) -> Self {
+        self.border = Some((thickness, color));
+        self
+    }
+
+    /// a line at the top, drawn in the border color
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
     pub fn build(self) -> Text<'static> {
         let position = self.position.unwrap_or(Vector2i::new(0, 0));
         let size = self.size.unwrap_or(Vector2i::new(1, 1));
@@ -129,20 +307,16 @@ impl TextBuilder {
         let bold = self.bold.unwrap_or(false);
         let cursor_state = self.cursor_state.unwrap_or(CursorState::Hidden);
 
-        let shader = Shader::from_file(
-            &config::Config::get_resource_path("color_bold.frag"),
-            ShaderType::Fragment,
-        )
-        .unwrap();
-
         let ansi_colors = color::AnsiColor::new();
         let insert_mode = self.insert_mode.unwrap_or(InsertMode::Overwrite);
+        let (border, border_color) = self.border.unwrap_or((0, fg_color));
 
         Text {
             text: vec![String::new()],
             position,
             vertical_alignment,
-            texture: RenderTexture::new(size.x as u32, size.y as u32).unwrap(),
+            size: Vector2i::new(size.x.max(1), size.y.max(1)),
+            texture: None,
             font_scale,
             fg_color,
             bg_color,
@@ -151,11 +325,29 @@ impl TextBuilder {
             ansi_colors,
             bold,
             redraw: true,
-            shader,
+            shader: None,
             cursor_state,
+            cursor_style: self.cursor_style.unwrap_or_default(),
+            cursor_blink_on: true,
             cursor_position: Vector2i::new(0, 0),
             scroll_pos_y: 0,
             insert_mode,
+            bracketed_paste: false,
+            search_matches: vec![],
+            search_current: None,
+            search_color: Color::rgb(0xFF, 0xFF, 0x00),
+            highlights: vec![],
+            search_current_color: Color::rgb(0xFF, 0x8C, 0x00),
+            selection: None,
+            selection_color: Color::rgb(0x87, 0xCE, 0xFA),
+            marks: vec![],
+            line_len_cache: None,
+            padding: self.padding.unwrap_or(0).max(0),
+            border: border.max(0),
+            border_color,
+            title: self.title,
+            char_size: Vector2i::new(0, 0),
+            grid_size: Vector2i::new(0, 0),
         }
     }
 }
@@ -167,10 +359,13 @@ impl Default for TextBuilder {
 }
 
 pub struct Text<'a> {
-    pub text: Vec<String>,
+    text: Vec<String>,
     position: Vector2i,
     pub vertical_alignment: VerticalAlignment,
-    texture: RenderTexture,
+    size: Vector2i,
+    // the render texture and the shader are created on the first draw, so that a Text can be
+    // used without a GPU context
+    texture: Option<RenderTexture>,
     font_scale: i32,
     ansi_colors: color::AnsiColor,
     fg_color: Color,
@@ -179,11 +374,35 @@ pub struct Text<'a> {
     cursor_normal_color: Color,
     bold: bool,
     pub redraw: bool,
-    shader: Shader<'a>,
+    shader: Option<Shader<'a>>,
     pub cursor_state: CursorState,
+    cursor_style: CursorStyle,
+    // false while a blinking cursor is in its invisible phase
+    cursor_blink_on: bool,
     pub cursor_position: Vector2i,
     pub scroll_pos_y: i32,
     pub insert_mode: InsertMode,
+    /// set with `CSI ? 2004 h` by a program that wants pasted text marked
+    pub bracketed_paste: bool,
+    search_matches: Vec<SearchMatch>,
+    search_current: Option<usize>,
+    search_color: Color,
+    highlights: Vec<Highlight>,
+    search_current_color: Color,
+    selection: Option<Selection>,
+    selection_color: Color,
+    marks: Vec<Mark>,
+    // line, length in bytes and length in chars of the line written to last, cleared by every
+    // change that doesn't go through `write`
+    line_len_cache: Option<(usize, usize, usize)>,
+    padding: i32,
+    border: i32,
+    border_color: Color,
+    title: Option<String>,
+    /// char size of the font the size was last set or drawn with
+    char_size: Vector2i,
+    /// columns and rows inside the border, title and padding
+    grid_size: Vector2i,
 }
 
 impl Default for Text<'_> {
@@ -198,16 +417,26 @@ impl<'a> Text<'a> {
         Self::default()
     }
 
-    pub fn position_size(&mut self, position: Vector2i, size: Vector2i) -> &mut Self {
+    /// also computes the grid inside the border, title and padding in chars of `char_size`, the
+    /// char size of the font
+    pub fn position_size(
+        &mut self,
+        position: Vector2i,
+        size: Vector2i,
+        char_size: Vector2i,
+    ) -> &mut Self {
         let size_x = if size.x < 1 { 1 } else { size.x };
         let size_y = if size.y < 1 { 1 } else { size.y };
         self.position = position;
-        self.texture = RenderTexture::new(size_x as u32, size_y as u32).unwrap();
+        self.size = Vector2i::new(size_x, size_y);
+        self.texture = None;
+        self.char_size = char_size;
+        self.update_grid_size();
         self
     }
 
-    pub fn set_position_size(&mut self, position: Vector2i, size: Vector2i) {
-        self.position_size(position, size);
+    pub fn set_position_size(&mut self, position: Vector2i, size: Vector2i, char_size: Vector2i) {
+        self.position_size(position, size, char_size);
         self.redraw = true;
     }
 
@@ -221,6 +450,30 @@ impl<'a> Text<'a> {
         self.redraw = true;
     }
 
+    pub fn cursor_style(&mut self, cursor_style: CursorStyle) -> &mut Self {
+        self.cursor_style = cursor_style;
+        self
+    }
+
+    pub fn set_cursor_style(&mut self, cursor_style: CursorStyle) {
+        self.cursor_style(cursor_style);
+        self.redraw = true;
+    }
+
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor_style
+    }
+
+    /// show or hide a blinking cursor, called from the frame loop
+    pub fn blink_cursor(&mut self, on: bool) {
+        if self.cursor_blink_on != on {
+            self.cursor_blink_on = on;
+            if self.cursor_style.blink {
+                self.redraw = true;
+            }
+        }
+    }
+
     pub fn cursor_colors(&mut self, insert: Color, normal: Color) -> &mut Self {
         self.cursor_insert_color = insert;
         self.cursor_normal_color = normal;
@@ -257,7 +510,76 @@ impl<'a> Text<'a> {
     }
 
     pub fn get_size(&self) -> Vector2i {
-        Vector2i::new(self.texture.size().x as i32, self.texture.size().y as i32)
+        self.size
+    }
+
+    pub fn set_title(&mut self, title: Option<&str>) {
+        self.title = title.map(|title| title.to_string());
+        self.update_grid_size();
+        self.redraw = true;
+    }
+
+    pub fn set_border(&mut self, thickness: i32, color: Color) {
+        self.border = thickness.max(0);
+        self.border_color = color;
+        self.update_grid_size();
+        self.redraw = true;
+    }
+
+    pub fn set_padding(&mut self, padding: i32) {
+        self.padding = padding.max(0);
+        self.update_grid_size();
+        self.redraw = true;
+    }
+
+    fn title_height(&self) -> i32 {
+        if self.title.is_some() {
+            self.char_size.y * self.font_scale
+        } else {
+            0
+        }
+    }
+
+    /// the area inside the border, title and padding, relative to the position
+    pub fn content_rect(&self) -> IntRect {
+        let inset = self.border + self.padding;
+        let top = inset + self.title_height();
+        IntRect::new(
+            inset,
+            top,
+            (self.size.x - 2 * inset).max(0),
+            (self.size.y - top - inset).max(0),
+        )
+    }
+
+    /// number of columns and rows that fit inside the border, title and padding
+    pub fn grid_size(&self) -> Vector2i {
+        self.grid_size
+    }
+
+    fn update_grid_size(&mut self) {
+        let content = self.content_rect();
+        let char_width = self.char_size.x * self.font_scale;
+        let char_height = self.char_size.y * self.font_scale;
+        self.grid_size = if char_width > 0 && char_height > 0 {
+            Vector2i::new(content.width / char_width, content.height / char_height)
+        } else {
+            Vector2i::new(0, 0)
+        };
+    }
+
+    /// the size to pass to `set_position_size` for a grid of `columns` x `rows` of `font`
+    pub fn size_for_grid(&self, columns: i32, rows: i32, font: &font::Font) -> Vector2i {
+        let inset = self.border + self.padding;
+        let title_height = if self.title.is_some() {
+            font.char_size.y * self.font_scale
+        } else {
+            0
+        };
+        Vector2i::new(
+            columns * font.char_size.x * self.font_scale + 2 * inset,
+            rows * font.char_size.y * self.font_scale + 2 * inset + title_height,
+        )
     }
 
     /// write text to the internal buffer
@@ -265,160 +587,197 @@ impl<'a> Text<'a> {
     /// ansi escape codes are interpreted if they manipulate the buffer or the cursor
     /// ansi escape codes for color and style are stored in the buffer
     pub fn write(&mut self, input: &str) {
-        if self.text.is_empty() {
-            self.text.push(String::new());
-        };
-
-        let mut line: Vec<char>;
+        let mut line_len = self.line_len();
 
-        macro_rules! text2line {
-            () => {
-                while self.text.len() <= self.cursor_position.y as usize {
-                    self.text.push(String::new());
+        let bytes = input.as_bytes();
+        let mut idx = 0;
+        while idx < input.len() {
+            match bytes[idx] {
+                b'\x1b' => {
+                    let codes = Ansi::parse(&input[idx..]);
+                    // char_count is in chars, idx is in bytes
+                    idx += input[idx..]
+                        .char_indices()
+                        .nth(codes.char_count.max(1))
+                        .map_or(input.len() - idx, |(i, _)| i);
+                    for code in codes.codes {
+                        self.write_ansi_code(code, &mut line_len);
+                    }
                 }
-                line = self.text[self.cursor_position.y as usize]
-                    .chars()
-                    .collect::<Vec<_>>()
-            };
+                b'\r' => {
+                    self.cursor_position.x = 0;
+                    idx += 1;
+                }
+                b'\n' => {
+                    self.write_newline(&mut line_len);
+                    idx += 1;
+                }
+                _ => {
+                    // the control characters are ascii, so they can be searched for bytewise
+                    let end = bytes[idx..]
+                        .iter()
+                        .position(|b| matches!(b, b'\x1b' | b'\r' | b'\n'))
+                        .map_or(input.len(), |len| idx + len);
+                    self.write_str(&input[idx..end], &mut line_len);
+                    idx = end;
+                }
+            }
         }
 
-        macro_rules! line2text {
-            () => {
-                self.text[self.cursor_position.y as usize] = line.into_iter().collect::<String>()
-            };
-        }
+        self.line_len_cache = Some((
+            self.cursor_position.y as usize,
+            self.text[self.cursor_position.y as usize].len(),
+            line_len,
+        ));
+        self.redraw = true;
+    }
 
-        macro_rules! line_set {
-            ($c:expr) => {
-                if self.insert_mode == InsertMode::Overwrite {
-                    while line.len() <= self.cursor_position.x as usize {
-                        line.push(' ');
-                    }
-                    line[self.cursor_position.x as usize] = $c;
-                } else {
-                    while line.len() < self.cursor_position.x as usize {
-                        line.push(' ');
-                    }
-                    line.insert(self.cursor_position.x as usize, $c);
-                }
-            };
+    /// number of chars in the cursor line, creating the line if needed
+    ///
+    /// Counting the chars of a long line on every write would make writing in small chunks
+    /// quadratic, so the count is cached.
+    fn line_len(&mut self) -> usize {
+        let y = self.cursor_position.y.max(0) as usize;
+        while self.text.len() <= y {
+            self.text.push(String::new());
         }
-
-        macro_rules! line_push_string {
-            ($s:expr) => {
-                for c in $s.chars() {
-                    line_set!(c);
-                    self.cursor_position.x += 1;
-                }
-            };
+        match self.line_len_cache {
+            Some((line, bytes, chars)) if line == y && self.text[y].len() == bytes => chars,
+            _ => self.text[y].chars().count(),
         }
+    }
 
-        text2line!();
+    /// byte offset of char `x` in `line`, or the length of the line
+    fn byte_offset(line: &str, x: usize) -> usize {
+        line.char_indices().nth(x).map_or(line.len(), |(i, _)| i)
+    }
 
-        let mut idx = 0;
-        let chars = input.chars().collect::<Vec<_>>();
-        while idx < chars.len() {
-            let c = chars[idx];
-            // test for Ansi sequence
-            if c == '\x1b' {
-                let codes = Ansi::parse(&input[idx..]);
-                idx += codes.char_count;
-                for code in codes.codes {
-                    match code {
-                        AnsiCode::CursorUp(n) => {
-                            line2text!();
-                            self.cursor_position.y = (self.cursor_position.y - n).max(0);
-                            text2line!();
-                        }
-                        AnsiCode::CursorDown(n) => {
-                            line2text!();
-                            self.cursor_position.y =
-                                (self.cursor_position.y + n).min(self.text.len() as i32 - 1);
-                            text2line!();
-                        }
-                        AnsiCode::CursorTo(y, x) => {
-                            self.cursor_position.x = (x - 1).max(0);
-                            self.cursor_position.y = (y - 1).max(0);
-                            text2line!();
-                        }
-                        AnsiCode::ColorForeground(color) => {
-                            let (r, g, b) = (color.r, color.g, color.b);
-                            line_push_string!(format!("\x1b[38;2;{};{};{}m", r, g, b));
-                        }
-                        AnsiCode::ColorBackground(color) => {
-                            let (r, g, b) = (color.r, color.g, color.b);
-                            line_push_string!(format!("\x1b[48;2;{};{};{}m", r, g, b));
-                        }
-                        AnsiCode::Bold => {
-                            line_push_string!("\x1b[1m");
-                        }
-                        AnsiCode::ResetAll => {
-                            line_push_string!("\x1b[0m");
-                        }
-                        _ => {
-                            eprintln!("Unrecognized ANSI escape code: {:?}", code);
-                        }
-                    }
+    /// write a string without control characters at the cursor position
+    fn write_str(&mut self, s: &str, line_len: &mut usize) {
+        let x = self.cursor_position.x as usize;
+        let n = s.chars().count();
+        let line = &mut self.text[self.cursor_position.y as usize];
+        if x >= *line_len {
+            // appending is the common case, it does not need to look at the line
+            line.extend(std::iter::repeat_n(' ', x - *line_len));
+            line.push_str(s);
+            *line_len = x + n;
+        } else {
+            let start = Self::byte_offset(line, x);
+            match self.insert_mode {
+                InsertMode::Overwrite => {
+                    let end = start + Self::byte_offset(&line[start..], n);
+                    line.replace_range(start..end, s);
+                    *line_len = (*line_len).max(x + n);
                 }
-            } else if c == '\r' {
-                self.cursor_position.x = 0;
-                idx += 1;
-            } else if c == '\n' {
-                if self.insert_mode == InsertMode::Overwrite {
-                    line2text!();
-                    self.cursor_position.x = 0;
-                    self.cursor_position.y += 1;
-                    text2line!();
-                } else {
-                    let line_right: Vec<char> = line[self.cursor_position.x as usize..].to_vec();
-                    line = line[..self.cursor_position.x as usize].to_vec();
-                    line2text!();
-                    line = line_right;
-                    if self.cursor_position.y == self.text.len() as i32 - 1 {
-                        self.text.push(String::new());
-                    } else {
-                        self.text
-                            .insert(self.cursor_position.y as usize + 1, String::new());
-                    }
-                    self.cursor_position.x = 0;
-                    self.cursor_position.y += 1;
+                InsertMode::Insert => {
+                    line.insert_str(start, s);
+                    *line_len += n;
                 }
-                idx += 1;
-            } else {
-                line_set!(c);
-                self.cursor_position.x += 1;
-                idx += 1;
             }
         }
+        self.cursor_position.x += n as i32;
+    }
 
-        line2text!();
+    fn write_newline(&mut self, line_len: &mut usize) {
+        let y = self.cursor_position.y as usize;
+        if self.insert_mode == InsertMode::Insert {
+            let line = &mut self.text[y];
+            let split = Self::byte_offset(line, self.cursor_position.x as usize);
+            let line_right = line.split_off(split);
+            self.text.insert(y + 1, line_right);
+        }
+        self.cursor_position.x = 0;
+        self.cursor_position.y += 1;
+        *line_len = self.line_len();
+    }
 
-        self.redraw = true;
+    fn write_ansi_code(&mut self, code: AnsiCode, line_len: &mut usize) {
+        match code {
+            AnsiCode::CursorUp(n) => {
+                self.cursor_position.y = (self.cursor_position.y - n).max(0);
+                *line_len = self.line_len();
+            }
+            AnsiCode::CursorDown(n) => {
+                self.cursor_position.y =
+                    (self.cursor_position.y + n).min(self.text.len() as i32 - 1);
+                *line_len = self.line_len();
+            }
+            AnsiCode::CursorTo(y, x) => {
+                self.cursor_position.x = (x - 1).max(0);
+                self.cursor_position.y = (y - 1).max(0);
+                *line_len = self.line_len();
+            }
+            AnsiCode::ColorForeground(color) => {
+                let (r, g, b) = (color.r, color.g, color.b);
+                self.write_str(&format!("\x1b[38;2;{};{};{}m", r, g, b), line_len);
+            }
+            AnsiCode::ColorBackground(color) => {
+                let (r, g, b) = (color.r, color.g, color.b);
+                self.write_str(&format!("\x1b[48;2;{};{};{}m", r, g, b), line_len);
+            }
+            AnsiCode::Bold => {
+                self.write_str("\x1b[1m", line_len);
+            }
+            AnsiCode::ResetAll => {
+                self.write_str("\x1b[0m", line_len);
+            }
+            AnsiCode::SemanticPrompt(mark, exit_code) => {
+                let kind = match mark {
+                    'A' => MarkKind::Prompt,
+                    'B' => MarkKind::Command,
+                    'C' => MarkKind::OutputStart,
+                    _ => MarkKind::OutputEnd(exit_code),
+                };
+                self.add_mark(self.cursor_position.y as usize, kind);
+            }
+            AnsiCode::CursorShape(ps) => match CursorStyle::from_decscusr(ps) {
+                Some(style) => self.cursor_style = style,
+                None => eprintln!("Unrecognized cursor shape: {}", ps),
+            },
+            AnsiCode::PrivateMode(2004, on) => self.bracketed_paste = on,
+            _ => {
+                eprintln!("Unrecognized ANSI escape code: {:?}", code);
+            }
+        }
     }
 
     pub fn get_text(&self) -> Vec<String> {
         self.text.clone()
     }
 
+    pub fn lines(&self) -> &[String] {
+        &self.text
+    }
+
+    /// the lines to change in place
+    pub fn lines_mut(&mut self) -> &mut Vec<String> {
+        self.line_len_cache = None;
+        self.redraw = true;
+        &mut self.text
+    }
+
     pub fn clear_to_here(&mut self) {
-        let line = self.text[self.cursor_position.y as usize].clone()
-            [self.cursor_position.x as usize..]
-            .to_string();
+        self.line_len_cache = None;
+        let line = &self.text[self.cursor_position.y as usize];
+        let line = line[Self::byte_offset(line, self.cursor_position.x as usize)..].to_string();
         self.text[self.cursor_position.y as usize] = line;
         self.cursor_position.x = 0;
         self.redraw = true;
     }
 
     pub fn clear_from_here(&mut self) {
-        let line = self.text[self.cursor_position.y as usize].clone()
-            [..self.cursor_position.x as usize]
-            .to_string();
+        self.line_len_cache = None;
+        let line = &self.text[self.cursor_position.y as usize];
+        let line = line[..Self::byte_offset(line, self.cursor_position.x as usize)].to_string();
         self.text[self.cursor_position.y as usize] = line;
         self.redraw = true;
     }
 
     pub fn replace(&mut self, text: Vec<String>) -> Vec<String> {
         let old_text = std::mem::replace(&mut self.text, text);
+        self.line_len_cache = None;
+        self.marks.clear();
         if self.text.is_empty() {
             self.text.push(String::new());
         }
@@ -433,10 +792,11 @@ impl<'a> Text<'a> {
         let text_end = self.text.len() - scroll_up_lines;
 
         let font_height = font.char_size.y * self.font_scale;
+        let content = self.content_rect();
+        let height = content.height;
 
-        let fully_visible_lines = self.texture.size().y as i32 / font_height;
-        let partially_visible_lines =
-            (self.texture.size().y as i32 + font_height - 1) / font_height;
+        let fully_visible_lines = height / font_height;
+        let partially_visible_lines = (height + font_height - 1) / font_height;
 
         let text_len = text_end as i32;
         let fully_shown_lines = fully_visible_lines.min(text_len);
@@ -445,7 +805,7 @@ impl<'a> Text<'a> {
         let partially_skipped_lines = text_len - partially_shown_lines;
         let fully_skipped_lines = text_len - fully_shown_lines;
         let start_y = if fully_skipped_lines > 0 {
-            self.texture.size().y as i32 - partially_shown_lines * font_height
+            height - partially_shown_lines * font_height
         } else {
             0
         };
@@ -453,10 +813,10 @@ impl<'a> Text<'a> {
         let (partially_skipped_lines, start_y) = match self.vertical_alignment {
             VerticalAlignment::AlwaysTop => (0, 0),
             VerticalAlignment::AlwaysBottom => {
-                if text_len * font_height > self.texture.size().y as i32 {
+                if text_len * font_height > height {
                     (partially_skipped_lines, start_y)
                 } else {
-                    (0, self.texture.size().y as i32 - text_len * font_height)
+                    (0, height - text_len * font_height)
                 }
             }
             VerticalAlignment::BottomOnOverflow => {
@@ -467,133 +827,269 @@ impl<'a> Text<'a> {
                 }
             }
         };
-        (text_end, partially_skipped_lines, start_y)
+        (text_end, partially_skipped_lines, content.top + start_y)
     }
 
-    fn draw_text(
-        &mut self,
-        font: &font::Font,
-        text_end: usize,
-        partially_skipped_lines: i32,
-        start_y: i32,
-    ) {
-        let font_width = font.char_size.x * self.font_scale;
-        let font_height = font.char_size.y * self.font_scale;
-        let bold_offset = if font.char_size.x > 12 { 2.0 } else { 1.0 }; // try to guess if font
-                                                                         // stroke is 2 pixels
+    fn default_style(&self) -> CellStyle {
+        CellStyle {
+            fg: self.fg_color,
+            bg: self.bg_color,
+            bold: self.bold,
+        }
+    }
+
+    fn apply_color_code(&self, code: ColorType, style: &mut CellStyle) {
+        match code {
+            ColorType::Regular(color) | ColorType::HighIntensity(color) => {
+                style.fg = self.ansi_colors.get_color_from_ansi(color).unwrap()
+            }
+            ColorType::Background(color) | ColorType::BackgroundHighIntensity(color) => {
+                style.bg = self.ansi_colors.get_color_from_ansi(color).unwrap()
+            }
+            ColorType::ResetFg => style.fg = self.fg_color,
+            ColorType::ResetBg => style.bg = self.bg_color,
+            ColorType::RgbFg(r, g, b) => style.fg = Color::rgb(r, g, b),
+            ColorType::RgbBg(r, g, b) => style.bg = Color::rgb(r, g, b),
+            ColorType::Reset => {
+                style.fg = self.fg_color;
+                style.bg = self.bg_color;
+                style.bold = false;
+            }
+            ColorType::Bold => style.bold = true,
+            ColorType::Italic => {}
+        }
+    }
 
-        let font_texture_size = font.texture.size();
-        self.shader.set_uniform_vec2(
+    /// split a line into the cells that are drawn, interpreting the ansi color codes
+    /// `style` carries over to the next line
+    fn line_cells(&self, line: &str, style: &mut CellStyle) -> Vec<Cell> {
+        let line = line.chars().collect::<Vec<_>>();
+        let mut cells = Vec::with_capacity(line.len());
+        let mut x = 0;
+        while x < line.len() {
+            if line[x] == '\x1b' {
+                let rest_of_line: String = line[x..].iter().collect();
+                let (to_skip, codes) = self.ansi_colors.parse_ansi_color_code(&rest_of_line);
+                if codes.is_empty() {
+                    eprintln!(
+                        "Unrecognized ANSI escape code: {}",
+                        line.iter().collect::<String>()
+                    );
+                }
+                for code in codes {
+                    self.apply_color_code(code, style);
+                }
+                x += to_skip.max(1);
+            } else {
+                cells.push(Cell {
+                    ch: line[x],
+                    style: *style,
+                });
+                x += 1;
+            }
+        }
+        cells
+    }
+
+    /// column ranges (end exclusive) of `line` that are drawn with a different background,
+    /// the selection comes first as it has precedence over search matches
+    fn line_highlights(&self, line: usize) -> Vec<(usize, usize, Color)> {
+        let mut highlights = vec![];
+        if let Some((start, end)) = self.selection_columns(line) {
+            highlights.push((start, end, self.selection_color));
+        }
+        let first_match = self.search_matches.partition_point(|m| m.line < line);
+        for (i, m) in self.search_matches[first_match..]
+            .iter()
+            .take_while(|m| m.line == line)
+            .enumerate()
+        {
+            let color = if Some(first_match + i) == self.search_current {
+                self.search_current_color
+            } else {
+                self.search_color
+            };
+            highlights.push((m.start, m.end, color));
+        }
+        highlights
+    }
+
+    /// the color of a highlight, sorted by line, that contains `column`
+    fn cell_foreground(&self, line: usize, column: usize, cell: &Cell) -> Color {
+        let first = self.highlights.partition_point(|h| h.line < line);
+        self.highlights[first..]
+            .iter()
+            .take_while(|h| h.line == line)
+            .find(|h| (h.start..h.end).contains(&column))
+            .map_or(cell.style.fg, |h| h.color)
+    }
+
+    fn cell_background(highlights: &[(usize, usize, Color)], column: usize, cell: &Cell) -> Color {
+        highlights
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&column))
+            .map_or(cell.style.bg, |(_, _, color)| *color)
+    }
+
+    fn set_atlas_uniforms(shader: &mut Shader, font: &font::Font) {
+        let font_texture_size = font.page_size();
+        shader.set_uniform_vec2(
             "atlas_size",
             Vec2::new(font_texture_size.x as f32, font_texture_size.y as f32),
         );
-        self.shader.set_uniform_vec2(
+        shader.set_uniform_vec2(
             "sprite_size",
             Vec2::new(font.char_size.x as f32, font.char_size.y as f32),
         );
+    }
 
-        macro_rules! set_fg {
-            ($fg:expr) => {
-                self.shader.set_uniform_vec4("fg_color", Vec4::from($fg))
-            };
-        }
-        macro_rules! set_bg {
-            ($bg:expr) => {
-                self.shader.set_uniform_vec4("bg_color", Vec4::from($bg))
-            };
-        }
-        macro_rules! set_bold {
-            ($bold:expr) => {
-                self.shader
-                    .set_uniform_float("bold_offset", if $bold { bold_offset } else { 0.0 })
-            };
-        }
-        set_fg!(self.fg_color);
-        set_bg!(self.bg_color);
-        set_bold!(self.bold);
+    #[allow(clippy::too_many_arguments)]
+    fn draw_cell(
+        &self,
+        texture: &mut RenderTexture,
+        shader: &mut Shader,
+        font: &font::Font,
+        ch: char,
+        position: Vector2f,
+        fg: Color,
+        bg: Color,
+        bold_offset: f32,
+    ) {
+        shader.set_uniform_vec4("fg_color", Vec4::from(fg));
+        shader.set_uniform_vec4("bg_color", Vec4::from(bg));
+        shader.set_uniform_float("bold_offset", bold_offset);
+
+        let mut sprite = font.get_sprite(ch as i32);
+        sprite.set_position(position);
+        sprite.set_scale(Vector2f::new(
+            self.font_scale as f32,
+            self.font_scale as f32,
+        ));
+        shader.set_uniform_vec2(
+            "sprite_position",
+            Vec2::new(
+                sprite.texture_rect().left as f32,
+                sprite.texture_rect().top as f32,
+            ),
+        );
+        let mut states_fg_bg = RenderStates::default();
+        states_fg_bg.set_shader(Some(shader));
+        texture.draw_with_renderstates(&sprite, &states_fg_bg);
+    }
 
-        self.texture.clear(self.bg_color);
+    fn draw_text(
+        &self,
+        texture: &mut RenderTexture,
+        shader: &mut Shader,
+        font: &font::Font,
+        text_end: usize,
+        partially_skipped_lines: i32,
+        start_y: i32,
+    ) {
+        let font_width = font.char_size.x * self.font_scale;
+        let font_height = font.char_size.y * self.font_scale;
+        let bold_offset = font.stroke_width as f32;
+        let left = self.content_rect().left;
+
+        texture.clear(self.bg_color);
 
+        let mut style = self.default_style();
         for (y, line) in self.text[partially_skipped_lines as usize..text_end]
             .iter()
             .enumerate()
         {
-            let line = line.chars().collect::<Vec<_>>();
-            let mut skip_chars = 0;
-            let mut skipped_chars = 0;
-            for (x, ch) in line.iter().enumerate() {
-                if skip_chars > 1 {
-                    skip_chars -= 1;
-                    skipped_chars += 1;
-                } else if *ch == 27 as char {
-                    skipped_chars += 1;
-                    let rest_of_line: String = line[x..].iter().collect();
-                    let (to_skip, codes) = self.ansi_colors.parse_ansi_color_code(&rest_of_line);
-                    skip_chars = to_skip;
-                    if codes.is_empty() {
-                        eprintln!(
-                            "Unrecognized ANSI escape code: {}",
-                            line.iter().collect::<String>()
-                        );
-                    }
-                    for code in codes {
-                        match code {
-                            ColorType::Regular(color) => {
-                                set_fg!(self.ansi_colors.get_color_from_ansi(color).unwrap())
-                            }
-                            ColorType::HighIntensity(color) => {
-                                set_fg!(self.ansi_colors.get_color_from_ansi(color).unwrap())
-                            }
-                            ColorType::Background(color) => {
-                                set_bg!(self.ansi_colors.get_color_from_ansi(color).unwrap())
-                            }
-                            ColorType::BackgroundHighIntensity(color) => {
-                                set_bg!(self.ansi_colors.get_color_from_ansi(color).unwrap())
-                            }
-                            ColorType::ResetFg => set_fg!(self.fg_color),
-                            ColorType::ResetBg => set_bg!(self.bg_color),
-                            ColorType::RgbFg(r, g, b) => set_fg!(Color::rgb(r, g, b)),
-                            ColorType::RgbBg(r, g, b) => set_bg!(Color::rgb(r, g, b)),
-                            ColorType::Reset => {
-                                set_fg!(self.fg_color);
-                                set_bg!(self.bg_color);
-                                set_bold!(false);
-                            }
-                            ColorType::Bold => {
-                                set_bold!(true);
-                            }
-                            ColorType::Italic => {}
-                        }
-                    }
-                } else {
-                    let mut sprite = font.get_sprite(*ch as i32);
-                    sprite.set_position(Vector2f::new(
-                        ((x - skipped_chars) as i32 * font_width) as f32,
-                        (start_y + y as i32 * font_height) as f32,
-                    ));
-                    sprite.set_scale(Vector2f::new(
-                        self.font_scale as f32,
-                        self.font_scale as f32,
-                    ));
-                    self.shader.set_uniform_vec2(
-                        "sprite_position",
-                        Vec2::new(
-                            sprite.texture_rect().left as f32,
-                            sprite.texture_rect().top as f32,
-                        ),
-                    );
-                    let mut states_fg_bg = RenderStates::default();
-                    states_fg_bg.set_shader(Some(&self.shader));
-                    self.texture.draw_with_renderstates(&sprite, &states_fg_bg);
-                }
+            let line_idx = partially_skipped_lines as usize + y;
+            let highlights = self.line_highlights(line_idx);
+            for (column, cell) in self.line_cells(line, &mut style).iter().enumerate() {
+                let fg = self.cell_foreground(line_idx, column, cell);
+                let bg = Self::cell_background(&highlights, column, cell);
+                let position = Vector2f::new(
+                    (left + column as i32 * font_width) as f32,
+                    (start_y + y as i32 * font_height) as f32,
+                );
+                let bold_offset = if cell.style.bold { bold_offset } else { 0.0 };
+                self.draw_cell(
+                    texture,
+                    shader,
+                    font,
+                    cell.ch,
+                    position,
+                    fg,
+                    bg,
+                    bold_offset,
+                );
             }
         }
     }
 
-    fn draw_cursor(&mut self, font: &font::Font, start_y: i32) {
+    /// padding, border and title, drawn over lines that are only partially visible
+    fn draw_chrome(&self, texture: &mut RenderTexture, shader: &mut Shader, font: &font::Font) {
+        let content = self.content_rect();
+        let size = Vector2f::new(self.size.x as f32, self.size.y as f32);
+
+        if self.padding > 0 {
+            let mut rectangle = RectangleShape::with_size(Vector2f::new(
+                content.width as f32,
+                content.height as f32,
+            ));
+            rectangle.set_position(Vector2f::new(content.left as f32, content.top as f32));
+            rectangle.set_fill_color(Color::TRANSPARENT);
+            rectangle.set_outline_color(self.bg_color);
+            rectangle.set_outline_thickness(self.padding as f32);
+            texture.draw(&rectangle);
+        }
+
+        if self.border > 0 {
+            let border = self.border as f32;
+            let mut rectangle = RectangleShape::with_size(Vector2f::new(
+                (size.x - 2.0 * border).max(0.0),
+                (size.y - 2.0 * border).max(0.0),
+            ));
+            rectangle.set_position(Vector2f::new(border, border));
+            rectangle.set_fill_color(Color::TRANSPARENT);
+            rectangle.set_outline_color(self.border_color);
+            rectangle.set_outline_thickness(border);
+            texture.draw(&rectangle);
+        }
+
+        if let Some(title) = &self.title {
+            let font_width = font.char_size.x * self.font_scale;
+            let border = self.border as f32;
+            let mut bar = RectangleShape::with_size(Vector2f::new(
+                (size.x - 2.0 * border).max(0.0),
+                self.title_height() as f32,
+            ));
+            bar.set_position(Vector2f::new(border, border));
+            bar.set_fill_color(self.border_color);
+            texture.draw(&bar);
+            let columns = (size.x as i32 - 2 * self.border - 2 * self.padding) / font_width;
+            for (column, ch) in title.chars().take(columns.max(0) as usize).enumerate() {
+                let position =
+                    Vector2f::new((content.left + column as i32 * font_width) as f32, border);
+                self.draw_cell(
+                    texture,
+                    shader,
+                    font,
+                    ch,
+                    position,
+                    self.bg_color,
+                    self.border_color,
+                    0.0,
+                );
+            }
+        }
+    }
+
+    fn draw_cursor(
+        &self,
+        texture: &mut RenderTexture,
+        shader: &mut Shader,
+        font: &font::Font,
+        start_y: i32,
+        skipped_lines: i32,
+    ) {
         let font_width = font.char_size.x * self.font_scale;
         let font_height = font.char_size.y * self.font_scale;
-        //let stroke_width = if font_width > 10 { 2.0 } else { 1.0 };
 
         let mix = |fg: Vec4, bg: Vec4| -> Vec4 {
             Vec4 {
@@ -617,57 +1113,126 @@ impl<'a> Text<'a> {
             CursorState::Hidden => return,
         };
 
-        self.shader.set_uniform_vec4("bg_color", fg);
-        self.shader
-            .set_uniform_vec4("fg_color", Vec4::from(self.bg_color));
-        self.shader
-            .set_uniform_float("bold_offset", if self.bold { 1.0 } else { 0.0 });
-        let font_texture_size = font.texture.size();
-        self.shader.set_uniform_vec2(
-            "atlas_size",
-            Vec2::new(font_texture_size.x as f32, font_texture_size.y as f32),
+        let active = matches!(
+            self.cursor_state,
+            CursorState::NormalActive | CursorState::InsertActive
         );
-        self.shader.set_uniform_vec2(
-            "sprite_size",
-            Vec2::new(font.char_size.x as f32, font.char_size.y as f32),
+        if active && self.cursor_style.blink && !self.cursor_blink_on {
+            return;
+        }
+
+        let position = Vector2f::new(
+            (self.content_rect().left + self.cursor_position.x * font_width) as f32,
+            (start_y + (self.cursor_position.y - skipped_lines) * font_height) as f32,
         );
+        let stroke_width = (font.stroke_width * 2 * self.font_scale) as f32;
+        let (offset, size) = match self.cursor_style.shape {
+            CursorShape::Block => (Vector2f::new(0.0, 0.0), None),
+            // the underline of the font
+            CursorShape::Underline => (
+                Vector2f::new(0.0, (font.underline_position * self.font_scale) as f32),
+                Some(Vector2f::new(
+                    font_width as f32,
+                    (font.underline_thickness * self.font_scale) as f32,
+                )),
+            ),
+            CursorShape::Bar => (
+                Vector2f::new(0.0, 0.0),
+                Some(Vector2f::new(stroke_width, font_height as f32)),
+            ),
+        };
+        if let Some(size) = size {
+            let to_u8 = |c: f32| (c * 255.0).round() as u8;
+            let mut rectangle = RectangleShape::with_size(size);
+            rectangle.set_position(position + offset);
+            rectangle.set_fill_color(Color::rgba(
+                to_u8(fg.x),
+                to_u8(fg.y),
+                to_u8(fg.z),
+                to_u8(fg.w),
+            ));
+            texture.draw(&rectangle);
+            return;
+        }
+
+        shader.set_uniform_vec4("bg_color", fg);
+        shader.set_uniform_vec4("fg_color", Vec4::from(self.bg_color));
+        let bold_offset = if self.bold { font.stroke_width } else { 0 };
+        shader.set_uniform_float("bold_offset", bold_offset as f32);
 
         let mut states_bg_fg = RenderStates::default();
-        states_bg_fg.set_shader(Some(&self.shader));
+        states_bg_fg.set_shader(Some(shader));
 
         let ch = self.text[self.cursor_position.y as usize]
             .chars()
             .nth(self.cursor_position.x as usize)
             .unwrap_or(' ');
         let mut sprite = font.get_sprite(ch as i32);
-        sprite.set_position(Vector2f::new(
-            (self.cursor_position.x * font_width) as f32,
-            (start_y + self.cursor_position.y * font_height) as f32,
-        ));
+        sprite.set_position(position);
         sprite.set_scale(Vector2f::new(
             self.font_scale as f32,
             self.font_scale as f32,
         ));
-        self.texture.draw_with_renderstates(&sprite, &states_bg_fg);
+        texture.draw_with_renderstates(&sprite, &states_bg_fg);
     }
 
     pub fn draw(&mut self, window: &mut RenderWindow, font: &font::Font) {
+        // sized before the font was known, or the font changed since
+        if self.char_size != font.char_size {
+            self.char_size = font.char_size;
+            self.update_grid_size();
+        }
+        let mut texture = match self.texture.take() {
+            Some(texture) => texture,
+            None => {
+                self.redraw = true;
+                RenderTexture::new(self.size.x as u32, self.size.y as u32).unwrap()
+            }
+        };
+        let mut shader = match self.shader.take() {
+            Some(shader) => shader,
+            None => Shader::from_file(
+                &config::Config::get_resource_path("color_bold.frag"),
+                ShaderType::Fragment,
+            )
+            .unwrap(),
+        };
+
         if self.redraw {
             self.redraw = false;
 
             let (text_end, partially_skipped_lines, start_y) = self.calculate_scroll_position(font);
-            self.draw_text(font, text_end, partially_skipped_lines, start_y);
-            self.draw_cursor(font, start_y);
+            Self::set_atlas_uniforms(&mut shader, font);
+            self.draw_text(
+                &mut texture,
+                &mut shader,
+                font,
+                text_end,
+                partially_skipped_lines,
+                start_y,
+            );
+            self.draw_cursor(
+                &mut texture,
+                &mut shader,
+                font,
+                start_y,
+                partially_skipped_lines,
+            );
+            self.draw_chrome(&mut texture, &mut shader, font);
         }
-        self.texture.display();
+        texture.display();
 
-        let mut sprite = Sprite::with_texture(self.texture.texture());
+        let mut sprite = Sprite::with_texture(texture.texture());
         sprite.set_position(Vector2f::new(
             self.position.x as f32,
             self.position.y as f32,
         ));
 
         window.draw(&sprite);
+        drop(sprite);
+
+        self.texture = Some(texture);
+        self.shader = Some(shader);
     }
 
     pub fn must_draw(&self) -> bool {
@@ -687,6 +1252,247 @@ impl<'a> Text<'a> {
         }
         self.redraw = true;
     }
+
+    /// marks are kept sorted by line, marks on the same line in the order they were added
+    pub fn add_mark(&mut self, line: usize, kind: MarkKind) {
+        let idx = self.marks.partition_point(|m| m.line <= line);
+        self.marks.insert(idx, Mark { line, kind });
+    }
+
+    pub fn marks(&self) -> &[Mark] {
+        &self.marks
+    }
+
+    /// line ranges of all command outputs, from an output start mark up to the next output end
+    /// or prompt mark; the last output is open ended while its command is running
+    pub fn outputs(&self) -> Vec<std::ops::Range<usize>> {
+        let mut outputs = vec![];
+        let mut start = None;
+        for mark in &self.marks {
+            match mark.kind {
+                MarkKind::OutputStart => start = Some(mark.line),
+                MarkKind::OutputEnd(_) | MarkKind::Prompt => {
+                    if let Some(start) = start.take() {
+                        outputs.push(start..mark.line.max(start));
+                    }
+                }
+                MarkKind::Command => {}
+            }
+        }
+        if let Some(start) = start {
+            outputs.push(start..self.text.len().max(start));
+        }
+        outputs
+    }
+
+    /// get a line of the buffer without ansi escape codes
+    pub fn plain_line(&self, line: usize) -> String {
+        strip_ansi(&self.text[line])
+    }
+
+    /// search the whole buffer for `pattern` and highlight all matches
+    /// `pattern` is a regular expression if `use_regex` is set, plain text otherwise
+    /// the match closest to the end of the buffer becomes the current match
+    /// returns the number of matches
+    pub fn search(&mut self, pattern: &str, use_regex: bool) -> Result<usize, regex::Error> {
+        self.search_clear();
+        if pattern.is_empty() {
+            return Ok(0);
+        }
+        let regex = if use_regex {
+            Some(regex::Regex::new(pattern)?)
+        } else {
+            None
+        };
+
+        let char_idx = |line: &str, byte_idx: usize| line[..byte_idx].chars().count();
+        for (line_idx, line) in self.text.iter().enumerate() {
+            let plain = strip_ansi(line);
+            let ranges = match &regex {
+                Some(regex) => regex
+                    .find_iter(&plain)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (m.start(), m.end()))
+                    .collect::<Vec<_>>(),
+                None => plain
+                    .match_indices(pattern)
+                    .map(|(start, s)| (start, start + s.len()))
+                    .collect(),
+            };
+            for (start, end) in ranges {
+                self.search_matches.push(SearchMatch {
+                    line: line_idx,
+                    start: char_idx(&plain, start),
+                    end: char_idx(&plain, end),
+                });
+            }
+        }
+
+        if !self.search_matches.is_empty() {
+            self.search_current = Some(self.search_matches.len() - 1);
+        }
+        self.redraw = true;
+        Ok(self.search_matches.len())
+    }
+
+    pub fn search_clear(&mut self) {
+        if !self.search_matches.is_empty() {
+            self.redraw = true;
+        }
+        self.search_matches.clear();
+        self.search_current = None;
+    }
+
+    /// move to the next match, towards the start of the buffer if `backward` is set
+    /// wraps around at either end of the buffer
+    pub fn search_next(&mut self, backward: bool) -> Option<SearchMatch> {
+        let len = self.search_matches.len();
+        if len == 0 {
+            return None;
+        }
+        let current = match (self.search_current, backward) {
+            (None, _) => len - 1,
+            (Some(idx), true) => (idx + len - 1) % len,
+            (Some(idx), false) => (idx + 1) % len,
+        };
+        self.search_current = Some(current);
+        self.redraw = true;
+        Some(self.search_matches[current])
+    }
+
+    pub fn search_matches(&self) -> &[SearchMatch] {
+        &self.search_matches
+    }
+
+    /// index of the current match in `search_matches()`
+    pub fn search_current(&self) -> Option<usize> {
+        self.search_current
+    }
+
+    /// color parts of the text without changing it, the highlights are sorted by line
+    pub fn set_highlights(&mut self, mut highlights: Vec<Highlight>) {
+        highlights.sort_by_key(|h| (h.line, h.start));
+        self.highlights = highlights;
+        self.redraw = true;
+    }
+
+    pub fn highlights(&self) -> &[Highlight] {
+        &self.highlights
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let size = self.get_size();
+        (self.position.x..self.position.x + size.x).contains(&x)
+            && (self.position.y..self.position.y + size.y).contains(&y)
+    }
+
+    /// the cell under window coordinates `x`, `y`, clamped to the text buffer
+    pub fn cell_at(&self, x: i32, y: i32, font: &font::Font) -> Vector2i {
+        let font_width = font.char_size.x * self.font_scale;
+        let font_height = font.char_size.y * self.font_scale;
+        let (text_end, partially_skipped_lines, start_y) = self.calculate_scroll_position(font);
+        let line =
+            partially_skipped_lines + (y - self.position.y - start_y).div_euclid(font_height);
+        let line = line.min(text_end as i32 - 1).max(0);
+        let column = ((x - self.position.x - self.content_rect().left) / font_width).max(0);
+        Vector2i::new(column, line)
+    }
+
+    pub fn selection_start(&mut self, cell: Vector2i, mode: SelectionMode) {
+        self.selection = Some(Selection {
+            anchor: cell,
+            head: cell,
+            mode,
+        });
+        self.redraw = true;
+    }
+
+    pub fn selection_extend(&mut self, cell: Vector2i) {
+        if let Some(selection) = &mut self.selection {
+            if selection.head != cell {
+                selection.head = cell;
+                self.redraw = true;
+            }
+        }
+    }
+
+    pub fn selection_clear(&mut self) {
+        if self.selection.take().is_some() {
+            self.redraw = true;
+        }
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// start and end (inclusive) of the selection, expanded to words or lines
+    fn selection_range(&self) -> Option<(SelectionMode, Vector2i, Vector2i)> {
+        let selection = self.selection?;
+        let (anchor, head) = (selection.anchor, selection.head);
+        let (mut start, mut end) = if (anchor.y, anchor.x) <= (head.y, head.x) {
+            (anchor, head)
+        } else {
+            (head, anchor)
+        };
+        match selection.mode {
+            SelectionMode::Char => {}
+            SelectionMode::Word => {
+                let line = |y: i32| {
+                    self.text
+                        .get(y as usize)
+                        .map(|line| strip_ansi(line).chars().collect::<Vec<_>>())
+                        .unwrap_or_default()
+                };
+                start.x = word_bounds(&line(start.y), start.x as usize).0 as i32;
+                end.x = word_bounds(&line(end.y), end.x as usize).1 as i32 - 1;
+            }
+            SelectionMode::Line => {
+                start.x = 0;
+                end.x = i32::MAX - 1;
+            }
+            SelectionMode::Block => {
+                start.x = anchor.x.min(head.x);
+                end.x = anchor.x.max(head.x);
+            }
+        }
+        Some((selection.mode, start, end))
+    }
+
+    /// columns of `line` that are selected, end exclusive
+    fn selection_columns(&self, line: usize) -> Option<(usize, usize)> {
+        let (mode, start, end) = self.selection_range()?;
+        let line = line as i32;
+        if line < start.y || line > end.y {
+            return None;
+        }
+        let (start_x, end_x) = if mode == SelectionMode::Block {
+            (start.x, end.x)
+        } else {
+            (
+                if line == start.y { start.x } else { 0 },
+                if line == end.y { end.x } else { i32::MAX - 1 },
+            )
+        };
+        Some((start_x as usize, end_x as usize + 1))
+    }
+
+    /// the selected text without ansi escape codes, trailing whitespace is removed from each line
+    pub fn selected_text(&self) -> Option<String> {
+        let (_, start, end) = self.selection_range()?;
+        let mut lines = vec![];
+        for y in start.y..=end.y.min(self.text.len() as i32 - 1) {
+            let line = strip_ansi(&self.text[y as usize])
+                .chars()
+                .collect::<Vec<_>>();
+            let (start_x, end_x) = self.selection_columns(y as usize).unwrap();
+            let start_x = start_x.min(line.len());
+            let end_x = end_x.min(line.len());
+            lines.push(line[start_x..end_x].iter().collect::<String>());
+        }
+        let lines = lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>();
+        Some(lines.join("\n"))
+    }
 }
 
 #[cfg(test)]
@@ -700,6 +1506,49 @@ mod tests {
         assert_eq!(text.text, vec!["Hello World"]);
     }
 
+    #[test]
+    fn test_write_chunks() {
+        let mut text = Text::default();
+        // a multibyte char in front of an escape code
+        text.write("ä\x1b[1mb");
+        assert_eq!(text.text, vec!["ä\x1b[1mb"]);
+        assert_eq!(text.cursor_position, Vector2i::new(6, 0));
+
+        // appending to a long line in small chunks must not be quadratic
+        let mut text = Text::default();
+        for _ in 0..100_000 {
+            text.write("äbcdefghij");
+        }
+        text.write("\rX\n");
+        assert_eq!(text.text.len(), 2);
+        assert_eq!(text.text[0].chars().count(), 1_000_000);
+        assert!(text.text[0].starts_with("Xbcd"));
+
+        // the cache must not be used when the line was changed from the outside
+        let mut text = Text::default();
+        text.write("abc");
+        text.lines_mut()[0] = "ä".to_string();
+        text.cursor_position.x = 1;
+        text.write("x");
+        assert_eq!(text.text, vec!["äx"]);
+
+        // nor after a change that keeps the length in bytes
+        let mut text = Text::default();
+        text.write("ab");
+        text.lines_mut()[0] = "ä".to_string();
+        text.cursor_position.x = 2;
+        text.write("x");
+        assert_eq!(text.text, vec!["ä x"]);
+        text.write("\ryz");
+        text.replace(vec!["ö".to_string()]);
+        text.write("!");
+        assert_eq!(text.text, vec!["ö!"]);
+        text.cursor_position.x = 0;
+        text.clear_from_here();
+        text.write("ä");
+        assert_eq!(text.text, vec!["ä"]);
+    }
+
     #[test]
     fn test_insert1() {
         let mut text = TextBuilder::new().insert_mode(InsertMode::Insert).build();
@@ -795,4 +1644,205 @@ mod tests {
         assert_eq!(text.cursor_position, Vector2i::new(3, 1));
         assert_eq!(text.text, vec!["Line 1", "Line 2", "Line 3", "Line 4", ""],);
     }
+
+    #[test]
+    fn test_cursor_style() {
+        let mut text = Text::new();
+        assert_eq!(text.get_cursor_style(), CursorStyle::default());
+        text.write("vim\x1b[6 q");
+        assert_eq!(
+            text.get_cursor_style(),
+            CursorStyle::new(CursorShape::Bar, false)
+        );
+        text.write("\x1b[3 q");
+        assert_eq!(
+            text.get_cursor_style(),
+            CursorStyle::new(CursorShape::Underline, true)
+        );
+        text.write("\x1b[0 q");
+        assert_eq!(
+            text.get_cursor_style(),
+            CursorStyle::new(CursorShape::Block, true)
+        );
+        assert_eq!(text.text, vec!["vim"]);
+        assert_eq!(CursorStyle::from_decscusr(7), None);
+    }
+
+    #[test]
+    fn test_bracketed_paste() {
+        let mut text = Text::new();
+        text.write("$ \x1b[?2004h");
+        assert!(text.bracketed_paste);
+        text.write("\x1b[?2004l\n");
+        assert!(!text.bracketed_paste);
+        assert_eq!(text.text, vec!["$ ", ""]);
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut text = Text::new();
+        text.write("\x1b]133;A\x07$ \x1b]133;B\x07ls\n\x1b]133;C\x07a\nb\n");
+        text.write("\x1b]133;D;0\x07\x1b]133;A\x07$ \x1b]133;B\x07cat\n\x1b]133;C\x07c\n");
+        assert_eq!(text.text, vec!["$ ls", "a", "b", "$ cat", "c", ""]);
+        assert_eq!(
+            text.marks()
+                .iter()
+                .map(|m| (m.line, m.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, MarkKind::Prompt),
+                (0, MarkKind::Command),
+                (1, MarkKind::OutputStart),
+                (3, MarkKind::OutputEnd(Some(0))),
+                (3, MarkKind::Prompt),
+                (3, MarkKind::Command),
+                (4, MarkKind::OutputStart),
+            ]
+        );
+        assert_eq!(text.outputs(), vec![1..3, 4..6]);
+        text.add_mark(5, MarkKind::OutputEnd(Some(1)));
+        assert_eq!(text.outputs(), vec![1..3, 4..5]);
+        text.replace(vec![]);
+        assert!(text.outputs().is_empty());
+    }
+
+    #[test]
+    fn test_chrome() {
+        let font = font::Font::new("font", Vector2i::new(8, 16));
+        let mut text = TextBuilder::new()
+            .position(Vector2i::new(100, 200))
+            .size(Vector2i::new(800, 600))
+            .padding(4)
+            .border(2, Color::BLACK)
+            .title("title")
+            .build();
+        text.set_position_size(
+            Vector2i::new(100, 200),
+            Vector2i::new(800, 600),
+            font.char_size,
+        );
+        assert_eq!(text.content_rect(), IntRect::new(6, 22, 788, 572));
+        assert_eq!(text.grid_size(), Vector2i::new(98, 35));
+
+        let size = text.size_for_grid(80, 25, &font);
+        text.set_position_size(Vector2i::new(100, 200), size, font.char_size);
+        assert_eq!(text.grid_size(), Vector2i::new(80, 25));
+
+        text.write("a\nb");
+        assert_eq!(
+            text.cell_at(100 + 6 + 8, 200 + 22 + 16, &font),
+            Vector2i::new(1, 1)
+        );
+
+        text.set_title(None);
+        assert_eq!(text.content_rect().top, 6);
+        assert_eq!(text.grid_size(), Vector2i::new(80, 26));
+        text.set_padding(0);
+        text.set_border(0, Color::BLACK);
+        assert_eq!(text.grid_size(), Vector2i::new(81, 26));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;31mHello\x1b[0m World"), "Hello World");
+        assert_eq!(strip_ansi("\x1b[38;2;1;2;3mrgb"), "rgb");
+    }
+
+    #[test]
+    fn test_highlights() {
+        let mut text = Text::new();
+        text.write("ls *.rs\necho");
+        let green = Color::GREEN;
+        let red = Color::RED;
+        text.set_highlights(vec![
+            Highlight {
+                line: 1,
+                start: 0,
+                end: 4,
+                color: red,
+            },
+            Highlight {
+                line: 0,
+                start: 0,
+                end: 2,
+                color: green,
+            },
+        ]);
+        assert_eq!(text.highlights()[0].color, green);
+        let mut style = text.default_style();
+        let cells = text.line_cells(&text.text[0], &mut style);
+        assert_eq!(text.cell_foreground(0, 1, &cells[1]), green);
+        assert_eq!(text.cell_foreground(0, 2, &cells[2]), text.fg_color);
+        assert_eq!(text.cell_foreground(1, 3, &cells[3]), red);
+        // the text is unchanged
+        assert_eq!(text.get_text(), vec!["ls *.rs", "echo"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut text = Text::default();
+        text.write("error: one\n\x1b[31merror\x1b[0m: two\nwarning\nerrors: 3 error\n");
+        assert_eq!(text.search("error", false).unwrap(), 4);
+        assert_eq!(
+            text.search_matches()[1],
+            SearchMatch {
+                line: 1,
+                start: 0,
+                end: 5
+            }
+        );
+        assert_eq!(text.search_current(), Some(3));
+        assert_eq!(text.search_next(true).unwrap().line, 3);
+        assert_eq!(text.search_current(), Some(2));
+        assert_eq!(text.search_next(false).unwrap().start, 10);
+        assert_eq!(text.search_next(false).unwrap().line, 0);
+
+        assert_eq!(text.search(r"errors?: \d", true).unwrap(), 1);
+        assert_eq!(
+            text.search_matches()[0],
+            SearchMatch {
+                line: 3,
+                start: 0,
+                end: 9
+            }
+        );
+        assert!(text.search("(", true).is_err());
+        assert_eq!(text.search("", false).unwrap(), 0);
+        assert_eq!(text.search_next(false), None);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut text = Text::default();
+        text.write("ls -l /tmp\n\x1b[31mfile1\x1b[0m  file2.txt\nthird line\n");
+        assert_eq!(text.selected_text(), None);
+
+        text.selection_start(Vector2i::new(3, 0), SelectionMode::Char);
+        text.selection_extend(Vector2i::new(4, 1));
+        assert_eq!(text.selected_text().unwrap(), "-l /tmp\nfile1");
+
+        // selecting backwards gives the same result
+        text.selection_start(Vector2i::new(4, 1), SelectionMode::Char);
+        text.selection_extend(Vector2i::new(3, 0));
+        assert_eq!(text.selected_text().unwrap(), "-l /tmp\nfile1");
+
+        text.selection_start(Vector2i::new(9, 1), SelectionMode::Word);
+        assert_eq!(text.selected_text().unwrap(), "file2.txt");
+        text.selection_extend(Vector2i::new(1, 2));
+        assert_eq!(text.selected_text().unwrap(), "file2.txt\nthird");
+
+        text.selection_start(Vector2i::new(2, 1), SelectionMode::Line);
+        text.selection_extend(Vector2i::new(0, 2));
+        assert_eq!(
+            text.selected_text().unwrap(),
+            "file1  file2.txt\nthird line"
+        );
+
+        text.selection_start(Vector2i::new(6, 2), SelectionMode::Block);
+        text.selection_extend(Vector2i::new(7, 0));
+        assert_eq!(text.selected_text().unwrap(), "/t\n f\nli");
+
+        text.selection_clear();
+        assert_eq!(text.selection(), None);
+    }
 }
@@ -0,0 +1,286 @@
+//! CPU rasterizer for `Text`, it produces the same pixels as the `color_bold.frag` shader
+//! without needing a GPU context, which makes it usable in tests
+
+use sfml::graphics::Color;
+
+use crate::{strip_ansi, Cell, Text};
+
+/// an RGBA image with 8 bits per channel, stored row by row
+#[derive(Debug, PartialEq, Clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32, color: Color) -> Self {
+        let pixels = [color.r, color.g, color.b, color.a].repeat((width * height) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * 4) as usize
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let i = self.offset(x, y);
+        let p = &self.pixels[i..i + 4];
+        Color::rgba(p[0], p[1], p[2], p[3])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let i = self.offset(x, y);
+        self.pixels[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+/// the pixels of a font atlas, as they would be sampled by the shader
+pub struct Atlas {
+    image: RgbaImage,
+}
+
+impl Atlas {
+    pub fn new(image: RgbaImage) -> Self {
+        Self { image }
+    }
+
+    /// the pages of the font atlas stacked vertically, like `font::Font::glyph_position`
+    pub fn from_font(font: &font::Font) -> Self {
+        let mut image = RgbaImage {
+            width: font.page_size().x as u32,
+            height: 0,
+            pixels: vec![],
+        };
+        for page in &font.pages {
+            image.height += page.image.size().y;
+            image.pixels.extend_from_slice(page.image.pixel_data());
+        }
+        Self::new(image)
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Color {
+        if x < 0 || y < 0 || x as u32 >= self.image.width || y as u32 >= self.image.height {
+            return Color::TRANSPARENT;
+        }
+        self.image.pixel(x as u32, y as u32)
+    }
+}
+
+fn mix(bg: Color, fg: Color, amount: u8) -> Color {
+    let amount = amount as f32 / 255.0;
+    let channel = |bg: u8, fg: u8| (bg as f32 * (1.0 - amount) + fg as f32 * amount).round() as u8;
+    Color::rgba(
+        channel(bg.r, fg.r),
+        channel(bg.g, fg.g),
+        channel(bg.b, fg.b),
+        channel(bg.a, fg.a),
+    )
+}
+
+/// color of pixel (`x`, `y`) of the glyph at `glyph` in the atlas, see `color_bold.frag`
+fn shade(
+    atlas: &Atlas,
+    glyph: (i32, i32),
+    char_width: i32,
+    (x, y): (i32, i32),
+    fg: Color,
+    bg: Color,
+    bold_offset: i32,
+) -> Color {
+    let texel = atlas.texel(glyph.0 + x, glyph.1 + y);
+    if texel != Color::BLACK {
+        return mix(bg, fg, texel.r);
+    }
+    if bold_offset > 0 && x + bold_offset < char_width {
+        let right = atlas.texel(glyph.0 + x + bold_offset, glyph.1 + y);
+        if right.r > 0 {
+            return mix(bg, fg, right.r);
+        }
+    }
+    bg
+}
+
+impl Text<'_> {
+    /// draw the whole text buffer into an image on the CPU, using the atlas of `font`
+    pub fn rasterize(&self, font: &font::Font) -> RgbaImage {
+        self.rasterize_atlas(font, &Atlas::from_font(font))
+    }
+
+    /// draw the whole text buffer into an image, taking the glyph positions from `font` and
+    /// the glyph pixels from `atlas`
+    ///
+    /// The image is as wide as the longest line and as high as all lines, scrolling and the
+    /// cursor are ignored.
+    pub fn rasterize_atlas(&self, font: &font::Font, atlas: &Atlas) -> RgbaImage {
+        let scale = self.font_scale;
+        let char_size = font.char_size;
+        let columns = self
+            .text
+            .iter()
+            .map(|line| strip_ansi(line).chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        let mut image = RgbaImage::new(
+            (columns * char_size.x * scale) as u32,
+            (self.text.len() as i32 * char_size.y * scale) as u32,
+            self.bg_color,
+        );
+        let bold_offset = font.stroke_width;
+
+        let mut style = self.default_style();
+        for (row, line) in self.text.iter().enumerate() {
+            let highlights = self.line_highlights(row);
+            for (column, cell) in self.line_cells(line, &mut style).iter().enumerate() {
+                let bg = Self::cell_background(&highlights, column, cell);
+                self.rasterize_cell(
+                    &mut image,
+                    font,
+                    atlas,
+                    cell,
+                    bg,
+                    (column as i32, row as i32),
+                    bold_offset,
+                );
+            }
+        }
+        image
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rasterize_cell(
+        &self,
+        image: &mut RgbaImage,
+        font: &font::Font,
+        atlas: &Atlas,
+        cell: &Cell,
+        bg: Color,
+        (column, row): (i32, i32),
+        bold_offset: i32,
+    ) {
+        let scale = self.font_scale;
+        let char_size = font.char_size;
+        let glyph = font.glyph_position(cell.ch as i32);
+        let bold_offset = if cell.style.bold { bold_offset } else { 0 };
+        for y in 0..char_size.y {
+            for x in 0..char_size.x {
+                let color = shade(
+                    atlas,
+                    (glyph.x, glyph.y),
+                    char_size.x,
+                    (x, y),
+                    cell.style.fg,
+                    bg,
+                    bold_offset,
+                );
+                let left = (column * char_size.x + x) * scale;
+                let top = (row * char_size.y + y) * scale;
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        image.set_pixel((left + dx) as u32, (top + dy) as u32, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use sfml::system::Vector2i;
+
+    use super::*;
+
+    fn manifest_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn read_png(path: &Path) -> RgbaImage {
+        let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba, "{}", path.display());
+        assert_eq!(info.bit_depth, png::BitDepth::Eight, "{}", path.display());
+        pixels.truncate(info.buffer_size());
+        RgbaImage {
+            width: info.width,
+            height: info.height,
+            pixels,
+        }
+    }
+
+    fn write_png(path: &Path, image: &RgbaImage) {
+        let file = std::fs::File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, image.width, image.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&image.pixels).unwrap();
+    }
+
+    fn run_script(name: &str) -> String {
+        let script = manifest_dir().join("..").join("scripts").join(name);
+        let output = Command::new("bash").arg(script).output().unwrap();
+        assert!(output.status.success(), "{} failed", name);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// compare the rasterized output of `script` with `golden/<name>.png`
+    /// set UPDATE_GOLDEN=1 to regenerate the golden images
+    fn check_golden(script: &str, name: &str) {
+        let font = font::Font::new("font", Vector2i::new(8, 16));
+        let atlas = Atlas::new(read_png(&manifest_dir().join("../resources/font_8x16.png")));
+        let mut text = Text::new();
+        text.write(&run_script(script));
+        let actual = text.rasterize_atlas(&font, &atlas);
+
+        let golden = manifest_dir().join("golden").join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            write_png(&golden, &actual);
+            return;
+        }
+        assert!(
+            golden.exists(),
+            "{} is missing, set UPDATE_GOLDEN=1 to create it",
+            golden.display()
+        );
+        if read_png(&golden) != actual {
+            let failed = std::env::temp_dir().join(format!("{}.actual.png", name));
+            write_png(&failed, &actual);
+            panic!(
+                "{} differs from {}, the actual output is in {}",
+                script,
+                golden.display(),
+                failed.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_mix() {
+        let bg = Color::rgb(0, 0, 0);
+        let fg = Color::rgb(255, 100, 10);
+        assert_eq!(mix(bg, fg, 0), bg);
+        assert_eq!(mix(bg, fg, 255), fg);
+        assert_eq!(mix(bg, fg, 128), Color::rgb(128, 50, 5));
+    }
+
+    #[test]
+    fn test_golden_colors() {
+        check_golden("test_colors.sh", "test_colors");
+    }
+
+    #[test]
+    fn test_golden_ansi_codes() {
+        check_golden("test_ansi_codes.sh", "test_ansi_codes");
+    }
+}